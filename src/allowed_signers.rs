@@ -0,0 +1,525 @@
+//! allowed_signers
+//!
+//! this module provides parsing of the `allowed_signers` files used by
+//! `ssh-keygen -Y verify` to decide which keys may make signatures for which
+//! identities. each line of the file is a list of principal patterns,
+//! optional options, and a public key. the format is described in the ALLOWED
+//! SIGNERS section of ssh-keygen(1).
+
+use errors::*;
+
+use authorized_keys::dequote;
+use cert::{CertType, Certificate};
+use known_hosts::wildcard_match;
+use lines::{self, Lines};
+use sshsig::SshSig;
+use PublicKey;
+
+use std::io::Read;
+use std::slice;
+
+const CERT_AUTHORITY: &str = "cert-authority";
+const NAMESPACES: &str = "namespaces";
+const VALID_AFTER: &str = "valid-after";
+const VALID_BEFORE: &str = "valid-before";
+
+/// AllowedSigner is a single entry of an allowed_signers file
+#[derive(Clone, Debug)]
+pub struct AllowedSigner {
+    principals: String,
+    cert_authority: bool,
+    namespaces: Option<String>,
+    valid_after: Option<u64>,
+    valid_before: Option<u64>,
+    key: PublicKey,
+}
+
+impl AllowedSigner {
+    /// parse reads an allowed_signers entry from a single line. the
+    /// principals can be quoted if they contain whitespace. like ssh-keygen,
+    /// it first tries to read what follows them as a key, and if that fails
+    /// it reads the options and then the key following them. see
+    /// parse_principals_key_and_options in
+    /// https://github.com/openssh/openssh-portable/blob/master/sshsig.c
+    ///
+    /// ```
+    /// # use openssh_keys::allowed_signers::AllowedSigner;
+    /// let line = r#"*@example.com namespaces="git,file" ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/"#;
+    /// let entry = AllowedSigner::parse(line).unwrap();
+    /// assert_eq!("*@example.com", entry.principals());
+    /// assert_eq!(Some("git,file"), entry.namespaces());
+    /// ```
+    pub fn parse(line: &str) -> Result<Self> {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        let (principals, rest) = if line.starts_with('"') {
+            dequote(line)?
+        } else {
+            let end = line.find([' ', '\t']).ok_or(ErrorKind::InvalidFormat)?;
+            (line[..end].to_string(), &line[end..])
+        };
+        if principals.is_empty() || !(rest.starts_with(' ') || rest.starts_with('\t')) {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        let rest = rest.trim_start();
+
+        let key = PublicKey::parse(rest);
+        let mut entry = AllowedSigner {
+            principals,
+            cert_authority: false,
+            namespaces: None,
+            valid_after: None,
+            valid_before: None,
+            key: match key {
+                Ok(ref key) => key.clone(),
+                Err(_) => PublicKey::parse(skip_options(rest)?.trim_start())?,
+            },
+        };
+        if key.is_ok() {
+            return Ok(entry);
+        }
+
+        // the options are checked here, after skip_options found the key
+        let mut opts = rest;
+        loop {
+            let name_len = opts.find(['=', ',', ' ', '\t']).unwrap_or(opts.len());
+            let name = opts[..name_len].to_ascii_lowercase();
+            opts = &opts[name_len..];
+            if name == CERT_AUTHORITY {
+                entry.cert_authority = true;
+            } else {
+                if !opts.starts_with('=') {
+                    return Err(ErrorKind::InvalidOption(name).into());
+                }
+                let (value, r) = dequote(&opts[1..])?;
+                opts = r;
+                match name.as_str() {
+                    NAMESPACES => entry.namespaces = Some(value),
+                    VALID_AFTER => entry.valid_after = Some(parse_time(&value)?),
+                    VALID_BEFORE => entry.valid_before = Some(parse_time(&value)?),
+                    _ => return Err(ErrorKind::InvalidOption(name).into()),
+                }
+            }
+            match opts.strip_prefix(',') {
+                Some(r) => opts = r,
+                None if opts.starts_with(' ') || opts.starts_with('\t') => break,
+                None => return Err(ErrorKind::InvalidOption(opts.to_string()).into()),
+            }
+        }
+        Ok(entry)
+    }
+
+    /// principals returns the comma-separated list of principal patterns,
+    /// which can have `*` and `?` wildcards and be negated with `!`
+    pub fn principals(&self) -> &str {
+        &self.principals
+    }
+
+    /// cert_authority is true if the key is a certificate authority trusted
+    /// to certify signing keys for these principals, rather than a signing
+    /// key itself
+    pub fn cert_authority(&self) -> bool {
+        self.cert_authority
+    }
+
+    /// namespaces returns the pattern list of namespaces this key may sign
+    /// for, or None if it may sign for any namespace
+    pub fn namespaces(&self) -> Option<&str> {
+        self.namespaces.as_deref()
+    }
+
+    /// valid_after returns the time before which the key isn't valid, in
+    /// seconds since the unix epoch
+    pub fn valid_after(&self) -> Option<u64> {
+        self.valid_after
+    }
+
+    /// valid_before returns the time after which the key isn't valid, in
+    /// seconds since the unix epoch
+    pub fn valid_before(&self) -> Option<u64> {
+        self.valid_before
+    }
+
+    /// key returns the public key of this entry
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// matches_principal checks whether principal matches the principal
+    /// patterns of this entry. a principal matching a negated pattern never
+    /// matches.
+    pub fn matches_principal(&self, principal: &str) -> bool {
+        match_pattern_list(principal, &self.principals)
+    }
+
+    /// is_valid_at checks whether now is inside the validity period of this
+    /// entry
+    pub fn is_valid_at(&self, now: u64) -> bool {
+        self.valid_after.is_none_or(|t| now >= t) && self.valid_before.is_none_or(|t| now <= t)
+    }
+
+    /// allows checks whether key may make signatures for principal in
+    /// namespace at the time now, following check_allowed_keys_line in
+    /// https://github.com/openssh/openssh-portable/blob/master/sshsig.c.
+    /// cert-authority entries never allow a plain key, use
+    /// allows_certificate for signatures made with a certificate.
+    pub fn allows(&self, principal: &str, key: &PublicKey, namespace: &str, now: u64) -> bool {
+        !self.cert_authority && self.key.data() == key.data() && self.allows_use(principal, namespace, now)
+    }
+
+    /// allows_certificate checks whether the key certified by cert may make
+    /// signatures for principal in namespace at the time now. only
+    /// cert-authority entries allow certificates, and only if cert is a user
+    /// certificate signed by the entry's key that is valid for principal at
+    /// the time now, as checked by Certificate::validate.
+    pub fn allows_certificate(&self, principal: &str, cert: &Certificate, namespace: &str, now: u64) -> bool {
+        self.cert_authority
+            && self.allows_use(principal, namespace, now)
+            && cert.validate(CertType::User, principal, slice::from_ref(&self.key), now).is_ok()
+    }
+
+    /// allows_use checks the principal, namespace and validity period of the
+    /// entry, which apply whatever the key is
+    fn allows_use(&self, principal: &str, namespace: &str, now: u64) -> bool {
+        self.matches_principal(principal)
+            && self.namespaces.as_ref().is_none_or(|n| match_pattern_list(namespace, n))
+            && self.is_valid_at(now)
+    }
+}
+
+/// skip_options skips past the options at the start of an entry and
+/// returns the rest of it, without checking the options
+fn skip_options(s: &str) -> Result<&str> {
+    let mut quoted = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ' ' | '\t' if !quoted => return Ok(&s[i..]),
+            _ => {}
+        }
+    }
+    Err(ErrorKind::InvalidFormat.into())
+}
+
+/// match_pattern_list checks whether s matches a comma-separated list of
+/// patterns, the way match_pattern_list in
+/// https://github.com/openssh/openssh-portable/blob/master/match.c does for
+/// principals and namespaces. it's case sensitive, unlike host patterns.
+fn match_pattern_list(s: &str, patterns: &str) -> bool {
+    let mut found = false;
+    for pattern in patterns.split(',') {
+        let (negated, pattern) = match pattern.strip_prefix('!') {
+            Some(p) => (true, p),
+            None => (false, pattern),
+        };
+        if wildcard_match(pattern, s) {
+            if negated {
+                return false;
+            }
+            found = true;
+        }
+    }
+    found
+}
+
+/// parse_time reads a time in the YYYYMMDD[HHMM[SS]][Z] format ssh-keygen
+/// uses, and returns it in seconds since the unix epoch. ssh-keygen reads
+/// times without the Z in the local time zone, but they're read as UTC here.
+fn parse_time(s: &str) -> Result<u64> {
+    let digits = s.strip_suffix('Z').unwrap_or(s);
+    if !(digits.len() == 8 || digits.len() == 12 || digits.len() == 14) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(ErrorKind::InvalidOption(s.to_string()).into());
+    }
+    // all ascii digits, so these can't fail
+    let field = |start: usize, len: usize| -> u64 {
+        digits.get(start..start + len).map_or(0, |f| f.parse().unwrap())
+    };
+    let (year, month, day) = (field(0, 4), field(4, 2), field(6, 2));
+    let (hour, minute, second) = (field(8, 2), field(10, 2), field(12, 2));
+    if year < 1970 || !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) || hour > 23 || minute > 59 || second > 59 {
+        return Err(ErrorKind::InvalidOption(s.to_string()).into());
+    }
+    Ok(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// days_in_month returns the number of days in month of year
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// days_from_civil returns the number of days between 1970-01-01 and the
+/// given date, using the algorithm from
+/// https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Line is a single line of an allowed_signers file
+pub type Line = lines::Line<AllowedSigner>;
+
+impl lines::Entry for AllowedSigner {
+    fn parse(text: &str) -> Result<Self> {
+        AllowedSigner::parse(text)
+    }
+}
+
+/// AllowedSigners is the contents of a whole allowed_signers file
+#[derive(Debug, Default)]
+pub struct AllowedSigners {
+    lines: Lines<AllowedSigner>,
+}
+
+impl AllowedSigners {
+    /// parse reads the contents of an allowed_signers file. this can't fail,
+    /// lines that aren't valid are kept as Line::Invalid and ignored for
+    /// lookups, like ssh-keygen does.
+    pub fn parse(contents: &str) -> Self {
+        AllowedSigners {
+            lines: Lines::parse(contents),
+        }
+    }
+
+    /// read reads an allowed_signers file from r. the contents must be valid
+    /// utf-8.
+    pub fn read<R: Read>(mut r: R) -> Result<Self> {
+        let mut contents = String::new();
+        r.read_to_string(&mut contents)?;
+        Ok(AllowedSigners::parse(&contents))
+    }
+
+    /// lines returns every line of the file in order
+    pub fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter()
+    }
+
+    /// entries returns the successfully parsed entries of the file in order
+    pub fn entries(&self) -> impl Iterator<Item = &AllowedSigner> {
+        self.lines.entries()
+    }
+
+    /// find returns the first entry that allows key to make signatures for
+    /// principal in namespace at the time now
+    pub fn find(&self, principal: &str, key: &PublicKey, namespace: &str, now: u64) -> Option<&AllowedSigner> {
+        self.entries().find(|e| e.allows(principal, key, namespace, now))
+    }
+
+    /// find_certificate returns the first entry that allows the key certified
+    /// by cert to make signatures for principal in namespace at the time now
+    pub fn find_certificate(&self, principal: &str, cert: &Certificate, namespace: &str, now: u64) -> Option<&AllowedSigner> {
+        self.entries().find(|e| e.allows_certificate(principal, cert, namespace, now))
+    }
+
+    /// verify checks signature the way `ssh-keygen -Y verify` does: it has
+    /// to be a valid signature of message for namespace, and its key has to
+    /// be allowed to sign for principal in namespace at the time now. a
+    /// signature made with a certificate has to be allowed by a
+    /// cert-authority entry for its CA instead. it returns the entry that
+    /// allowed the key. a valid signature by a key that isn't allowed gives a
+    /// SignerNotAllowed error.
+    pub fn verify(&self, signature: &SshSig, principal: &str, namespace: &str, message: &[u8], now: u64) -> Result<&AllowedSigner> {
+        signature.verify(namespace, message)?;
+        let entry = match signature.certificate() {
+            Some(cert) => self.find_certificate(principal, cert, namespace, now),
+            None => self.find(principal, signature.key(), namespace, now),
+        };
+        entry.ok_or_else(|| ErrorKind::SignerNotAllowed(principal.to_string()).into())
+    }
+
+    /// find_principals returns the principals of the first entry for key
+    /// that is valid at the time now, like `ssh-keygen -Y find-principals`.
+    /// the principal list is split up the same way ssh-keygen prints it, so
+    /// patterns and negated patterns are returned as they are, and it stops
+    /// at an empty principal. cert-authority entries are skipped.
+    pub fn find_principals(&self, key: &PublicKey, now: u64) -> Vec<&str> {
+        let data = key.data();
+        self.entries()
+            .find(|e| !e.cert_authority && e.key.data() == data && e.is_valid_at(now))
+            .map_or(vec![], |e| e.principals.split(',').take_while(|p| !p.is_empty()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ED25519_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril";
+    const TEST_OTHER_ED25519_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINcpQ9CVied9hMxFn+IBOAosk7sYHeg4a8hU1/2dU6fZ";
+    // the signature of "hello world\n" in the file namespace by
+    // TEST_ED25519_KEY, generated with
+    //   ssh-keygen -Y sign -f fixtures/ed25519 -n file msg
+    const TEST_SIG: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgCEGvr74VBcHySSA4xt3EG7KsfO
+5fkeQSWzCs3oNCg38AAAAEZmlsZQAAAAAAAAAGc2hhNTEyAAAAUwAAAAtzc2gtZWQyNTUx
+OQAAAEA83Q8aQXjNa0/ZM2QKoln8H20XLgX81fni4PDb/CQLzFZ3sYPH+Gr3IPE/wTzgFw
+nxejns8MjcNBZcba5NlM4J
+-----END SSH SIGNATURE-----
+";
+
+    // TEST_ED25519_KEY certified for demos@example.com from 2020 to 2030 by
+    // TEST_CA_KEY, and the signature of "hello world\n" in the file
+    // namespace made with that certificate, generated with
+    //   ssh-keygen -s ca -I demos -n demos@example.com -V 20200101:20300101 ed25519.pub
+    //   ssh-keygen -Y sign -f ed25519-cert.pub -n file msg
+    const TEST_CA_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPX7f3nb/jAR/0/j4rnqzHyZWg1YPDd1SBIUPA4rmETC ca";
+    const TEST_CERT_SIG: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAAcYAAAAgc3NoLWVkMjU1MTktY2VydC12MDFAb3BlbnNzaC5jb20AAA
+AgJOEZq2pqDO6BHyDHMVIInu7XULpF8LLdbTC/2Rk9BZYAAAAgCEGvr74VBcHySSA4xt3E
+G7KsfO5fkeQSWzCs3oNCg38AAAAAAAAAAAAAAAEAAAAFZGVtb3MAAAAVAAAAEWRlbW9zQG
+V4YW1wbGUuY29tAAAAAF4L4QAAAAAAcNvYgAAAAAAAAACCAAAAFXBlcm1pdC1YMTEtZm9y
+d2FyZGluZwAAAAAAAAAXcGVybWl0LWFnZW50LWZvcndhcmRpbmcAAAAAAAAAFnBlcm1pdC
+1wb3J0LWZvcndhcmRpbmcAAAAAAAAACnBlcm1pdC1wdHkAAAAAAAAADnBlcm1pdC11c2Vy
+LXJjAAAAAAAAAAAAAAAzAAAAC3NzaC1lZDI1NTE5AAAAIPX7f3nb/jAR/0/j4rnqzHyZWg
+1YPDd1SBIUPA4rmETCAAAAUwAAAAtzc2gtZWQyNTUxOQAAAECBXz1ZVyNEHEBp01WA4yNj
+F2wOetLzqd6MQOxuyQacMZJmsISRWfZDlpYf/s2/VDllb6vJF9YcAa+UWnBW/awKAAAABG
+ZpbGUAAAAAAAAABnNoYTUxMgAAAFMAAAALc3NoLWVkMjU1MTkAAABAPN0PGkF4zWtP2TNk
+CqJZ/B9tFy4F/NX54uDw2/wkC8xWd7GDx/hq9yDxP8E84BcJ8Xo57PDI3DQWXG2uTZTOCQ
+==
+-----END SSH SIGNATURE-----
+";
+
+    #[test]
+    fn parse_entry() {
+        let entry = AllowedSigner::parse(&format!("demos@example.com {}", TEST_ED25519_KEY)).unwrap();
+        assert_eq!("demos@example.com", entry.principals());
+        assert!(!entry.cert_authority());
+        assert_eq!(None, entry.namespaces());
+        assert_eq!(Some("demos@siril"), entry.key().comment.as_deref());
+
+        let line = format!(
+            r#""demos user,*@example.com"  cert-authority,NAMESPACES="git",valid-after="20200101",valid-before="20300101120000Z" {}"#,
+            TEST_ED25519_KEY
+        );
+        let entry = AllowedSigner::parse(&line).unwrap();
+        assert_eq!("demos user,*@example.com", entry.principals());
+        assert!(entry.cert_authority());
+        assert_eq!(Some("git"), entry.namespaces());
+        assert_eq!(Some(1577836800), entry.valid_after());
+        assert_eq!(Some(1893499200), entry.valid_before());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(AllowedSigner::parse(TEST_ED25519_KEY).is_err());
+        assert!(AllowedSigner::parse("# comment").is_err());
+        assert!(AllowedSigner::parse(&format!("a@b unknown-option {}", TEST_ED25519_KEY)).is_err());
+        assert!(AllowedSigner::parse(&format!("a@b namespaces=git {}", TEST_ED25519_KEY)).is_err());
+        assert!(AllowedSigner::parse(&format!(r#"a@b valid-after="2020" {}"#, TEST_ED25519_KEY)).is_err());
+        assert!(AllowedSigner::parse(&format!(r#"a@b valid-after="20201301" {}"#, TEST_ED25519_KEY)).is_err());
+        assert!(AllowedSigner::parse(r#"a@b namespaces="git""#).is_err());
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(0, parse_time("19700101").unwrap());
+        assert_eq!(951782400, parse_time("20000229").unwrap());
+        assert_eq!(1735689599, parse_time("20241231235959Z").unwrap());
+        assert_eq!(1735689540, parse_time("202412312359").unwrap());
+        assert!(parse_time("20200231").is_err());
+        assert!(parse_time("20210229").is_err());
+        assert!(parse_time("21000229").is_err());
+        assert!(parse_time("20240431").is_err());
+        assert_eq!(parse_time("20240301").unwrap() - 86400, parse_time("20240229").unwrap());
+    }
+
+    #[test]
+    fn patterns() {
+        assert!(match_pattern_list("demos@example.com", "*@example.com"));
+        assert!(match_pattern_list("git", "file,git"));
+        assert!(!match_pattern_list("root@example.com", "!root@*,*@example.com"));
+        assert!(!match_pattern_list("Demos@example.com", "demos@example.com"));
+        assert!(!match_pattern_list("gitx", "git"));
+    }
+
+    #[test]
+    fn lookup() {
+        let contents = format!(
+            "# allowed signers\n\n*@example.com,!root@example.com namespaces=\"git\" {key}\nops@example.com valid-before=\"20250101\" {key}\nnot an entry\nops@example.com cert-authority {other}\n",
+            key = TEST_ED25519_KEY,
+            other = TEST_OTHER_ED25519_KEY,
+        );
+        let signers = AllowedSigners::parse(&contents);
+        assert_eq!(6, signers.lines().count());
+        assert_eq!(3, signers.entries().count());
+        assert!(signers.lines().any(|l| match *l {
+            Line::Invalid(ref text, _) => text == "not an entry",
+            _ => false,
+        }));
+
+        let key = PublicKey::parse(TEST_ED25519_KEY).unwrap();
+        let other = PublicKey::parse(TEST_OTHER_ED25519_KEY).unwrap();
+        let now = 1700000000;
+        assert!(signers.find("demos@example.com", &key, "git", now).is_some());
+        assert!(signers.find("demos@example.com", &key, "file", now).is_none());
+        assert!(signers.find("root@example.com", &key, "git", now).is_none());
+        assert!(signers.find("demos@example.com", &other, "git", now).is_none());
+        // the second entry allows any namespace until it expires
+        assert!(signers.find("ops@example.com", &key, "file", now).is_some());
+        assert!(signers.find("ops@example.com", &key, "file", 1735689600).is_some());
+        assert!(signers.find("ops@example.com", &key, "file", 1735689601).is_none());
+        // cert-authority keys don't sign themselves
+        assert!(signers.find("ops@example.com", &other, "file", now).is_none());
+
+        // only the first entry for the key counts, like ssh-keygen
+        assert_eq!(vec!["*@example.com", "!root@example.com"], signers.find_principals(&key, now));
+        assert!(signers.find_principals(&other, now).is_empty());
+
+        let signers = AllowedSigners::parse(&format!(
+            "ops@example.com valid-before=\"20200101\" {key}\ndemos@example.com,,root@example.com {key}\n",
+            key = TEST_ED25519_KEY,
+        ));
+        assert_eq!(vec!["demos@example.com"], signers.find_principals(&key, now));
+        assert_eq!(vec!["ops@example.com"], signers.find_principals(&key, 0));
+    }
+
+    #[test]
+    fn verify() {
+        let signers = AllowedSigners::parse(&format!("demos@example.com namespaces=\"file\" {}\n", TEST_ED25519_KEY));
+        let sig = SshSig::parse(TEST_SIG).unwrap();
+        let entry = signers.verify(&sig, "demos@example.com", "file", b"hello world\n", 0).unwrap();
+        assert_eq!("demos@example.com", entry.principals());
+        match signers.verify(&sig, "root@example.com", "file", b"hello world\n", 0) {
+            Err(Error(ErrorKind::SignerNotAllowed(ref p), _)) if p == "root@example.com" => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(signers.verify(&sig, "demos@example.com", "file", b"goodbye world\n", 0).is_err());
+        assert!(signers.verify(&sig, "demos@example.com", "git", b"hello world\n", 0).is_err());
+    }
+
+    #[test]
+    fn verify_certificate() {
+        let sig = SshSig::parse(TEST_CERT_SIG).unwrap();
+        let now = 1700000000;
+        let signers = AllowedSigners::parse(&format!(
+            "*@example.com namespaces=\"git\",cert-authority {ca}\n*@example.com cert-authority {ca}\n",
+            ca = TEST_CA_KEY,
+        ));
+        let entry = signers.verify(&sig, "demos@example.com", "file", b"hello world\n", now).unwrap();
+        assert_eq!(None, entry.namespaces());
+        // the principal has to be in the certificate as well as the entry
+        assert!(signers.verify(&sig, "ops@example.com", "file", b"hello world\n", now).is_err());
+        // the certificate expires in 2030
+        assert!(signers.verify(&sig, "demos@example.com", "file", b"hello world\n", 1900000000).is_err());
+
+        // the certified key itself doesn't allow certificate signatures, and
+        // the CA key doesn't sign without cert-authority
+        for line in &[TEST_ED25519_KEY, TEST_CA_KEY] {
+            let signers = AllowedSigners::parse(&format!("demos@example.com {}\n", line));
+            match signers.verify(&sig, "demos@example.com", "file", b"hello world\n", now) {
+                Err(Error(ErrorKind::SignerNotAllowed(_), _)) => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+}
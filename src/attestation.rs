@@ -0,0 +1,446 @@
+//! attestation
+//!
+//! this module provides verification of the attestation that a FIDO
+//! authenticator makes when a security key is enrolled, which
+//! `ssh-keygen -t ecdsa-sk -O write-attestation=path` saves. the attestation
+//! is signed with a key certified by the vendor of the authenticator, so
+//! checking it against the vendor's root certificates shows which kind of
+//! authenticator holds the key. the format is described in
+//! https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.u2f, and
+//! the signed data in https://www.w3.org/TR/webauthn-2/#sctn-attestation.
+
+use errors::*;
+
+use ec;
+use pem;
+use reader::Reader;
+use signature::hash;
+use x509::{self, Certificate};
+use {Curve, Data, PublicKey};
+
+use crypto::sha2::Sha256;
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+const ATTESTATION_V01: &str = "ssh-sk-attest-v01";
+const CERTIFICATE_LABEL: &str = "CERTIFICATE";
+// the attested credential data flag of the authenticator data
+const ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+// the COSE key parameters from https://tools.ietf.org/html/rfc8152#section-13
+// that describe the keys of security keys
+const COSE_KTY: i64 = 1;
+const COSE_ALG: i64 = 3;
+const COSE_CRV: i64 = -1;
+const COSE_X: i64 = -2;
+const COSE_Y: i64 = -3;
+const COSE_KTY_OKP: i64 = 1;
+const COSE_KTY_EC2: i64 = 2;
+const COSE_ALG_ES256: i64 = -7;
+const COSE_ALG_EDDSA: i64 = -8;
+const COSE_CRV_P256: i64 = 1;
+const COSE_CRV_ED25519: i64 = 6;
+
+/// Attestation is the attestation of a security key from its enrollment
+#[derive(Clone, Debug)]
+pub struct Attestation {
+    certificate: Vec<u8>,
+    signature: Vec<u8>,
+    auth_data: Vec<u8>,
+    aaguid: Vec<u8>,
+    credential_id: Vec<u8>,
+    credential_key: Vec<u8>,
+}
+
+impl Attestation {
+    /// from_blob reads an `ssh-sk-attest-v01` attestation, which holds the
+    /// attestation certificate, the signature, and the authenticator data
+    /// with the new credential. this is what ssh-keygen writes out.
+    pub fn from_blob(blob: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(blob);
+        if reader.read_string()? != ATTESTATION_V01 {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        let certificate = reader.read_bytes()?;
+        let signature = reader.read_bytes()?;
+        let (auth_data, rest) = cbor_bytes(reader.read_bytes()?)?;
+        // reserved flags and string
+        reader.read_int()?;
+        reader.read_bytes()?;
+        if !rest.is_empty() || !reader.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+
+        // the authenticator data is the hash of the application, the flags,
+        // the counter, and then the credential: the aaguid of the
+        // authenticator model, the credential id, and the key.
+        if auth_data.len() < 55 || auth_data[32] & ATTESTED_CREDENTIAL_DATA == 0 {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        let id_len = (auth_data[53] as usize) << 8 | auth_data[54] as usize;
+        if auth_data.len() < 55 + id_len {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        Ok(Attestation {
+            certificate: certificate.into(),
+            signature: signature.into(),
+            auth_data: auth_data.into(),
+            aaguid: auth_data[37..53].into(),
+            credential_id: auth_data[55..55 + id_len].into(),
+            credential_key: auth_data[55 + id_len..].into(),
+        })
+    }
+
+    /// read reads an attestation file written by ssh-keygen from r
+    pub fn read<R: Read>(mut r: R) -> Result<Self> {
+        let mut blob = vec![];
+        r.read_to_end(&mut blob)?;
+        Attestation::from_blob(&blob)
+    }
+
+    /// certificate returns the DER encoded attestation certificate
+    pub fn certificate(&self) -> &[u8] {
+        &self.certificate
+    }
+
+    /// aaguid returns the 16 byte identifier of the authenticator model. it
+    /// is all zeros for authenticators that only speak U2F.
+    pub fn aaguid(&self) -> &[u8] {
+        &self.aaguid
+    }
+
+    /// verify checks that this is the attestation of key, made in response to
+    /// challenge, and signed with an attestation certificate issued by one of
+    /// roots. challenge is the data given to `ssh-keygen -O challenge=path`,
+    /// which enrollment has to use since there's no other way to know it. a
+    /// key that doesn't match the attested credential gives an
+    /// AttestationMismatch error, a bad signature an InvalidSignature error,
+    /// and a certificate that none of the roots issued an
+    /// UntrustedAttestation error. the validity periods of the certificates
+    /// aren't checked.
+    pub fn verify(&self, key: &PublicKey, challenge: &[u8], roots: &AttestationRoots) -> Result<()> {
+        let application = match key.data {
+            Data::SkEd25519 { ref application, .. } | Data::SkEcdsa { ref application, .. } => application,
+            _ => return Err(ErrorKind::UnsupportedKeytype(key.keytype().into()).into()),
+        };
+        let application_hash = hash(Sha256::new(), application.as_bytes());
+        if self.auth_data[..32] != application_hash[..] || !cose_key_matches(&self.credential_key, key)? {
+            return Err(ErrorKind::AttestationMismatch.into());
+        }
+
+        let certificate = Certificate::from_der(&self.certificate)?;
+        let algorithm = match certificate.key().data {
+            Data::Rsa { .. } => "rsa-sha2-256",
+            Data::Ecdsa { .. } | Data::Ed25519 { .. } => certificate.key().keytype(),
+            _ => return Err(ErrorKind::UnsupportedKeytype(certificate.key().keytype().into()).into()),
+        };
+        // authenticators that support FIDO2 use the packed format, which
+        // signs the authenticator data and the hash of the challenge. U2F
+        // ones use the fido-u2f format, which signs the parts of it that
+        // U2F has.
+        let client_data_hash = hash(Sha256::new(), challenge);
+        let mut packed = self.auth_data.clone();
+        packed.extend_from_slice(&client_data_hash);
+        let valid = x509::verify(certificate.key(), algorithm, &packed, &self.signature).is_ok() || match key.data {
+            Data::SkEcdsa { key: ref point, .. } => {
+                let mut u2f = vec![0];
+                u2f.extend_from_slice(&application_hash);
+                u2f.extend_from_slice(&client_data_hash);
+                u2f.extend_from_slice(&self.credential_id);
+                u2f.append(&mut ec::decode_point(&Curve::Nistp256, point)?);
+                x509::verify(certificate.key(), algorithm, &u2f, &self.signature).is_ok()
+            },
+            _ => false,
+        };
+        if !valid {
+            return Err(ErrorKind::InvalidSignature.into());
+        }
+
+        if !roots.roots.iter().any(|root| root.is_ca() && certificate.is_signed_by(root)) {
+            return Err(ErrorKind::UntrustedAttestation.into());
+        }
+        Ok(())
+    }
+}
+
+/// AttestationRoots is the set of vendor certificates that attestation
+/// certificates are checked against. no chain is built: the attestation
+/// certificate has to be issued directly by one of them, and only CA
+/// certificates, with basicConstraints cA set, are trusted to issue it. an
+/// intermediate CA certificate that is added is trusted on its own, without
+/// checking what issued it.
+#[derive(Clone, Debug, Default)]
+pub struct AttestationRoots {
+    roots: Vec<Certificate>,
+}
+
+impl AttestationRoots {
+    /// new creates an empty set of roots
+    pub fn new() -> Self {
+        AttestationRoots::default()
+    }
+
+    /// add_der adds a DER encoded certificate
+    pub fn add_der(&mut self, der: &[u8]) -> Result<()> {
+        self.roots.push(Certificate::from_der(der)?);
+        Ok(())
+    }
+
+    /// add_pem adds every certificate in pem, which can hold several
+    /// `-----BEGIN CERTIFICATE-----` blocks. text without any certificates
+    /// gives an InvalidFormat error.
+    pub fn add_pem(&mut self, pem: &str) -> Result<()> {
+        let certificates: Vec<_> = pem::parse_all(pem)?.into_iter().filter(|p| p.label == CERTIFICATE_LABEL).collect();
+        if certificates.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        for certificate in certificates {
+            self.add_der(&certificate.data)?;
+        }
+        Ok(())
+    }
+
+    /// load adds the certificates in the file at path, which can be PEM or
+    /// DER encoded
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let contents = fs::read(path)?;
+        match ::std::str::from_utf8(&contents) {
+            Ok(text) if text.contains("-----BEGIN ") => self.add_pem(text),
+            _ => self.add_der(&contents),
+        }
+    }
+
+    /// len returns the number of certificates in the set
+    pub fn len(&self) -> usize {
+        self.roots.len()
+    }
+
+    /// is_empty returns whether there are no certificates in the set
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+}
+
+/// cose_key_matches returns whether the CBOR encoded COSE key of the attested
+/// credential is the same as key
+fn cose_key_matches(cose: &[u8], key: &PublicKey) -> Result<bool> {
+    let params = cose_key(cose)?;
+    let param = |label| params.iter().find(|p| p.0 == label).map(|p| p.1);
+    Ok(match key.data {
+        Data::SkEcdsa { key: ref point, .. } => {
+            let point = ec::decode_point(&Curve::Nistp256, point)?;
+            param(COSE_KTY) == Some(CoseValue::Int(COSE_KTY_EC2))
+                && param(COSE_ALG) == Some(CoseValue::Int(COSE_ALG_ES256))
+                && param(COSE_CRV) == Some(CoseValue::Int(COSE_CRV_P256))
+                && param(COSE_X) == Some(CoseValue::Bytes(&point[1..33]))
+                && param(COSE_Y) == Some(CoseValue::Bytes(&point[33..]))
+        },
+        Data::SkEd25519 { ref key, .. } => {
+            param(COSE_KTY) == Some(CoseValue::Int(COSE_KTY_OKP))
+                && param(COSE_ALG) == Some(CoseValue::Int(COSE_ALG_EDDSA))
+                && param(COSE_CRV) == Some(CoseValue::Int(COSE_CRV_ED25519))
+                && param(COSE_X) == Some(CoseValue::Bytes(key))
+        },
+        _ => false,
+    })
+}
+
+/// CoseValue is the value of a COSE key parameter. the keys of security keys
+/// only have integers and byte strings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CoseValue<'a> {
+    Int(i64),
+    Bytes(&'a [u8]),
+}
+
+/// cose_key reads a COSE key, which is a CBOR map from integer labels to the
+/// values of the parameters. anything after the key, like extensions, is
+/// ignored.
+fn cose_key(data: &[u8]) -> Result<Vec<(i64, CoseValue<'_>)>> {
+    let (major, len, mut rest) = cbor_head(data)?;
+    if major != CBOR_MAP {
+        return Err(ErrorKind::InvalidFormat.into());
+    }
+    let mut params = vec![];
+    for _ in 0..len {
+        let (label, r) = cbor_int(rest)?;
+        let (value, r) = if cbor_head(r)?.0 == CBOR_BYTES {
+            let (bytes, r) = cbor_bytes(r)?;
+            (CoseValue::Bytes(bytes), r)
+        } else {
+            let (int, r) = cbor_int(r)?;
+            (CoseValue::Int(int), r)
+        };
+        params.push((label, value));
+        rest = r;
+    }
+    Ok(params)
+}
+
+// the CBOR major types from https://tools.ietf.org/html/rfc8949#section-3.1
+const CBOR_UINT: u8 = 0;
+const CBOR_NEGINT: u8 = 1;
+const CBOR_BYTES: u8 = 2;
+const CBOR_MAP: u8 = 5;
+
+/// cbor_head reads the head of a CBOR data item, returning its major type,
+/// its argument, and the rest of data. indefinite lengths aren't supported,
+/// since authenticators don't use them.
+fn cbor_head(data: &[u8]) -> Result<(u8, u64, &[u8])> {
+    let (&first, rest) = data.split_first().ok_or(ErrorKind::InvalidFormat)?;
+    let len = match first & 0x1f {
+        n @ 0..=23 => return Ok((first >> 5, u64::from(n), rest)),
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => return Err(ErrorKind::InvalidFormat.into()),
+    };
+    if rest.len() < len {
+        return Err(ErrorKind::InvalidFormat.into());
+    }
+    let arg = rest[..len].iter().fold(0, |arg, &b| (arg << 8) | u64::from(b));
+    Ok((first >> 5, arg, &rest[len..]))
+}
+
+/// cbor_int reads an integer, returning it and the rest of data
+fn cbor_int(data: &[u8]) -> Result<(i64, &[u8])> {
+    match cbor_head(data)? {
+        (CBOR_UINT, n, rest) if n <= i64::MAX as u64 => Ok((n as i64, rest)),
+        (CBOR_NEGINT, n, rest) if n <= i64::MAX as u64 => Ok((-1 - n as i64, rest)),
+        _ => Err(ErrorKind::InvalidFormat.into()),
+    }
+}
+
+/// cbor_bytes reads a byte string, returning it and the rest of data
+fn cbor_bytes(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    match cbor_head(data)? {
+        (CBOR_BYTES, len, rest) if len <= rest.len() as u64 => Ok(rest.split_at(len as usize)),
+        _ => Err(ErrorKind::InvalidFormat.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // there are no authenticators to enroll with here, so these attestations
+    // were made with a test root in fixtures/attestation-root.pem and an
+    // attestation certificate it issued, for the security keys with the key
+    // material of fixtures/ecdsa and fixtures/ed25519. libfido2's
+    // fido_cred_verify accepts all of them.
+    const CHALLENGE: &[u8] = b"demos enrollment challenge\n";
+    const TEST_SK_ECDSA_KEY: &str = "sk-ecdsa-sha2-nistp256@openssh.com AAAAInNrLWVjZHNhLXNoYTItbmlzdHAyNTZAb3BlbnNzaC5jb20AAAAIbmlzdHAyNTYAAABBBIhfLQrww4DlhYzbSWXoX3ctOQ0jVosvfHfW+QWVotksbPzM2YgkIikTpoHUfZrYpJKWx7WYs5aqeLkdCDdk+jkAAAAEc3NoOg==";
+    const TEST_SK_ED25519_KEY: &str = "sk-ssh-ed25519@openssh.com AAAAGnNrLXNzaC1lZDI1NTE5QG9wZW5zc2guY29tAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAABHNzaDo=";
+
+    fn roots() -> AttestationRoots {
+        let mut roots = AttestationRoots::new();
+        roots.add_pem(include_str!("../fixtures/attestation-root.pem")).unwrap();
+        roots
+    }
+
+    #[test]
+    fn verify() {
+        let cases = [
+            (TEST_SK_ECDSA_KEY, &include_bytes!("../fixtures/ecdsa-sk.attestation")[..]),
+            (TEST_SK_ECDSA_KEY, &include_bytes!("../fixtures/ecdsa-sk-u2f.attestation")[..]),
+            (TEST_SK_ED25519_KEY, &include_bytes!("../fixtures/ed25519-sk.attestation")[..]),
+        ];
+        for &(key, blob) in &cases {
+            let key = PublicKey::parse(key).unwrap();
+            let attestation = Attestation::read(blob).unwrap();
+            let aaguid: Vec<u8> = (1..17).collect();
+            assert_eq!(&aaguid[..], attestation.aaguid());
+            attestation.verify(&key, CHALLENGE, &roots()).unwrap();
+
+            match attestation.verify(&key, b"another challenge", &roots()) {
+                Err(Error(ErrorKind::InvalidSignature, _)) => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+            match attestation.verify(&key, CHALLENGE, &AttestationRoots::new()) {
+                Err(Error(ErrorKind::UntrustedAttestation, _)) => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+            let mut other = AttestationRoots::new();
+            other.add_pem(include_str!("../fixtures/attestation-other-root.pem")).unwrap();
+            match attestation.verify(&key, CHALLENGE, &other) {
+                Err(Error(ErrorKind::UntrustedAttestation, _)) => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    // the ecdsa-sk attestation again, but with an attestation certificate
+    // issued by fixtures/attestation-issuer.pem. the certificate in
+    // fixtures/attestation-issuer-not-ca.pem has the same name and key but
+    // isn't a CA, which openssl verify rejects as well.
+    #[test]
+    fn issuer_must_be_ca() {
+        let key = PublicKey::parse(TEST_SK_ECDSA_KEY).unwrap();
+        let attestation = Attestation::from_blob(include_bytes!("../fixtures/ecdsa-sk-issuer.attestation")).unwrap();
+        let mut roots = AttestationRoots::new();
+        roots.add_pem(include_str!("../fixtures/attestation-issuer.pem")).unwrap();
+        attestation.verify(&key, CHALLENGE, &roots).unwrap();
+
+        let mut roots = AttestationRoots::new();
+        roots.add_pem(include_str!("../fixtures/attestation-issuer-not-ca.pem")).unwrap();
+        match attestation.verify(&key, CHALLENGE, &roots) {
+            Err(Error(ErrorKind::UntrustedAttestation, _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn wrong_key() {
+        let attestation = Attestation::from_blob(include_bytes!("../fixtures/ecdsa-sk.attestation")).unwrap();
+        // the ed25519 key, and the ecdsa key for another application
+        let keys = [
+            TEST_SK_ED25519_KEY.to_string(),
+            TEST_SK_ECDSA_KEY.replace("AAAAEc3NoOg==", "AAAAFc3NoOmE="),
+        ];
+        for key in &keys {
+            match attestation.verify(&PublicKey::parse(key).unwrap(), CHALLENGE, &roots()) {
+                Err(Error(ErrorKind::AttestationMismatch, _)) => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+        let key = PublicKey::parse(include_str!("../fixtures/ecdsa.pub")).unwrap();
+        match attestation.verify(&key, CHALLENGE, &roots()) {
+            Err(Error(ErrorKind::UnsupportedKeytype(_), _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn roots_files() {
+        let der = pem::parse(include_str!("../fixtures/attestation-root.pem")).unwrap().data;
+        let mut roots = AttestationRoots::new();
+        roots.add_der(&der).unwrap();
+        let bundle = format!("{}{}", include_str!("../fixtures/attestation-other-root.pem"), include_str!("../fixtures/attestation-root.pem"));
+        roots.add_pem(&bundle).unwrap();
+        roots.load(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/attestation-root.pem")).unwrap();
+        assert_eq!(4, roots.len());
+        assert!(roots.add_pem(include_str!("../fixtures/ecdsa.pub")).is_err());
+        assert!(roots.add_der(&der[1..]).is_err());
+
+        let attestation = Attestation::from_blob(include_bytes!("../fixtures/ecdsa-sk.attestation")).unwrap();
+        let key = PublicKey::parse(TEST_SK_ECDSA_KEY).unwrap();
+        let mut roots = AttestationRoots::new();
+        roots.add_pem(include_str!("../fixtures/attestation-other-root.pem")).unwrap();
+        roots.add_der(&der).unwrap();
+        attestation.verify(&key, CHALLENGE, &roots).unwrap();
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let blob = include_bytes!("../fixtures/ecdsa-sk.attestation");
+        assert!(Attestation::from_blob(&blob[..blob.len() - 1]).is_err());
+        let mut bad = blob.to_vec();
+        bad[16] = b'0';
+        assert!(Attestation::from_blob(&bad).is_err());
+        assert!(cose_key(&[0xa1, 0x01]).is_err());
+        assert_eq!(vec![(1, CoseValue::Int(2)), (-2, CoseValue::Bytes(&[7]))], cose_key(&[0xa2, 0x01, 0x02, 0x21, 0x41, 0x07]).unwrap());
+    }
+}
@@ -0,0 +1,539 @@
+//! authorized_keys
+//!
+//! this module provides parsing and printing of the lines found in an
+//! `~/.ssh/authorized_keys` file. each line is a public key optionally preceded
+//! by a comma-separated list of options that sshd applies to logins using that
+//! key. the format is described in the AUTHORIZED_KEYS FILE FORMAT section of
+//! sshd(8).
+
+use errors::*;
+
+use lines::{self, Lines};
+use {PublicKey, SkSignature};
+
+use std::fmt;
+use std::io::{Read, Write};
+
+/// KeyOption is a single option from the options section of an
+/// authorized_keys line. options which take a value keep it exactly as sshd
+/// would see it after removing the surrounding quotes and escapes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyOption {
+    AgentForwarding,
+    CertAuthority,
+    NoAgentForwarding,
+    NoPortForwarding,
+    NoPty,
+    NoTouchRequired,
+    NoUserRc,
+    NoX11Forwarding,
+    PortForwarding,
+    Pty,
+    Restrict,
+    UserRc,
+    VerifyRequired,
+    X11Forwarding,
+    Command(String),
+    /// environment="NAME=value", split into the name and the value.
+    Environment(String, String),
+    ExpiryTime(String),
+    From(String),
+    PermitListen(String),
+    PermitOpen(String),
+    Principals(String),
+    Tunnel(String),
+}
+
+// the option names as they are written by sshd. matching against them is done
+// case-insensitively, the same way sshd does it.
+const FLAGS: &[(&str, KeyOption)] = &[
+    ("agent-forwarding", KeyOption::AgentForwarding),
+    ("cert-authority", KeyOption::CertAuthority),
+    ("no-agent-forwarding", KeyOption::NoAgentForwarding),
+    ("no-port-forwarding", KeyOption::NoPortForwarding),
+    ("no-pty", KeyOption::NoPty),
+    ("no-touch-required", KeyOption::NoTouchRequired),
+    ("no-user-rc", KeyOption::NoUserRc),
+    ("no-X11-forwarding", KeyOption::NoX11Forwarding),
+    ("port-forwarding", KeyOption::PortForwarding),
+    ("pty", KeyOption::Pty),
+    ("restrict", KeyOption::Restrict),
+    ("user-rc", KeyOption::UserRc),
+    ("verify-required", KeyOption::VerifyRequired),
+    ("X11-forwarding", KeyOption::X11Forwarding),
+];
+
+const COMMAND: &str = "command";
+const ENVIRONMENT: &str = "environment";
+const EXPIRY_TIME: &str = "expiry-time";
+const FROM: &str = "from";
+const PERMITLISTEN: &str = "permitlisten";
+const PERMITOPEN: &str = "permitopen";
+const PRINCIPALS: &str = "principals";
+const TUNNEL: &str = "tunnel";
+
+impl KeyOption {
+    /// name returns the name of the option as written in an authorized_keys
+    /// file, without any value.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            KeyOption::Command(..) => COMMAND,
+            KeyOption::Environment(..) => ENVIRONMENT,
+            KeyOption::ExpiryTime(..) => EXPIRY_TIME,
+            KeyOption::From(..) => FROM,
+            KeyOption::PermitListen(..) => PERMITLISTEN,
+            KeyOption::PermitOpen(..) => PERMITOPEN,
+            KeyOption::Principals(..) => PRINCIPALS,
+            KeyOption::Tunnel(..) => TUNNEL,
+            ref flag => FLAGS
+                .iter()
+                .find(|&(_, f)| f == flag)
+                .map(|&(name, _)| name)
+                .unwrap(),
+        }
+    }
+
+    /// value returns the value of the option, or None if the option is a
+    /// flag. for environment options this is the full NAME=value string.
+    pub fn value(&self) -> Option<String> {
+        match *self {
+            KeyOption::Command(ref v)
+            | KeyOption::ExpiryTime(ref v)
+            | KeyOption::From(ref v)
+            | KeyOption::PermitListen(ref v)
+            | KeyOption::PermitOpen(ref v)
+            | KeyOption::Principals(ref v)
+            | KeyOption::Tunnel(ref v) => Some(v.clone()),
+            KeyOption::Environment(ref name, ref value) => Some(format!("{}={}", name, value)),
+            _ => None,
+        }
+    }
+
+    /// parse reads a single option from the start of opts, returning the
+    /// option and the rest of the string following it. this follows
+    /// sshauthopt_parse in
+    /// https://github.com/openssh/openssh-portable/blob/master/auth-options.c
+    fn parse(opts: &str) -> Result<(Self, &str)> {
+        let name_len = opts
+            .find(['=', ',', ' ', '\t'])
+            .unwrap_or(opts.len());
+        let name = &opts[..name_len];
+        let rest = &opts[name_len..];
+
+        if !rest.starts_with('=') {
+            return FLAGS
+                .iter()
+                .find(|(flag, _)| flag.eq_ignore_ascii_case(name))
+                .map(|(_, flag)| (flag.clone(), rest))
+                .ok_or_else(|| ErrorKind::InvalidOption(name.to_string()).into());
+        }
+
+        let (value, rest) = dequote(&rest[1..])?;
+        let option = match name.to_ascii_lowercase().as_str() {
+            COMMAND => KeyOption::Command(value),
+            ENVIRONMENT => {
+                // sshd requires the value to have a name before the '='
+                let eq = match value.find('=') {
+                    Some(eq) if eq > 0 => eq,
+                    _ => return Err(ErrorKind::InvalidOption(value).into()),
+                };
+                let (name, value) = value.split_at(eq);
+                KeyOption::Environment(name.to_string(), value[1..].to_string())
+            }
+            EXPIRY_TIME => KeyOption::ExpiryTime(value),
+            FROM => KeyOption::From(value),
+            PERMITLISTEN => KeyOption::PermitListen(value),
+            PERMITOPEN => KeyOption::PermitOpen(value),
+            PRINCIPALS => KeyOption::Principals(value),
+            TUNNEL => KeyOption::Tunnel(value),
+            _ => return Err(ErrorKind::InvalidOption(name.to_string()).into()),
+        };
+        Ok((option, rest))
+    }
+}
+
+impl fmt::Display for KeyOption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value() {
+            Some(value) => write!(f, "{}=\"{}\"", self.name(), value.replace('"', "\\\"")),
+            None => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+/// dequote reads a double-quoted value from the start of s, returning the
+/// unescaped value and the rest of the string after the closing quote. the
+/// only escape sshd recognizes is \", every other backslash is kept verbatim.
+/// see opt_dequote in
+/// https://github.com/openssh/openssh-portable/blob/master/auth-options.c
+pub(crate) fn dequote(s: &str) -> Result<(String, &str)> {
+    if !s.starts_with('"') {
+        return Err(ErrorKind::InvalidOption(s.to_string()).into());
+    }
+    let mut value = String::new();
+    let mut chars = s.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Ok((value, &s[i + 1..])),
+            '\\' if chars.peek().map(|&(_, c)| c) == Some('"') => {
+                chars.next();
+                value.push('"');
+            }
+            c => value.push(c),
+        }
+    }
+    // we ran out of input before finding the closing quote
+    Err(ErrorKind::InvalidOption(s.to_string()).into())
+}
+
+/// AuthorizedKey is a single entry of an authorized_keys file: a public key
+/// along with the options that apply to it, in the order they were written.
+#[derive(Clone, Debug)]
+pub struct AuthorizedKey {
+    options: Vec<KeyOption>,
+    key: PublicKey,
+    // the key as it was written. PublicKey only keeps the first word of the
+    // comment, so this is what gets printed after the options.
+    key_text: String,
+}
+
+impl fmt::Display for AuthorizedKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.options.is_empty() {
+            let options: Vec<String> = self.options.iter().map(|o| o.to_string()).collect();
+            write!(f, "{} ", options.join(","))?;
+        }
+        write!(f, "{}", self.key_text)
+    }
+}
+
+impl AuthorizedKey {
+    /// new creates an authorized_keys entry for a key with no options
+    pub fn new(key: PublicKey) -> Self {
+        AuthorizedKey {
+            options: vec![],
+            key_text: key.to_key_file().trim_end().to_string(),
+            key,
+        }
+    }
+
+    /// parse reads an authorized_keys entry from a single line. like sshd, it
+    /// first tries to read the line as a bare public key, and if that fails it
+    /// reads the options up to the first unquoted whitespace and then the key
+    /// following them.
+    ///
+    /// ```
+    /// # use openssh_keys::authorized_keys::{AuthorizedKey, KeyOption};
+    /// let line = r#"command="echo \"hi, there\"",no-pty ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril"#;
+    /// let entry = AuthorizedKey::parse(line).unwrap();
+    /// assert_eq!(entry.options(), &[KeyOption::Command("echo \"hi, there\"".into()), KeyOption::NoPty]);
+    /// assert_eq!(line, entry.to_string());
+    /// ```
+    pub fn parse(line: &str) -> Result<Self> {
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+
+        let key_err = match PublicKey::parse(line) {
+            Ok(key) => {
+                return Ok(AuthorizedKey {
+                    options: vec![],
+                    key,
+                    key_text: line.trim_end().to_string(),
+                })
+            }
+            Err(e) => e,
+        };
+
+        let mut options = vec![];
+        let mut rest = line;
+        loop {
+            let (option, r) = match KeyOption::parse(rest) {
+                Ok(o) => o,
+                // if the very first thing on the line isn't an option then
+                // the line was most likely meant to be a bare key, so report
+                // whatever went wrong parsing that.
+                Err(_) if options.is_empty() => return Err(key_err),
+                Err(e) => return Err(e),
+            };
+            options.push(option);
+            if let Some(r) = r.strip_prefix(',') {
+                rest = r;
+            } else if r.starts_with(' ') || r.starts_with('\t') {
+                rest = r;
+                break;
+            } else {
+                return Err(ErrorKind::InvalidOption(r.to_string()).into());
+            }
+        }
+
+        let key_text = rest.trim().to_string();
+        let key = PublicKey::parse(&key_text)?;
+        Ok(AuthorizedKey { options, key, key_text })
+    }
+
+    /// key returns the public key of this entry
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// options returns the options of this entry in the order they appear
+    pub fn options(&self) -> &[KeyOption] {
+        &self.options
+    }
+
+    /// options_mut returns the options of this entry for modification
+    pub fn options_mut(&mut self) -> &mut Vec<KeyOption> {
+        &mut self.options
+    }
+
+    /// required_sk_flags returns the flags sshd requires on signatures made
+    /// by this entry's key when it's a security key, to pass to
+    /// PublicKey::verify_sk. that is user presence unless the entry has the
+    /// no-touch-required option, and user verification if it has
+    /// verify-required.
+    pub fn required_sk_flags(&self) -> u8 {
+        let mut flags = SkSignature::USER_PRESENT;
+        if self.options.contains(&KeyOption::NoTouchRequired) {
+            flags &= !SkSignature::USER_PRESENT;
+        }
+        if self.options.contains(&KeyOption::VerifyRequired) {
+            flags |= SkSignature::USER_VERIFIED;
+        }
+        flags
+    }
+}
+
+/// Line is a single line of an authorized_keys file. lines that sshd would
+/// skip are kept so that the file can be written back out unchanged.
+pub type Line = lines::Line<AuthorizedKey>;
+
+impl lines::Entry for AuthorizedKey {
+    fn parse(text: &str) -> Result<Self> {
+        AuthorizedKey::parse(text)
+    }
+}
+
+/// AuthorizedKeysFile is the contents of a whole authorized_keys file. every
+/// line of the file is kept, including comments, blank lines, and lines that
+/// failed to parse, so writing it back out without making any changes
+/// produces exactly the same bytes that were read. lines that are modified
+/// are printed from their parsed form, everything else is left untouched.
+#[derive(Debug, Default)]
+pub struct AuthorizedKeysFile {
+    lines: Lines<AuthorizedKey>,
+}
+
+impl fmt::Display for AuthorizedKeysFile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.lines)
+    }
+}
+
+impl AuthorizedKeysFile {
+    /// new creates an empty authorized_keys file
+    pub fn new() -> Self {
+        AuthorizedKeysFile::default()
+    }
+
+    /// parse reads the contents of an authorized_keys file. this can't fail,
+    /// lines that aren't valid are kept as Line::Invalid.
+    ///
+    /// ```
+    /// # use openssh_keys::authorized_keys::AuthorizedKeysFile;
+    /// let contents = "# deploy keys\n\nno-pty ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril\n";
+    /// let file = AuthorizedKeysFile::parse(contents);
+    /// assert_eq!(1, file.entries().count());
+    /// assert_eq!(contents, file.to_string());
+    /// ```
+    pub fn parse(contents: &str) -> Self {
+        AuthorizedKeysFile {
+            lines: Lines::parse(contents),
+        }
+    }
+
+    /// read reads an authorized_keys file from r. the contents must be valid
+    /// utf-8.
+    pub fn read<R: Read>(mut r: R) -> Result<Self> {
+        let mut contents = String::new();
+        r.read_to_string(&mut contents)?;
+        Ok(AuthorizedKeysFile::parse(&contents))
+    }
+
+    /// write writes the authorized_keys file out to w
+    pub fn write<W: Write>(&self, mut w: W) -> Result<()> {
+        w.write_all(self.to_string().as_bytes())?;
+        Ok(())
+    }
+
+    /// lines returns every line of the file in order
+    pub fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter()
+    }
+
+    /// entries returns the successfully parsed entries of the file in order
+    pub fn entries(&self) -> impl Iterator<Item = &AuthorizedKey> {
+        self.lines.entries()
+    }
+
+    /// entry_mut returns the entry on line n (counting from zero) for
+    /// modification, or None if that line isn't an entry. the line will be
+    /// printed from the modified entry from then on.
+    pub fn entry_mut(&mut self, n: usize) -> Option<&mut AuthorizedKey> {
+        self.lines.entry_mut(n)
+    }
+
+    /// push adds an entry to the end of the file. if the file didn't end with
+    /// a newline, one is added to the current last line.
+    pub fn push(&mut self, entry: AuthorizedKey) {
+        self.lines.push(entry)
+    }
+
+    /// remove removes line n (counting from zero) from the file and returns
+    /// it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if n is out of bounds.
+    pub fn remove(&mut self, n: usize) -> Line {
+        self.lines.remove(n)
+    }
+
+    /// retain removes every entry for which f returns false. lines that
+    /// aren't entries are always kept.
+    pub fn retain<F: FnMut(&AuthorizedKey) -> bool>(&mut self, f: F) {
+        self.lines.retain(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ED25519_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril";
+
+    #[test]
+    fn parse_bare_key() {
+        let entry = AuthorizedKey::parse(TEST_ED25519_KEY).unwrap();
+        assert!(entry.options().is_empty());
+        assert_eq!(TEST_ED25519_KEY, entry.to_string());
+    }
+
+    #[test]
+    fn parse_flags() {
+        let line = format!("no-pty,restrict,NO-X11-FORWARDING {}", TEST_ED25519_KEY);
+        let entry = AuthorizedKey::parse(&line).unwrap();
+        assert_eq!(entry.options(), &[KeyOption::NoPty, KeyOption::Restrict, KeyOption::NoX11Forwarding]);
+        assert_eq!(format!("no-pty,restrict,no-X11-forwarding {}", TEST_ED25519_KEY), entry.to_string());
+    }
+
+    #[test]
+    fn required_sk_flags() {
+        let flags = |options: &str| AuthorizedKey::parse(&format!("{}{}", options, TEST_ED25519_KEY)).unwrap().required_sk_flags();
+        assert_eq!(SkSignature::USER_PRESENT, flags(""));
+        assert_eq!(0, flags("no-touch-required "));
+        assert_eq!(SkSignature::USER_PRESENT | SkSignature::USER_VERIFIED, flags("verify-required "));
+        assert_eq!(SkSignature::USER_VERIFIED, flags("no-touch-required,verify-required "));
+    }
+
+    #[test]
+    fn parse_quoted_values() {
+        let line = format!(r#"from="10.0.0.1,*.example.com",command="/bin/echo \"a, b\" c\d",environment="PATH=/bin:/usr/bin",permitopen="host:22"	{}"#, TEST_ED25519_KEY);
+        let entry = AuthorizedKey::parse(&line).unwrap();
+        assert_eq!(entry.options(), &[
+            KeyOption::From("10.0.0.1,*.example.com".into()),
+            KeyOption::Command(r#"/bin/echo "a, b" c\d"#.into()),
+            KeyOption::Environment("PATH".into(), "/bin:/usr/bin".into()),
+            KeyOption::PermitOpen("host:22".into()),
+        ]);
+        assert_eq!(line.replace('\t', " "), entry.to_string());
+    }
+
+    #[test]
+    fn reparse_output() {
+        let line = format!(r#"  principals="a b",Tunnel="1",expiry-time="20300101" {}"#, TEST_ED25519_KEY);
+        let entry = AuthorizedKey::parse(&line).unwrap();
+        let again = AuthorizedKey::parse(&entry.to_string()).unwrap();
+        assert_eq!(entry.options(), again.options());
+        assert_eq!(entry.key().fingerprint(), again.key().fingerprint());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(AuthorizedKey::parse("").is_err());
+        assert!(AuthorizedKey::parse("# ssh-ed25519 AAAA").is_err());
+        let unterminated = format!(r#"command="echo {}"#, TEST_ED25519_KEY);
+        assert!(AuthorizedKey::parse(&unterminated).is_err());
+        let unknown = format!("no-pty,frobnicate {}", TEST_ED25519_KEY);
+        match *AuthorizedKey::parse(&unknown).unwrap_err().kind() {
+            ErrorKind::InvalidOption(ref o) => assert_eq!("frobnicate", o),
+            ref e => panic!("unexpected error {:?}", e),
+        }
+        let missing_key = "no-pty,restrict";
+        assert!(AuthorizedKey::parse(missing_key).is_err());
+    }
+
+    const TEST_FILE: &str = "# keys for the deploy user\r\n\r\n  \tno-pty,command=\"uptime\"  ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril\r\nssh-rsa not-a-key\r\nssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/";
+
+    #[test]
+    fn file_round_trip() {
+        let file = AuthorizedKeysFile::read(TEST_FILE.as_bytes()).unwrap();
+        let mut out = vec![];
+        file.write(&mut out).unwrap();
+        assert_eq!(TEST_FILE.as_bytes(), &out[..]);
+    }
+
+    #[test]
+    fn file_lines() {
+        let file = AuthorizedKeysFile::parse(TEST_FILE);
+        let lines: Vec<&Line> = file.lines().collect();
+        assert_eq!(5, lines.len());
+        match *lines[0] {
+            Line::Comment(ref c) => assert_eq!("# keys for the deploy user", c),
+            ref l => panic!("unexpected line {:?}", l),
+        }
+        match *lines[1] {
+            Line::Blank(ref b) => assert_eq!("", b),
+            ref l => panic!("unexpected line {:?}", l),
+        }
+        match *lines[3] {
+            Line::Invalid(ref text, _) => assert_eq!("ssh-rsa not-a-key", text),
+            ref l => panic!("unexpected line {:?}", l),
+        }
+        assert_eq!(2, file.entries().count());
+    }
+
+    #[test]
+    fn file_edit_touches_only_edited_lines() {
+        let mut file = AuthorizedKeysFile::parse(TEST_FILE);
+        file.entry_mut(2).unwrap().options_mut().push(KeyOption::Restrict);
+        file.push(AuthorizedKey::parse(TEST_ED25519_KEY).unwrap());
+        let expected = format!("# keys for the deploy user\r\n\r\nno-pty,command=\"uptime\",restrict {key}\r\nssh-rsa not-a-key\r\nssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/\r\n{key}\r\n", key = TEST_ED25519_KEY);
+        assert_eq!(expected, file.to_string());
+    }
+
+    #[test]
+    fn edit_keeps_key_text() {
+        // PublicKey only keeps the first word of a comment
+        let line = "no-pty ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ John Doe laptop";
+        let mut file = AuthorizedKeysFile::parse(&format!("{}\n", line));
+        file.entry_mut(0).unwrap().options_mut().push(KeyOption::Restrict);
+        assert_eq!(format!("{}\n", line.replace("no-pty", "no-pty,restrict")), file.to_string());
+
+        let key = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/";
+        let mut entry = AuthorizedKey::parse(key).unwrap();
+        entry.options_mut().push(KeyOption::NoPty);
+        assert_eq!(format!("no-pty {}", key), entry.to_string());
+        assert_eq!(key, AuthorizedKey::new(entry.key().clone()).to_string());
+    }
+
+    #[test]
+    fn file_retain() {
+        let mut file = AuthorizedKeysFile::parse(TEST_FILE);
+        file.retain(|e| e.options().is_empty());
+        assert_eq!(1, file.entries().count());
+        assert_eq!(4, file.lines().count());
+        assert!(AuthorizedKeysFile::new().to_string().is_empty());
+    }
+}
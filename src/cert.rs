@@ -0,0 +1,940 @@
+//! cert
+//!
+//! this module provides parsing and printing of OpenSSH certificates, the
+//! `*-cert-v01@openssh.com` key types. a certificate is a public key along
+//! with identity and validity information, signed by a certificate
+//! authority. the format is described in
+//! https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.certkeys
+
+use errors::*;
+
+use known_hosts::wildcard_match;
+use private_key::PrivateKey;
+use reader::Reader;
+use signature;
+use writer::Writer;
+use {Data, PublicKey, SignatureAlgorithm};
+
+use base64;
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub(crate) const CERT_SUFFIX: &str = "-cert-v01@openssh.com";
+const OPENSSH_SUFFIX: &str = "@openssh.com";
+const USER_CERT: u32 = 1;
+const HOST_CERT: u32 = 2;
+const NONCE_LEN: usize = 32;
+// the extensions ssh-keygen gives user certificates unless told otherwise
+const DEFAULT_EXTENSIONS: &[&str] = &[
+    "permit-X11-forwarding",
+    "permit-agent-forwarding",
+    "permit-port-forwarding",
+    "permit-pty",
+    "permit-user-rc",
+];
+// the critical options sshd understands. see the list in
+// https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.certkeys
+const USER_CRITICAL_OPTIONS: &[&str] = &["force-command", "source-address", "verify-required"];
+
+/// CertType is the kind of principal a certificate identifies
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CertType {
+    User,
+    Host,
+}
+
+impl CertType {
+    fn get(t: u32) -> Result<Self> {
+        Ok(match t {
+            USER_CERT => CertType::User,
+            HOST_CERT => CertType::Host,
+            _ => return Err(ErrorKind::InvalidFormat.into()),
+        })
+    }
+
+    fn code(self) -> u32 {
+        match self {
+            CertType::User => USER_CERT,
+            CertType::Host => HOST_CERT,
+        }
+    }
+}
+
+impl fmt::Display for CertType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CertType::User => write!(f, "user"),
+            CertType::Host => write!(f, "host"),
+        }
+    }
+}
+
+/// Rejection is the reason a certificate failed validation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// the certificate is a user certificate where a host certificate was
+    /// wanted, or the other way around
+    WrongType(CertType),
+    /// the certificate wasn't signed by one of the trusted CAs
+    UntrustedCa,
+    /// the CA signature over the certificate doesn't verify
+    BadSignature,
+    /// the CA signed the certificate with an algorithm that isn't allowed,
+    /// like `ssh-rsa` with SHA-1
+    SignatureAlgorithmNotAllowed(String),
+    /// the validity period hasn't started yet
+    NotYetValid,
+    /// the validity period is over
+    Expired,
+    /// a user certificate has no principals. sshd doesn't accept these when
+    /// it checks certificates against TrustedUserCAKeys.
+    NoPrincipals,
+    /// the requested principal isn't one the certificate is valid for
+    PrincipalNotAllowed(String),
+    /// the certificate has a critical option that isn't understood, so it
+    /// can't be honored
+    UnknownCriticalOption(String),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Rejection::WrongType(t) => write!(f, "unexpected {} certificate", t),
+            Rejection::UntrustedCa => write!(f, "not signed by a trusted CA"),
+            Rejection::BadSignature => write!(f, "invalid CA signature"),
+            Rejection::SignatureAlgorithmNotAllowed(ref a) => write!(f, "CA signature algorithm {} not allowed", a),
+            Rejection::NotYetValid => write!(f, "not yet valid"),
+            Rejection::Expired => write!(f, "expired"),
+            Rejection::NoPrincipals => write!(f, "certificate lacks principal list"),
+            Rejection::PrincipalNotAllowed(ref p) => write!(f, "{} is not a listed principal", p),
+            Rejection::UnknownCriticalOption(ref o) => write!(f, "unsupported critical option {}", o),
+        }
+    }
+}
+
+/// Certificate is the struct representation of an OpenSSH certificate.
+#[derive(Clone, Debug)]
+pub struct Certificate {
+    nonce: Vec<u8>,
+    key: PublicKey,
+    serial: u64,
+    cert_type: CertType,
+    key_id: String,
+    valid_principals: Vec<String>,
+    valid_after: u64,
+    valid_before: u64,
+    critical_options: Vec<(String, Vec<u8>)>,
+    extensions: Vec<(String, Vec<u8>)>,
+    reserved: Vec<u8>,
+    signature_key: PublicKey,
+    signature: Vec<u8>,
+    comment: Option<String>,
+}
+
+impl fmt::Display for Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_key_file())
+    }
+}
+
+/// read_options reads the critical options or extensions section, which is a
+/// list of name and data pairs packed into a single string.
+fn read_options(buf: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut reader = Reader::new(buf);
+    let mut options = vec![];
+    while !reader.is_empty() {
+        let name = reader.read_string()?;
+        let data = reader.read_bytes()?;
+        options.push((name.to_string(), data.to_vec()));
+    }
+    Ok(options)
+}
+
+fn write_options(writer: &mut Writer, options: &[(String, Vec<u8>)]) {
+    let mut inner = Writer::new();
+    for (name, data) in options {
+        inner.write_string(name);
+        inner.write_bytes(data.clone());
+    }
+    writer.write_bytes(inner.into_vec());
+}
+
+/// option_string decodes the data of an option, which for all the options
+/// OpenSSH defines is either empty or a single string.
+fn option_string(data: &[u8]) -> Result<String> {
+    if data.is_empty() {
+        return Ok(String::new());
+    }
+    let mut reader = Reader::new(data);
+    let value = reader.read_string()?;
+    if !reader.is_empty() {
+        return Err(ErrorKind::InvalidFormat.into());
+    }
+    Ok(value.to_string())
+}
+
+impl Certificate {
+    /// parse takes a string and reads from it an OpenSSH certificate, in the
+    /// same `keytype data comment` format used for public keys, as found in
+    /// `id_ed25519-cert.pub` files.
+    ///
+    /// ```
+    /// # use openssh_keys::cert::{Certificate, CertType};
+    /// let cert = Certificate::parse("ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIL5ChEshyBB3/KIc9QnmA+ax6jnxF3qS/rIRQSZDXDL4AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAACoAAAABAAAAD2RlbW9zIHVzZXIgY2VydAAAABEAAAAFZGVtb3MAAAAEcm9vdAAAAABeC+EAAAAAAHDb2IAAAABGAAAADWZvcmNlLWNvbW1hbmQAAAANAAAACS9iaW4vdHJ1ZQAAAA5zb3VyY2UtYWRkcmVzcwAAAA4AAAAKMTAuMC4wLjAvOAAAAIIAAAAVcGVybWl0LVgxMS1mb3J3YXJkaW5nAAAAAAAAABdwZXJtaXQtYWdlbnQtZm9yd2FyZGluZwAAAAAAAAAWcGVybWl0LXBvcnQtZm9yd2FyZGluZwAAAAAAAAAKcGVybWl0LXB0eQAAAAAAAAAOcGVybWl0LXVzZXItcmMAAAAAAAAAAAAAADMAAAALc3NoLWVkMjU1MTkAAAAgviAC8cgHBokJasKlozpsCxmDL1DXKAglaUvRsCRzlX0AAABTAAAAC3NzaC1lZDI1NTE5AAAAQD3JSNvPno0pV/1hjBL1XHDWGwIBZ7/JK9qoN/A6pierYBNGto7JyJv1STt+kImLh8J+NsI+NVAiHQgA6NOK8Qs= demos@siril").unwrap();
+    /// assert_eq!(CertType::User, cert.cert_type());
+    /// assert_eq!(&["demos".to_string(), "root".to_string()], cert.valid_principals());
+    /// assert_eq!("SHA256:4UYT6thsPirHGnnUC5T91Aj/ba1y65PCqMUl+qsE4qo", cert.signature_key().fingerprint());
+    /// ```
+    pub fn parse(cert: &str) -> Result<Self> {
+        let mut parts = cert.split_whitespace();
+        let keytype = parts.next().ok_or(ErrorKind::InvalidFormat)?;
+        let data = parts.next().ok_or(ErrorKind::InvalidFormat)?;
+        let comment = parts.next().map(|c| c.to_string());
+
+        let buf = base64::decode(data).chain_err(|| ErrorKind::InvalidFormat)?;
+        let mut cert = Certificate::from_blob(&buf)?;
+        if cert.keytype() != keytype {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        cert.comment = comment;
+        Ok(cert)
+    }
+
+    /// from_blob reads a certificate from its binary encoding, as returned by
+    /// data()
+    pub fn from_blob(blob: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(blob);
+        let keytype = reader.read_string()?;
+        // the keytype of the certified key is the certificate keytype
+        // without the suffix, and its data section follows the nonce.
+        if !keytype.ends_with(CERT_SUFFIX) {
+            return Err(ErrorKind::UnsupportedKeytype(keytype.into()).into());
+        }
+        // security key types already end in @openssh.com, which the
+        // certificate types only have once
+        let key_keytype = keytype[..keytype.len() - CERT_SUFFIX.len()].to_string();
+        let key_keytype = if key_keytype.starts_with("sk-") {
+            format!("{}{}", key_keytype, OPENSSH_SUFFIX)
+        } else {
+            key_keytype
+        };
+        let nonce = reader.read_bytes()?;
+        let key = PublicKey {
+            data: PublicKey::read_data(&key_keytype, &mut reader)
+                .map_err(|_| Error::from(ErrorKind::UnsupportedKeytype(keytype.into())))?,
+            comment: None,
+        };
+        let serial = reader.read_u64()?;
+        let cert_type = CertType::get(reader.read_int()?)?;
+        let key_id = reader.read_string()?;
+
+        let mut principals = Reader::new(reader.read_bytes()?);
+        let mut valid_principals = vec![];
+        while !principals.is_empty() {
+            valid_principals.push(principals.read_string()?.to_string());
+        }
+
+        let valid_after = reader.read_u64()?;
+        let valid_before = reader.read_u64()?;
+        let critical_options = read_options(reader.read_bytes()?)?;
+        let extensions = read_options(reader.read_bytes()?)?;
+        let reserved = reader.read_bytes()?;
+        let signature_key = PublicKey::from_blob(reader.read_bytes()?)?;
+        let signature = reader.read_bytes()?;
+        if !reader.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+
+        Ok(Certificate {
+            nonce: nonce.to_vec(),
+            key,
+            serial,
+            cert_type,
+            key_id: key_id.to_string(),
+            valid_principals,
+            valid_after,
+            valid_before,
+            critical_options,
+            extensions,
+            reserved: reserved.to_vec(),
+            signature_key,
+            signature: signature.to_vec(),
+            comment: None,
+        })
+    }
+
+    /// keytype returns the certificate type, which is the keytype of the
+    /// certified key with `-cert-v01@openssh.com` appended, in place of the
+    /// `@openssh.com` that security key types end with.
+    pub fn keytype(&self) -> String {
+        let keytype = self.key.keytype();
+        format!("{}{}", keytype.strip_suffix(OPENSSH_SUFFIX).unwrap_or(keytype), CERT_SUFFIX)
+    }
+
+    /// body returns the signed part of the certificate, which is everything
+    /// but the signature itself.
+    pub(crate) fn body(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.write_string(&self.keytype());
+        writer.write_bytes(self.nonce.clone());
+        PublicKey::write_data(&self.key.data, &mut writer);
+        writer.write_u64(self.serial);
+        writer.write_int(self.cert_type.code());
+        writer.write_string(&self.key_id);
+        let mut principals = Writer::new();
+        for p in &self.valid_principals {
+            principals.write_string(p);
+        }
+        writer.write_bytes(principals.into_vec());
+        writer.write_u64(self.valid_after);
+        writer.write_u64(self.valid_before);
+        write_options(&mut writer, &self.critical_options);
+        write_options(&mut writer, &self.extensions);
+        writer.write_bytes(self.reserved.clone());
+        writer.write_bytes(self.signature_key.data());
+        writer.into_vec()
+    }
+
+    /// data returns the binary encoding of the certificate. this is what gets
+    /// base64 encoded in the text format.
+    pub fn data(&self) -> Vec<u8> {
+        let mut data = self.body();
+        let mut writer = Writer::new();
+        writer.write_bytes(self.signature.clone());
+        data.append(&mut writer.into_vec());
+        data
+    }
+
+    /// to_key_file returns the certificate in the format used by
+    /// `id_*-cert.pub` files
+    pub fn to_key_file(&self) -> String {
+        let key = format!("{} {}", self.keytype(), base64::encode(&self.data()));
+        match self.comment {
+            Some(ref comment) => format!("{} {}", key, comment),
+            None => key,
+        }
+    }
+
+    /// nonce returns the random value the CA included to make the signed
+    /// data unpredictable
+    pub fn nonce(&self) -> &[u8] {
+        &self.nonce
+    }
+
+    /// key returns the certified public key
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// key_data returns the data of the certified public key
+    pub fn key_data(&self) -> &Data {
+        &self.key.data
+    }
+
+    pub fn serial(&self) -> u64 {
+        self.serial
+    }
+
+    pub fn cert_type(&self) -> CertType {
+        self.cert_type
+    }
+
+    /// key_id returns the free-form identifier the CA gave the certificate,
+    /// which sshd logs
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// valid_principals returns the user or host names the certificate is
+    /// valid for. an empty list means it's valid for any principal.
+    pub fn valid_principals(&self) -> &[String] {
+        &self.valid_principals
+    }
+
+    /// valid_after returns the start of the validity period, in seconds since
+    /// the unix epoch
+    pub fn valid_after(&self) -> u64 {
+        self.valid_after
+    }
+
+    /// valid_before returns the end of the validity period, in seconds since
+    /// the unix epoch. certificates valid forever use u64::MAX.
+    pub fn valid_before(&self) -> u64 {
+        self.valid_before
+    }
+
+    /// critical_options returns the names and raw data of the critical
+    /// options, in order.
+    pub fn critical_options(&self) -> &[(String, Vec<u8>)] {
+        &self.critical_options
+    }
+
+    /// critical_option returns the value of a critical option like
+    /// force-command or source-address, an empty string if the option has no
+    /// value, or None if the option isn't present.
+    pub fn critical_option(&self, name: &str) -> Result<Option<String>> {
+        self.critical_options
+            .iter()
+            .find(|&(n, _)| n == name)
+            .map(|(_, data)| option_string(data))
+            .transpose()
+    }
+
+    /// extensions returns the names and raw data of the extensions, in order.
+    pub fn extensions(&self) -> &[(String, Vec<u8>)] {
+        &self.extensions
+    }
+
+    /// has_extension checks whether an extension like permit-pty is present
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions.iter().any(|(n, _)| n == name)
+    }
+
+    /// signature_key returns the key of the certificate authority that signed
+    /// the certificate
+    pub fn signature_key(&self) -> &PublicKey {
+        &self.signature_key
+    }
+
+    /// signature returns the signature of the certificate authority, in the
+    /// ssh signature encoding
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    pub fn set_comment(&mut self, comment: &str) {
+        self.comment = Some(comment.to_string());
+    }
+
+    /// validate checks that the certificate can be used to authenticate the
+    /// given principal at time now, in seconds since the unix epoch, in the
+    /// same way sshd checks certificates against TrustedUserCAKeys and ssh
+    /// checks host certificates against @cert-authority lines. the
+    /// certificate has to be of the given type and signed by one of
+    /// trusted_cas, now has to be within the validity period, and principal
+    /// has to be one of the valid principals. for host certificates, the
+    /// principals can have `*` and `?` wildcards and an empty list allows any
+    /// host. certificates with critical options that aren't understood are
+    /// rejected, and so are CA signatures that aren't in
+    /// SignatureAlgorithm::DEFAULT, which like sshd's default
+    /// CASignatureAlgorithms leaves out `ssh-rsa`.
+    ///
+    /// if the certificate isn't valid the error is a CertificateRejected with
+    /// the reason.
+    pub fn validate(&self, cert_type: CertType, principal: &str, trusted_cas: &[PublicKey], now: u64) -> Result<()> {
+        let reject = |r| Err(ErrorKind::CertificateRejected(r).into());
+
+        if self.cert_type != cert_type {
+            return reject(Rejection::WrongType(self.cert_type));
+        }
+        let ca = self.signature_key.data();
+        if !trusted_cas.iter().any(|k| k.data() == ca) {
+            return reject(Rejection::UntrustedCa);
+        }
+        if let Err(e) = self.signature_key.verify_allowing(&self.body(), &self.signature, SignatureAlgorithm::DEFAULT) {
+            return match *e.kind() {
+                ErrorKind::InvalidSignature => reject(Rejection::BadSignature),
+                ErrorKind::DisallowedSignatureAlgorithm(ref a) => reject(Rejection::SignatureAlgorithmNotAllowed(a.clone())),
+                _ => Err(e),
+            };
+        }
+
+        if now < self.valid_after {
+            return reject(Rejection::NotYetValid);
+        }
+        if now >= self.valid_before {
+            return reject(Rejection::Expired);
+        }
+
+        let allowed = match self.cert_type {
+            CertType::User => {
+                if self.valid_principals.is_empty() {
+                    return reject(Rejection::NoPrincipals);
+                }
+                self.valid_principals.iter().any(|p| p == principal)
+            },
+            CertType::Host => {
+                self.valid_principals.is_empty()
+                    || self.valid_principals.iter().any(|p| wildcard_match(p, principal))
+            },
+        };
+        if !allowed {
+            return reject(Rejection::PrincipalNotAllowed(principal.to_string()));
+        }
+
+        // host certificates don't have any critical options defined
+        let known: &[&str] = match self.cert_type {
+            CertType::User => USER_CRITICAL_OPTIONS,
+            CertType::Host => &[],
+        };
+        if let Some((name, _)) = self.critical_options.iter().find(|&(n, _)| !known.contains(&n.as_str())) {
+            return reject(Rejection::UnknownCriticalOption(name.clone()));
+        }
+        Ok(())
+    }
+
+    /// fingerprint returns the fingerprint of the certified key, which is
+    /// what `ssh-keygen -l` shows for a certificate. use
+    /// `signature_key().fingerprint()` for the fingerprint of the CA.
+    pub fn fingerprint(&self) -> String {
+        self.key.fingerprint()
+    }
+}
+
+/// CertificateBuilder creates and signs certificates, like `ssh-keygen -s`.
+///
+/// ```
+/// # use openssh_keys::PublicKey;
+/// # use openssh_keys::cert::{CertificateBuilder, CertType};
+/// let key = PublicKey::parse("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril").unwrap();
+/// let builder = CertificateBuilder::new(key, CertType::User)
+///     .serial(42)
+///     .key_id("demos user cert")
+///     .principal("demos")
+///     .valid_after(1577836800)
+///     .valid_before(1893456000)
+///     .critical_option("force-command", "/bin/true");
+/// ```
+#[derive(Clone, Debug)]
+pub struct CertificateBuilder {
+    key: PublicKey,
+    serial: u64,
+    cert_type: CertType,
+    key_id: String,
+    valid_principals: Vec<String>,
+    valid_after: u64,
+    valid_before: u64,
+    critical_options: BTreeMap<String, Vec<u8>>,
+    extensions: BTreeMap<String, Vec<u8>>,
+}
+
+/// option_data encodes the value of an option. empty values are encoded as
+/// empty data, the same way ssh-keygen does it.
+fn option_data(value: &str) -> Vec<u8> {
+    if value.is_empty() {
+        return vec![];
+    }
+    let mut writer = Writer::new();
+    writer.write_string(value);
+    writer.into_vec()
+}
+
+impl CertificateBuilder {
+    /// new starts a certificate for key. the certificate is valid forever
+    /// for any principal, and user certificates get the same default
+    /// extensions ssh-keygen gives them, which permit X11, agent and port
+    /// forwarding, a pty, and running ~/.ssh/rc.
+    pub fn new(key: PublicKey, cert_type: CertType) -> Self {
+        let extensions = match cert_type {
+            CertType::User => DEFAULT_EXTENSIONS.iter().map(|e| (e.to_string(), vec![])).collect(),
+            CertType::Host => BTreeMap::new(),
+        };
+        CertificateBuilder {
+            key,
+            serial: 0,
+            cert_type,
+            key_id: String::new(),
+            valid_principals: vec![],
+            valid_after: 0,
+            valid_before: u64::MAX,
+            critical_options: BTreeMap::new(),
+            extensions,
+        }
+    }
+
+    pub fn serial(mut self, serial: u64) -> Self {
+        self.serial = serial;
+        self
+    }
+
+    pub fn key_id(mut self, key_id: &str) -> Self {
+        self.key_id = key_id.to_string();
+        self
+    }
+
+    /// principal adds a user or host name the certificate is valid for
+    pub fn principal(mut self, principal: &str) -> Self {
+        self.valid_principals.push(principal.to_string());
+        self
+    }
+
+    /// valid_after sets the start of the validity period, in seconds since
+    /// the unix epoch
+    pub fn valid_after(mut self, time: u64) -> Self {
+        self.valid_after = time;
+        self
+    }
+
+    /// valid_before sets the end of the validity period, in seconds since
+    /// the unix epoch
+    pub fn valid_before(mut self, time: u64) -> Self {
+        self.valid_before = time;
+        self
+    }
+
+    /// critical_option sets a critical option like force-command or
+    /// source-address. options are written sorted by name, as required by
+    /// PROTOCOL.certkeys, and setting an option again replaces its value.
+    pub fn critical_option(mut self, name: &str, value: &str) -> Self {
+        self.critical_options.insert(name.to_string(), option_data(value));
+        self
+    }
+
+    /// extension adds an extension like permit-pty
+    pub fn extension(mut self, name: &str) -> Self {
+        self.extensions.insert(name.to_string(), vec![]);
+        self
+    }
+
+    /// remove_extension removes an extension, including the default ones
+    pub fn remove_extension(mut self, name: &str) -> Self {
+        self.extensions.remove(name);
+        self
+    }
+
+    /// clear removes all critical options and extensions, like
+    /// `ssh-keygen -O clear`
+    pub fn clear(mut self) -> Self {
+        self.critical_options.clear();
+        self.extensions.clear();
+        self
+    }
+
+    /// sign_with creates the certificate and signs it as the CA with the
+    /// public key ca. sign is given the data to sign and returns the
+    /// signature in the ssh signature encoding, which lets the CA key live
+    /// somewhere else, like an ssh-agent or a hardware token. the signature
+    /// is checked before the certificate is returned.
+    pub fn sign_with<F>(self, ca: &PublicKey, sign: F) -> Result<Certificate>
+    where
+        F: FnOnce(&[u8]) -> Result<Vec<u8>>,
+    {
+        let mut nonce = vec![0; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let comment = self.key.comment.clone();
+        let mut cert = Certificate {
+            nonce,
+            key: PublicKey {
+                data: self.key.data,
+                comment: None,
+            },
+            serial: self.serial,
+            cert_type: self.cert_type,
+            key_id: self.key_id,
+            valid_principals: self.valid_principals,
+            valid_after: self.valid_after,
+            valid_before: self.valid_before,
+            critical_options: self.critical_options.into_iter().collect(),
+            extensions: self.extensions.into_iter().collect(),
+            reserved: vec![],
+            signature_key: PublicKey {
+                data: ca.data.clone(),
+                comment: None,
+            },
+            signature: vec![],
+            comment,
+        };
+        let body = cert.body();
+        let signature = sign(&body)?;
+        signature::verify(&cert.signature_key, &body, &signature)?;
+        cert.signature = signature;
+        Ok(cert)
+    }
+
+    /// sign creates the certificate and signs it with the private key of
+    /// the CA, using the default signature algorithm for the key, which is
+    /// rsa-sha2-512 for rsa keys like ssh-keygen uses.
+    pub fn sign(self, ca: &PrivateKey) -> Result<Certificate> {
+        self.sign_with(ca.public_key(), |body| ca.sign(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::ed25519;
+
+    // generated with
+    //   ssh-keygen -s ca -I "demos user cert" -n demos,root -V 20200101000000Z:20300101000000Z -z 42 \
+    //     -O force-command=/bin/true -O source-address=10.0.0.0/8 fixtures/ed25519.pub
+    const TEST_USER_CERT: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIL5ChEshyBB3/KIc9QnmA+ax6jnxF3qS/rIRQSZDXDL4AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAACoAAAABAAAAD2RlbW9zIHVzZXIgY2VydAAAABEAAAAFZGVtb3MAAAAEcm9vdAAAAABeC+EAAAAAAHDb2IAAAABGAAAADWZvcmNlLWNvbW1hbmQAAAANAAAACS9iaW4vdHJ1ZQAAAA5zb3VyY2UtYWRkcmVzcwAAAA4AAAAKMTAuMC4wLjAvOAAAAIIAAAAVcGVybWl0LVgxMS1mb3J3YXJkaW5nAAAAAAAAABdwZXJtaXQtYWdlbnQtZm9yd2FyZGluZwAAAAAAAAAWcGVybWl0LXBvcnQtZm9yd2FyZGluZwAAAAAAAAAKcGVybWl0LXB0eQAAAAAAAAAOcGVybWl0LXVzZXItcmMAAAAAAAAAAAAAADMAAAALc3NoLWVkMjU1MTkAAAAgviAC8cgHBokJasKlozpsCxmDL1DXKAglaUvRsCRzlX0AAABTAAAAC3NzaC1lZDI1NTE5AAAAQD3JSNvPno0pV/1hjBL1XHDWGwIBZ7/JK9qoN/A6pierYBNGto7JyJv1STt+kImLh8J+NsI+NVAiHQgA6NOK8Qs= demos@siril";
+    // generated with
+    //   ssh-keygen -s ca -h -I "host cert" -n host.example.com,*.example.org -V always:forever -z 7 fixtures/rsa.pub
+    const TEST_HOST_CERT: &str = "ssh-rsa-cert-v01@openssh.com AAAAHHNzaC1yc2EtY2VydC12MDFAb3BlbnNzaC5jb20AAAAgwkiCJmC5C/t60ObOFNzu/wTMv2vW7fpkgiMUysGdd3AAAAADAQABAAABAQCYH3vPUJThzriVlVKmKOg71EOVYm274oRa5KLWEoK0HmjMc9ru0j4ofouoeW/AVmRVujxfaIGR/8en/lUPkiv5DSeM6aXnDz5cExNptrAy/sMPLQhVALRrqQ+dkS9Ct/YA+A1Le5LPh4MJu79hCDLTwqSdKqDuUcYQzR0M7APslaDCR96zY+VUL4lKObUUd4wsP3opdTQ6G20qXEer14EPGr9N53S/u+JJGLoPlb1uPIH96oKY4t/SeLIRQsocdViRaiF/Aq7kPzWd/yCLVdXJSRt3CftboV4kLBHGteTS551J32MJoqjEi4Q/DucWYrQfx5H3qXVB+/G2HurKPIHLAAAAAAAAAAcAAAACAAAACWhvc3QgY2VydAAAACUAAAAQaG9zdC5leGFtcGxlLmNvbQAAAA0qLmV4YW1wbGUub3JnAAAAAAAAAAD//////////wAAAAAAAAAAAAAAAAAAADMAAAALc3NoLWVkMjU1MTkAAAAgviAC8cgHBokJasKlozpsCxmDL1DXKAglaUvRsCRzlX0AAABTAAAAC3NzaC1lZDI1NTE5AAAAQON8oeHzbZI80mDQJ7y5L3L3XrikjcKdGuLuL4k9tI05yIr0uRKHm52O1kV8nF+FgxyWDwDKnWWC4t4CPLwhjQE= demos@siril";
+    // fixtures/ed25519.pub signed by the fixtures/rsa key with ssh-rsa, generated with
+    //   ssh-keygen -s fixtures/rsa -t ssh-rsa -I "demos rsa ca" -n demos -z 7 fixtures/ed25519.pub
+    const TEST_SSH_RSA_CA_CERT: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIFggkJFOXmIxu1PZifQYFt0L9GIpoy41Ca9ao4c30POKAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAcAAAABAAAADGRlbW9zIHJzYSBjYQAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAIIAAAAVcGVybWl0LVgxMS1mb3J3YXJkaW5nAAAAAAAAABdwZXJtaXQtYWdlbnQtZm9yd2FyZGluZwAAAAAAAAAWcGVybWl0LXBvcnQtZm9yd2FyZGluZwAAAAAAAAAKcGVybWl0LXB0eQAAAAAAAAAOcGVybWl0LXVzZXItcmMAAAAAAAAAAAAAARcAAAAHc3NoLXJzYQAAAAMBAAEAAAEBAJgfe89QlOHOuJWVUqYo6DvUQ5VibbvihFrkotYSgrQeaMxz2u7SPih+i6h5b8BWZFW6PF9ogZH/x6f+VQ+SK/kNJ4zppecPPlwTE2m2sDL+ww8tCFUAtGupD52RL0K39gD4DUt7ks+Hgwm7v2EIMtPCpJ0qoO5RxhDNHQzsA+yVoMJH3rNj5VQviUo5tRR3jCw/eil1NDobbSpcR6vXgQ8av03ndL+74kkYug+VvW48gf3qgpji39J4shFCyhx1WJFqIX8CruQ/NZ3/IItV1clJG3cJ+1uhXiQsEca15NLnnUnfYwmiqMSLhD8O5xZitB/HkfepdUH78bYe6so8gcsAAAEPAAAAB3NzaC1yc2EAAAEAFhk/fB5HwhCISVf227Ch609zlfmFJpu+jU+bLWzvjd6+RHcMHR2MZvvuxYQh8/iucbMq2frDNURz2jI8xdwtS/VxeGuzryHF8pgHexHI/UQ07MhWPml+inaATAN/NtyhvqAtFqNSDB3o2SGsWySUN4yYh14d4gb1ylCKEP1rYNybQ2PGQuHMprc911QkPILrCvNBC4AQ1fLs3T12fZuOXKtVNvb1MmEoSvLwvf4k7ITj5ZJfYYwJ18Rmt/CQHcQj1XgJwFgULvnr+oa7pKAR1g2Z42rL/ZTOztyxk6zWUhTZTGMx+0Squkj02w4APIaV2gW0nSBo93DhNh0MQD+z9A== demos@siril";
+    // the same signed with rsa-sha2-512, generated with
+    //   ssh-keygen -s fixtures/rsa -t rsa-sha2-512 -I "demos rsa ca" -n demos -z 8 fixtures/ed25519.pub
+    const TEST_RSA_SHA512_CA_CERT: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIH27UkJbaOzp5mLOCj/W5oe+SkW+rZpO/T3PBYk5VprAAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAgAAAABAAAADGRlbW9zIHJzYSBjYQAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAIIAAAAVcGVybWl0LVgxMS1mb3J3YXJkaW5nAAAAAAAAABdwZXJtaXQtYWdlbnQtZm9yd2FyZGluZwAAAAAAAAAWcGVybWl0LXBvcnQtZm9yd2FyZGluZwAAAAAAAAAKcGVybWl0LXB0eQAAAAAAAAAOcGVybWl0LXVzZXItcmMAAAAAAAAAAAAAARcAAAAHc3NoLXJzYQAAAAMBAAEAAAEBAJgfe89QlOHOuJWVUqYo6DvUQ5VibbvihFrkotYSgrQeaMxz2u7SPih+i6h5b8BWZFW6PF9ogZH/x6f+VQ+SK/kNJ4zppecPPlwTE2m2sDL+ww8tCFUAtGupD52RL0K39gD4DUt7ks+Hgwm7v2EIMtPCpJ0qoO5RxhDNHQzsA+yVoMJH3rNj5VQviUo5tRR3jCw/eil1NDobbSpcR6vXgQ8av03ndL+74kkYug+VvW48gf3qgpji39J4shFCyhx1WJFqIX8CruQ/NZ3/IItV1clJG3cJ+1uhXiQsEca15NLnnUnfYwmiqMSLhD8O5xZitB/HkfepdUH78bYe6so8gcsAAAEUAAAADHJzYS1zaGEyLTUxMgAAAQBL27ftjT38u9mchVbGU1WU9XIKd9AS3KAf+t0Tvvfpmm2xFMZH6FwRtUvnhxIV3uwhNEAe1czlE0wmdsjmvvTF2umc5W/tNEUy075LnUq1kJoXxcQZ3aQtjOjYNrbQxIhYvkPEIqculotM1L1GRnVO/t2BS69aN8cWwQ4OAR1w+AhxpNRw3/YqhVygH7YEFfbP88c2PANZ2bnzXmylqNpu9Kbcp/r/ff53acrQUIkxC5zAuq/uyt+likj1dC0oV5OiFmP58jMNozl+LjcfchZ00cVk0wHUKpPtkJMETuzDNOT4+oEUMRsZyLDz/jp4tlvsE2PajS4bQULUykiFe6M+ demos@siril";
+    // generated with
+    //   ssh-keygen -s ca -I crit -n demos -z 2 -O clear -O critical:unknown@example.com=yes fixtures/ed25519.pub
+    const TEST_CRITICAL_CERT: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIEbhK1YADP6HPzJlVjgswGZPTINxEud8YLOWSCQy2d6CAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAIAAAABAAAABGNyaXQAAAAJAAAABWRlbW9zAAAAAAAAAAD//////////wAAACIAAAATdW5rbm93bkBleGFtcGxlLmNvbQAAAAcAAAADeWVzAAAAAAAAAAAAAAAzAAAAC3NzaC1lZDI1NTE5AAAAIL4gAvHIBwaJCWrCpaM6bAsZgy9Q1ygIJWlL0bAkc5V9AAAAUwAAAAtzc2gtZWQyNTUxOQAAAEAv2qkbfr92AxZBkt/E1GmvqXBTOeioezjhf3tChYQOWC6X5dlmdbEMtRbPLsdRIe5F5cQBnQWUOZcZYij/OtsJ";
+    // generated with
+    //   ssh-keygen -s ca -I any -z 3 -O clear fixtures/ed25519.pub
+    const TEST_NO_PRINCIPALS_CERT: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIFWdsJfa+Kc7uRqpU7BZ47D1eRziXul525KDpjquj5AoAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAMAAAABAAAAA2FueQAAAAAAAAAAAAAAAP//////////AAAAAAAAAAAAAAAAAAAAMwAAAAtzc2gtZWQyNTUxOQAAACC+IALxyAcGiQlqwqWjOmwLGYMvUNcoCCVpS9GwJHOVfQAAAFMAAAALc3NoLWVkMjU1MTkAAABAJsZ40SqgF4r9xBo3D3n8vjZ99VliXtLG7UfwB7Ea2BUGMuNNZjTTV4aFQOjZMtgvVJyTgQwEbfPuAiP7m+kzCg==";
+    // generated with
+    //   ssh-keygen -s fixtures/ed25519 -I sk -n demos -z 5 sk-ed25519.pub
+    // for security keys with the points of fixtures/ed25519.pub and
+    // fixtures/ecdsa.pub
+    const TEST_SK_ED25519_CERT: &str = "sk-ssh-ed25519-cert-v01@openssh.com AAAAI3NrLXNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIHhN6DaSUSf2UkcaHMUESZcMnOXJLJkwtnxvcDjzFDMbAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAABHNzaDoAAAAAAAAABQAAAAEAAAACc2sAAAAJAAAABWRlbW9zAAAAAAAAAAD//////////wAAAAAAAACCAAAAFXBlcm1pdC1YMTEtZm9yd2FyZGluZwAAAAAAAAAXcGVybWl0LWFnZW50LWZvcndhcmRpbmcAAAAAAAAAFnBlcm1pdC1wb3J0LWZvcndhcmRpbmcAAAAAAAAACnBlcm1pdC1wdHkAAAAAAAAADnBlcm1pdC11c2VyLXJjAAAAAAAAAAAAAAAzAAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAUwAAAAtzc2gtZWQyNTUxOQAAAECI37sBv1gfU1mQ/03VOXEfeoBC/H0Fl4dqmGBnnRlZADV9DF2Cckl6qu65Kdqdp9MPUAe/rRkkA8iTBYHuBnMA demos@siril";
+    // and the same with -z 6 for the ecdsa one
+    const TEST_SK_ECDSA_CERT: &str = "sk-ecdsa-sha2-nistp256-cert-v01@openssh.com AAAAK3NrLWVjZHNhLXNoYTItbmlzdHAyNTYtY2VydC12MDFAb3BlbnNzaC5jb20AAAAgd7rOE15rwIaVzRyw/+K85OlKaFApjc+hO/o6D3Yhhm0AAAAIbmlzdHAyNTYAAABBBIhfLQrww4DlhYzbSWXoX3ctOQ0jVosvfHfW+QWVotksbPzM2YgkIikTpoHUfZrYpJKWx7WYs5aqeLkdCDdk+jkAAAAEc3NoOgAAAAAAAAAGAAAAAQAAAAJzawAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAIIAAAAVcGVybWl0LVgxMS1mb3J3YXJkaW5nAAAAAAAAABdwZXJtaXQtYWdlbnQtZm9yd2FyZGluZwAAAAAAAAAWcGVybWl0LXBvcnQtZm9yd2FyZGluZwAAAAAAAAAKcGVybWl0LXB0eQAAAAAAAAAOcGVybWl0LXVzZXItcmMAAAAAAAAAAAAAADMAAAALc3NoLWVkMjU1MTkAAAAgCEGvr74VBcHySSA4xt3EG7KsfO5fkeQSWzCs3oNCg38AAABTAAAAC3NzaC1lZDI1NTE5AAAAQB+6T9RbTYlp224QzT8bZdvVfWSwlgVke732Pb8c6eNyFWvHogdn9tGrvCDNDKhXruoBad1K0bNuuUCFsHHznwM= demos@siril";
+    const TEST_CA_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIL4gAvHIBwaJCWrCpaM6bAsZgy9Q1ygIJWlL0bAkc5V9 ca@example.com";
+    const TEST_CA_FINGERPRINT: &str = "SHA256:4UYT6thsPirHGnnUC5T91Aj/ba1y65PCqMUl+qsE4qo";
+
+    #[test]
+    fn user_cert_parse_to_string() {
+        let cert = Certificate::parse(TEST_USER_CERT).unwrap();
+        assert_eq!(TEST_USER_CERT, cert.to_string());
+
+        let uncommented = TEST_USER_CERT.trim_end_matches(" demos@siril");
+        assert_eq!(uncommented, Certificate::parse(uncommented).unwrap().to_string());
+    }
+
+    #[test]
+    fn user_cert_fields() {
+        let cert = Certificate::parse(TEST_USER_CERT).unwrap();
+        assert_eq!("ssh-ed25519-cert-v01@openssh.com", cert.keytype());
+        assert_eq!(32, cert.nonce().len());
+        assert_eq!("ssh-ed25519", cert.key().keytype());
+        match *cert.key_data() {
+            Data::Ed25519 { ref key } => assert_eq!(32, key.len()),
+            ref d => panic!("unexpected key data {:?}", d),
+        }
+        assert_eq!(42, cert.serial());
+        assert_eq!(CertType::User, cert.cert_type());
+        assert_eq!("demos user cert", cert.key_id());
+        assert_eq!(&["demos".to_string(), "root".to_string()], cert.valid_principals());
+        // 2020-01-01T00:00:00Z and 2030-01-01T00:00:00Z
+        assert_eq!(1577836800, cert.valid_after());
+        assert_eq!(1893456000, cert.valid_before());
+        assert_eq!(2, cert.critical_options().len());
+        assert_eq!(Some("/bin/true".to_string()), cert.critical_option("force-command").unwrap());
+        assert_eq!(Some("10.0.0.0/8".to_string()), cert.critical_option("source-address").unwrap());
+        assert_eq!(None, cert.critical_option("verify-required").unwrap());
+        let extensions: Vec<&str> = cert.extensions().iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(vec!["permit-X11-forwarding", "permit-agent-forwarding", "permit-port-forwarding", "permit-pty", "permit-user-rc"], extensions);
+        assert!(cert.has_extension("permit-pty"));
+        assert_eq!(Some("demos@siril"), cert.comment());
+    }
+
+    #[test]
+    fn user_cert_fingerprints() {
+        let cert = Certificate::parse(TEST_USER_CERT).unwrap();
+        assert_eq!("SHA256:A/lHzXxsgbp11dcKKfSDyNQIdep7EQgZEoRYVDBfNdI", cert.fingerprint());
+        assert_eq!(TEST_CA_FINGERPRINT, cert.signature_key().fingerprint());
+    }
+
+    #[test]
+    fn host_cert() {
+        let cert = Certificate::parse(TEST_HOST_CERT).unwrap();
+        assert_eq!(TEST_HOST_CERT, cert.to_string());
+        assert_eq!(CertType::Host, cert.cert_type());
+        assert_eq!(&["host.example.com".to_string(), "*.example.org".to_string()], cert.valid_principals());
+        assert_eq!(0, cert.valid_after());
+        assert_eq!(u64::MAX, cert.valid_before());
+        assert!(cert.critical_options().is_empty());
+        assert!(cert.extensions().is_empty());
+        assert_eq!("SHA256:YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o", cert.fingerprint());
+        assert_eq!(TEST_CA_FINGERPRINT, cert.signature_key().fingerprint());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        // a plain key isn't a certificate
+        assert!(Certificate::parse("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/").is_err());
+        // the keytype has to match the data
+        let mismatched = TEST_USER_CERT.replacen("ssh-ed25519-cert", "ssh-rsa-cert", 1);
+        assert!(Certificate::parse(&mismatched).is_err());
+        // truncated data
+        let cert = Certificate::parse(TEST_USER_CERT).unwrap();
+        let data = cert.data();
+        assert!(Certificate::from_blob(&data[..data.len() - 1]).is_err());
+    }
+
+    fn rejection(r: Result<()>) -> Rejection {
+        match r {
+            Err(Error(ErrorKind::CertificateRejected(r), _)) => r,
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn validate_user_cert() {
+        let cert = Certificate::parse(TEST_USER_CERT).unwrap();
+        let ca = [PublicKey::parse(TEST_CA_KEY).unwrap()];
+        let other = PublicKey::parse("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINcpQ9CVied9hMxFn+IBOAosk7sYHeg4a8hU1/2dU6fZ").unwrap();
+        // 2025-01-01T00:00:00Z
+        let now = 1735689600;
+
+        cert.validate(CertType::User, "demos", &[other.clone(), ca[0].clone()], now).unwrap();
+        cert.validate(CertType::User, "root", &ca, now).unwrap();
+        assert_eq!(Rejection::WrongType(CertType::User), rejection(cert.validate(CertType::Host, "demos", &ca, now)));
+        assert_eq!(Rejection::UntrustedCa, rejection(cert.validate(CertType::User, "demos", &[other], now)));
+        assert_eq!(Rejection::UntrustedCa, rejection(cert.validate(CertType::User, "demos", &[], now)));
+        assert_eq!(Rejection::NotYetValid, rejection(cert.validate(CertType::User, "demos", &ca, 1577836799)));
+        cert.validate(CertType::User, "demos", &ca, 1577836800).unwrap();
+        assert_eq!(Rejection::Expired, rejection(cert.validate(CertType::User, "demos", &ca, 1893456000)));
+        assert_eq!(Rejection::PrincipalNotAllowed("demo".into()), rejection(cert.validate(CertType::User, "demo", &ca, now)));
+        // user principals don't have wildcards
+        assert_eq!(Rejection::PrincipalNotAllowed("*".into()), rejection(cert.validate(CertType::User, "*", &ca, now)));
+    }
+
+    #[test]
+    fn validate_signature() {
+        let mut cert = Certificate::parse(TEST_USER_CERT).unwrap();
+        let ca = [PublicKey::parse(TEST_CA_KEY).unwrap()];
+        cert.valid_principals.push("admin".into());
+        assert_eq!(Rejection::BadSignature, rejection(cert.validate(CertType::User, "admin", &ca, 1735689600)));
+    }
+
+    #[test]
+    fn validate_ca_signature_algorithm() {
+        let ca = [PublicKey::parse(include_str!("../fixtures/rsa.pub")).unwrap()];
+        let cert = Certificate::parse(TEST_RSA_SHA512_CA_CERT).unwrap();
+        cert.validate(CertType::User, "demos", &ca, 0).unwrap();
+        let cert = Certificate::parse(TEST_SSH_RSA_CA_CERT).unwrap();
+        assert_eq!(Rejection::SignatureAlgorithmNotAllowed("ssh-rsa".into()), rejection(cert.validate(CertType::User, "demos", &ca, 0)));
+    }
+
+    #[test]
+    fn validate_host_cert() {
+        let cert = Certificate::parse(TEST_HOST_CERT).unwrap();
+        let ca = [PublicKey::parse(TEST_CA_KEY).unwrap()];
+        cert.validate(CertType::Host, "host.example.com", &ca, 0).unwrap();
+        cert.validate(CertType::Host, "www.example.org", &ca, u64::MAX - 1).unwrap();
+        assert_eq!(Rejection::PrincipalNotAllowed("example.org".into()), rejection(cert.validate(CertType::Host, "example.org", &ca, 0)));
+        assert_eq!(Rejection::WrongType(CertType::Host), rejection(cert.validate(CertType::User, "host.example.com", &ca, 0)));
+    }
+
+    #[test]
+    fn validate_options_and_principals() {
+        let ca = [PublicKey::parse(TEST_CA_KEY).unwrap()];
+        let cert = Certificate::parse(TEST_CRITICAL_CERT).unwrap();
+        assert_eq!(Rejection::UnknownCriticalOption("unknown@example.com".into()), rejection(cert.validate(CertType::User, "demos", &ca, 0)));
+
+        // sshd won't accept user certificates without principals
+        let cert = Certificate::parse(TEST_NO_PRINCIPALS_CERT).unwrap();
+        assert_eq!(Rejection::NoPrincipals, rejection(cert.validate(CertType::User, "demos", &ca, 0)));
+    }
+
+    // the private key of TEST_CA_KEY
+    const TEST_CA_SEED: [u8; 32] = [
+        0x4e, 0xc1, 0xeb, 0x49, 0xe7, 0xab, 0x32, 0xdd, 0x6a, 0xc5, 0xb7, 0x8b, 0x62, 0xcc, 0x1d, 0x13,
+        0xde, 0xa6, 0x21, 0xa9, 0xa8, 0x99, 0x70, 0x3e, 0x95, 0x40, 0x58, 0xec, 0x86, 0xd3, 0x2a, 0xf9,
+    ];
+
+    fn sign_ed25519(data: &[u8]) -> Result<Vec<u8>> {
+        let (secret, _) = ed25519::keypair(&TEST_CA_SEED);
+        let mut writer = Writer::new();
+        writer.write_string("ssh-ed25519");
+        writer.write_bytes(ed25519::signature(data, &secret).to_vec());
+        Ok(writer.into_vec())
+    }
+
+    #[test]
+    fn build_user_cert() {
+        let ca = [PublicKey::parse(TEST_CA_KEY).unwrap()];
+        let parsed = Certificate::parse(TEST_USER_CERT).unwrap();
+        let cert = CertificateBuilder::new(parsed.key().clone(), CertType::User)
+            .serial(42)
+            .key_id("demos user cert")
+            .principal("demos")
+            .principal("root")
+            .valid_after(1577836800)
+            .valid_before(1893456000)
+            .critical_option("source-address", "10.0.0.0/8")
+            .critical_option("force-command", "/bin/true")
+            .sign_with(&ca[0], sign_ed25519)
+            .unwrap();
+        cert.validate(CertType::User, "root", &ca, 1735689600).unwrap();
+
+        // everything but the nonce and the signature is the same as what
+        // ssh-keygen made
+        assert_eq!(NONCE_LEN, cert.nonce().len());
+        let body = cert.body();
+        let expected = parsed.body();
+        assert_eq!(expected.len(), body.len());
+        assert_eq!(expected[..36], body[..36]);
+        assert_eq!(expected[72..], body[72..]);
+
+        // and it can be read back
+        let cert = Certificate::parse(&cert.to_string()).unwrap();
+        cert.validate(CertType::User, "demos", &ca, 1735689600).unwrap();
+    }
+
+    #[test]
+    fn build_host_cert() {
+        let ca = [PublicKey::parse(TEST_CA_KEY).unwrap()];
+        let key = PublicKey::parse("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINcpQ9CVied9hMxFn+IBOAosk7sYHeg4a8hU1/2dU6fZ host").unwrap();
+        let cert = CertificateBuilder::new(key, CertType::Host)
+            .principal("*.example.com")
+            .sign_with(&ca[0], sign_ed25519)
+            .unwrap();
+        assert_eq!(Some("host"), cert.comment());
+        assert!(cert.extensions().is_empty());
+        assert_eq!(u64::MAX, cert.valid_before());
+        cert.validate(CertType::Host, "www.example.com", &ca, 1735689600).unwrap();
+    }
+
+    #[test]
+    fn build_options() {
+        let ca = PublicKey::parse(TEST_CA_KEY).unwrap();
+        let key = Certificate::parse(TEST_USER_CERT).unwrap().key().clone();
+        let cert = CertificateBuilder::new(key, CertType::User)
+            .remove_extension("permit-pty")
+            .extension("no-touch-required")
+            .critical_option("verify-required", "")
+            .sign_with(&ca, sign_ed25519)
+            .unwrap();
+        let extensions: Vec<&str> = cert.extensions().iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(vec!["no-touch-required", "permit-X11-forwarding", "permit-agent-forwarding", "permit-port-forwarding", "permit-user-rc"], extensions);
+        assert_eq!(Some(String::new()), cert.critical_option("verify-required").unwrap());
+        assert!(cert.critical_options()[0].1.is_empty());
+    }
+
+    #[test]
+    fn build_bad_signature() {
+        let ca = PublicKey::parse(TEST_CA_KEY).unwrap();
+        let other = PublicKey::parse("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINcpQ9CVied9hMxFn+IBOAosk7sYHeg4a8hU1/2dU6fZ").unwrap();
+        // the signature has to be made by the CA key
+        let result = CertificateBuilder::new(ca, CertType::User).clear().sign_with(&other, sign_ed25519);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn build_with_private_key() {
+        let key = PublicKey::parse("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINcpQ9CVied9hMxFn+IBOAosk7sYHeg4a8hU1/2dU6fZ").unwrap();
+        for ca in &[include_str!("../fixtures/rsa"), include_str!("../fixtures/ecdsa"), include_str!("../fixtures/ed25519")] {
+            let ca = PrivateKey::parse(ca).unwrap();
+            let cert = CertificateBuilder::new(key.clone(), CertType::User).principal("demos").sign(&ca).unwrap();
+            cert.validate(CertType::User, "demos", &[ca.public_key().clone()], 1735689600).unwrap();
+        }
+        let rsa = PrivateKey::parse(include_str!("../fixtures/rsa")).unwrap();
+        let cert = CertificateBuilder::new(key, CertType::User).sign(&rsa).unwrap();
+        let mut reader = Reader::new(cert.signature());
+        assert_eq!("rsa-sha2-512", reader.read_string().unwrap());
+    }
+
+    #[test]
+    fn security_key_certs() {
+        let ca = [PublicKey::parse(include_str!("../fixtures/ed25519.pub")).unwrap()];
+        for &(cert, keytype) in &[
+            (TEST_SK_ED25519_CERT, "sk-ssh-ed25519@openssh.com"),
+            (TEST_SK_ECDSA_CERT, "sk-ecdsa-sha2-nistp256@openssh.com"),
+        ] {
+            let parsed = Certificate::parse(cert).unwrap();
+            assert_eq!(cert, parsed.to_string());
+            assert_eq!(keytype, parsed.key().keytype());
+            parsed.validate(CertType::User, "demos", &ca, 1735689600).unwrap();
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! cipher
+//!
+//! this module provides the ciphers OpenSSH private keys can be encrypted
+//! with. they are used the same way as for the ssh transport, with a sequence
+//! number of zero and no additional authenticated data. see
+//! https://github.com/openssh/openssh-portable/blob/master/cipher.c and
+//! https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.chacha20poly1305
+//!
+//! it also provides the aes-cbc decryption used by encrypted PKCS#8 keys.
+
+use errors::*;
+
+use crypto::aead::{AeadDecryptor, AeadEncryptor};
+use crypto::aes::{self, KeySize};
+use crypto::aes_gcm::AesGcm;
+use crypto::blockmodes::PkcsPadding;
+use crypto::buffer::{RefReadBuffer, RefWriteBuffer, WriteBuffer};
+use crypto::chacha20::ChaCha20;
+use crypto::mac::{Mac, MacResult};
+use crypto::poly1305::Poly1305;
+use crypto::symmetriccipher::SynchronousStreamCipher;
+
+use std::fmt;
+
+const AES256_CTR: &str = "aes256-ctr";
+const AES256_GCM: &str = "aes256-gcm@openssh.com";
+const CHACHA20_POLY1305: &str = "chacha20-poly1305@openssh.com";
+
+/// Cipher is a cipher used to encrypt private keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cipher {
+    Aes256Ctr,
+    Aes256Gcm,
+    ChaCha20Poly1305,
+}
+
+impl Cipher {
+    pub(crate) fn get(name: &str) -> Result<Self> {
+        Ok(match name {
+            AES256_CTR => Cipher::Aes256Ctr,
+            AES256_GCM => Cipher::Aes256Gcm,
+            CHACHA20_POLY1305 => Cipher::ChaCha20Poly1305,
+            _ => return Err(ErrorKind::UnsupportedCipher(name.to_string()).into()),
+        })
+    }
+
+    /// name returns the name openssh uses for the cipher
+    pub fn name(self) -> &'static str {
+        match self {
+            Cipher::Aes256Ctr => AES256_CTR,
+            Cipher::Aes256Gcm => AES256_GCM,
+            Cipher::ChaCha20Poly1305 => CHACHA20_POLY1305,
+        }
+    }
+
+    pub(crate) fn key_len(self) -> usize {
+        match self {
+            Cipher::Aes256Ctr | Cipher::Aes256Gcm => 32,
+            // chacha20-poly1305 has a second key for the packet length,
+            // which isn't used here
+            Cipher::ChaCha20Poly1305 => 64,
+        }
+    }
+
+    pub(crate) fn iv_len(self) -> usize {
+        match self {
+            Cipher::Aes256Ctr => 16,
+            Cipher::Aes256Gcm => 12,
+            // the nonce is the sequence number
+            Cipher::ChaCha20Poly1305 => 0,
+        }
+    }
+
+    pub(crate) fn block_size(self) -> usize {
+        match self {
+            Cipher::Aes256Ctr | Cipher::Aes256Gcm => 16,
+            Cipher::ChaCha20Poly1305 => 8,
+        }
+    }
+
+    /// tag_len is the length of the authentication tag that follows the
+    /// encrypted data
+    pub(crate) fn tag_len(self) -> usize {
+        match self {
+            Cipher::Aes256Ctr => 0,
+            Cipher::Aes256Gcm | Cipher::ChaCha20Poly1305 => 16,
+        }
+    }
+
+    /// encrypt encrypts data, which must be a multiple of the block size,
+    /// and returns the encrypted data and the authentication tag.
+    pub(crate) fn encrypt(self, key: &[u8], iv: &[u8], data: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut out = vec![0; data.len()];
+        let mut tag = vec![0; self.tag_len()];
+        match self {
+            Cipher::Aes256Ctr => aes::ctr(KeySize::KeySize256, key, iv).process(data, &mut out),
+            Cipher::Aes256Gcm => AesGcm::new(KeySize::KeySize256, key, iv, &[]).encrypt(data, &mut out, &mut tag),
+            Cipher::ChaCha20Poly1305 => {
+                let (mut chacha, mut poly) = chacha20_poly1305(key);
+                chacha.process(data, &mut out);
+                poly.input(&out);
+                poly.raw_result(&mut tag);
+            },
+        }
+        (out, tag)
+    }
+
+    /// decrypt decrypts data, checking the authentication tag. a tag that
+    /// doesn't match means the key was wrong, which gives an
+    /// IncorrectPassphrase error.
+    pub(crate) fn decrypt(self, key: &[u8], iv: &[u8], data: &[u8], tag: &[u8]) -> Result<Vec<u8>> {
+        let mut out = vec![0; data.len()];
+        let valid = match self {
+            Cipher::Aes256Ctr => {
+                aes::ctr(KeySize::KeySize256, key, iv).process(data, &mut out);
+                true
+            },
+            Cipher::Aes256Gcm => AesGcm::new(KeySize::KeySize256, key, iv, &[]).decrypt(data, &mut out, tag),
+            Cipher::ChaCha20Poly1305 => {
+                let (mut chacha, mut poly) = chacha20_poly1305(key);
+                poly.input(data);
+                if poly.result() == MacResult::new(tag) {
+                    chacha.process(data, &mut out);
+                    true
+                } else {
+                    false
+                }
+            },
+        };
+        if !valid {
+            return Err(ErrorKind::IncorrectPassphrase.into());
+        }
+        Ok(out)
+    }
+}
+
+impl fmt::Display for Cipher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// aes_cbc_decrypt decrypts data with aes in cbc mode, using the key size
+/// of key, and removes the PKCS#7 padding from
+/// https://tools.ietf.org/html/rfc8018#section-6.1.1. padding that isn't
+/// valid means the key was wrong, which gives an IncorrectPassphrase error.
+pub(crate) fn aes_cbc_decrypt(key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let key_size = match key.len() {
+        16 => KeySize::KeySize128,
+        24 => KeySize::KeySize192,
+        32 => KeySize::KeySize256,
+        _ => return Err(ErrorKind::InvalidFormat.into()),
+    };
+    if iv.len() != 16 || data.is_empty() || !data.len().is_multiple_of(16) {
+        return Err(ErrorKind::InvalidFormat.into());
+    }
+    let mut out = vec![0; data.len()];
+    let len = {
+        let mut decryptor = aes::cbc_decryptor(key_size, key, iv, PkcsPadding);
+        let mut output = RefWriteBuffer::new(&mut out);
+        decryptor
+            .decrypt(&mut RefReadBuffer::new(data), &mut output, true)
+            .map_err(|_| ErrorKind::IncorrectPassphrase)?;
+        output.position()
+    };
+    out.truncate(len);
+    Ok(out)
+}
+
+/// chacha20_poly1305 sets up the stream cipher and the mac for
+/// chacha20-poly1305@openssh.com with a sequence number of zero. the first
+/// block of the key stream is the poly1305 key, and the data is encrypted
+/// with the rest of it.
+fn chacha20_poly1305(key: &[u8]) -> (ChaCha20, Poly1305) {
+    let mut chacha = ChaCha20::new(&key[..32], &[0; 8]);
+    let mut block = [0; 64];
+    chacha.process(&[0; 64], &mut block);
+    (chacha, Poly1305::new(&block[..32]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let key: Vec<u8> = (0..64).collect();
+        let iv: Vec<u8> = (0..16).collect();
+        let data: Vec<u8> = (0..48).collect();
+        for &cipher in &[Cipher::Aes256Ctr, Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305] {
+            assert_eq!(cipher, Cipher::get(cipher.name()).unwrap());
+            let key = &key[..cipher.key_len()];
+            let iv = &iv[..cipher.iv_len()];
+            let (encrypted, tag) = cipher.encrypt(key, iv, &data);
+            assert_ne!(data, encrypted);
+            assert_eq!(cipher.tag_len(), tag.len());
+            assert_eq!(data, cipher.decrypt(key, iv, &encrypted, &tag).unwrap());
+        }
+    }
+
+    #[test]
+    fn bad_tag() {
+        let key = [7; 64];
+        let data = [1; 32];
+        for &cipher in &[Cipher::Aes256Gcm, Cipher::ChaCha20Poly1305] {
+            let iv = &[0; 12][..cipher.iv_len()];
+            let (mut encrypted, tag) = cipher.encrypt(&key[..cipher.key_len()], iv, &data);
+            encrypted[0] ^= 1;
+            match cipher.decrypt(&key[..cipher.key_len()], iv, &encrypted, &tag) {
+                Err(Error(ErrorKind::IncorrectPassphrase, _)) => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn cbc() {
+        // from https://tools.ietf.org/html/rfc3602#section-4, case #2, with
+        // a block of padding added
+        let key = [0xc2, 0x86, 0x69, 0x6d, 0x88, 0x7c, 0x9a, 0xa0, 0x61, 0x1b, 0xbb, 0x3e, 0x20, 0x25, 0xa4, 0x5a];
+        let iv = [0x56, 0x2e, 0x17, 0x99, 0x6d, 0x09, 0x3d, 0x28, 0xdd, 0xb3, 0xba, 0x69, 0x5a, 0x2e, 0x6f, 0x58];
+        let plain: Vec<u8> = (0..32).collect();
+        let encrypted = [
+            0xd2, 0x96, 0xcd, 0x94, 0xc2, 0xcc, 0xcf, 0x8a, 0x3a, 0x86, 0x30, 0x28, 0xb5, 0xe1, 0xdc, 0x0a,
+            0x75, 0x86, 0x60, 0x2d, 0x25, 0x3c, 0xff, 0xf9, 0x1b, 0x82, 0x66, 0xbe, 0xa6, 0xd6, 0x1a, 0xb1,
+            0xbc, 0xfd, 0x81, 0x02, 0x22, 0x02, 0x36, 0x6b, 0xde, 0x6d, 0xd2, 0x60, 0xa1, 0x58, 0x41, 0xa1,
+        ];
+        assert_eq!(plain, aes_cbc_decrypt(&key, &iv, &encrypted).unwrap());
+        match aes_cbc_decrypt(&[0; 16], &iv, &encrypted) {
+            Err(Error(ErrorKind::IncorrectPassphrase, _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(Cipher::get("3des-cbc").is_err());
+    }
+}
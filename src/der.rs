@@ -0,0 +1,277 @@
+//! der
+//!
+//! this module provides a minimal reader and writer for the subset of the ASN.1
+//! DER encoding used by key formats like PKCS#1, PKCS#8, SEC1, and X.509. see
+//! https://www.itu.int/rec/T-REC-X.690 for the encoding rules.
+
+use errors::*;
+
+pub const BOOLEAN: u8 = 0x01;
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OID: u8 = 0x06;
+pub const SEQUENCE: u8 = 0x30;
+
+pub struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &[u8]) -> Reader<'_> {
+        Reader { data, offset: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    /// peek_tag returns the tag of the next element without reading it, for
+    /// optional elements.
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.get(self.offset).cloned()
+    }
+
+    /// read_tlv reads the next element, returning its tag and contents
+    pub fn read_tlv(&mut self) -> Result<(u8, &'a [u8])> {
+        let cur = &self.data[self.offset..];
+        if cur.len() < 2 {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        let tag = cur[0];
+        // lengths below 128 are stored directly, longer ones are stored as
+        // the number of length bytes that follow with the high bit set.
+        let (len, header) = if cur[1] & 0x80 == 0 {
+            (cur[1] as usize, 2)
+        } else {
+            let n = (cur[1] & 0x7f) as usize;
+            if n == 0 || n > 4 || cur.len() < 2 + n {
+                return Err(ErrorKind::InvalidFormat.into());
+            }
+            let len = cur[2..2 + n].iter().fold(0, |len, &b| (len << 8) | b as usize);
+            (len, 2 + n)
+        };
+        if cur.len() < header + len {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        self.offset += header + len;
+        Ok((tag, &cur[header..header + len]))
+    }
+
+    /// read_raw reads the next element and returns its whole encoding,
+    /// for signed structures like the tbsCertificate of a certificate.
+    pub fn read_raw(&mut self) -> Result<&'a [u8]> {
+        let start = self.offset;
+        self.read_tlv()?;
+        Ok(&self.data[start..self.offset])
+    }
+
+    /// read reads the next element, which must have the given tag
+    pub fn read(&mut self, tag: u8) -> Result<&'a [u8]> {
+        let (t, contents) = self.read_tlv()?;
+        if t != tag {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        Ok(contents)
+    }
+
+    pub fn read_sequence(&mut self) -> Result<Reader<'a>> {
+        let contents = self.read(SEQUENCE)?;
+        Ok(Reader {
+            data: contents,
+            offset: 0,
+        })
+    }
+
+    /// read_boolean reads a boolean, which DER encodes as 0x00 or 0xff
+    pub fn read_boolean(&mut self) -> Result<bool> {
+        match self.read(BOOLEAN)? {
+            [0x00] => Ok(false),
+            [0xff] => Ok(true),
+            _ => Err(ErrorKind::InvalidFormat.into()),
+        }
+    }
+
+    /// read_integer reads a non-negative integer, returning its big-endian
+    /// bytes without any leading zeros.
+    pub fn read_integer(&mut self) -> Result<&'a [u8]> {
+        let mut bytes = self.read(INTEGER)?;
+        if bytes.is_empty() || bytes[0] & 0x80 != 0 {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        while bytes.len() > 1 && bytes[0] == 0 {
+            bytes = &bytes[1..];
+        }
+        Ok(bytes)
+    }
+
+    /// read_oid reads an object identifier, returning its encoded contents
+    pub fn read_oid(&mut self) -> Result<&'a [u8]> {
+        self.read(OID)
+    }
+
+    /// read_bit_string reads a bit string, which must be a whole number of
+    /// bytes.
+    pub fn read_bit_string(&mut self) -> Result<&'a [u8]> {
+        let bytes = self.read(BIT_STRING)?;
+        if bytes.first() != Some(&0) {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        Ok(&bytes[1..])
+    }
+
+    pub fn read_octet_string(&mut self) -> Result<&'a [u8]> {
+        self.read(OCTET_STRING)
+    }
+
+    pub fn read_null(&mut self) -> Result<()> {
+        if !self.read(NULL)?.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        Ok(())
+    }
+}
+
+pub struct Writer {
+    data: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        Writer { data: vec![] }
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn write_tlv(&mut self, tag: u8, contents: &[u8]) {
+        self.data.push(tag);
+        let len = contents.len();
+        if len < 0x80 {
+            self.data.push(len as u8);
+        } else {
+            let bytes: Vec<u8> = (0..4)
+                .rev()
+                .map(|i| (len >> (i * 8)) as u8)
+                .skip_while(|&b| b == 0)
+                .collect();
+            self.data.push(0x80 | bytes.len() as u8);
+            self.data.extend_from_slice(&bytes);
+        }
+        self.data.extend_from_slice(contents);
+    }
+
+    /// write_sequence writes a sequence whose contents are written by f
+    pub fn write_sequence<F: FnOnce(&mut Writer)>(&mut self, f: F) {
+        self.write_constructed(SEQUENCE, f)
+    }
+
+    /// write_constructed writes an element with the given tag whose contents
+    /// are written by f
+    pub fn write_constructed<F: FnOnce(&mut Writer)>(&mut self, tag: u8, f: F) {
+        let mut inner = Writer::new();
+        f(&mut inner);
+        self.write_tlv(tag, &inner.data)
+    }
+
+    /// write_integer writes a non-negative integer from its big-endian bytes
+    pub fn write_integer(&mut self, num: &[u8]) {
+        let mut num: Vec<u8> = num.iter().cloned().skip_while(|&b| b == 0).collect();
+        // like ssh mpints, DER integers are two's complement so positive
+        // numbers with the high bit set need a leading zero.
+        if num.first().is_none_or(|&b| b & 0x80 != 0) {
+            num.insert(0, 0);
+        }
+        self.write_tlv(INTEGER, &num)
+    }
+
+    pub fn write_oid(&mut self, oid: &[u8]) {
+        self.write_tlv(OID, oid)
+    }
+
+    pub fn write_bit_string(&mut self, bytes: &[u8]) {
+        let mut contents = vec![0];
+        contents.extend_from_slice(bytes);
+        self.write_tlv(BIT_STRING, &contents)
+    }
+
+    pub fn write_octet_string(&mut self, bytes: &[u8]) {
+        self.write_tlv(OCTET_STRING, bytes)
+    }
+
+    pub fn write_null(&mut self) {
+        self.write_tlv(NULL, &[])
+    }
+}
+
+/// oid_to_string formats the encoded contents of an object identifier in the
+/// usual dotted form, for error messages.
+pub fn oid_to_string(oid: &[u8]) -> String {
+    let mut parts = vec![];
+    let mut n: u64 = 0;
+    for &b in oid {
+        n = (n << 7) | u64::from(b & 0x7f);
+        if b & 0x80 == 0 {
+            if parts.is_empty() {
+                // the first byte holds the first two components
+                let first = ::std::cmp::min(n / 40, 2);
+                parts.push(first);
+                parts.push(n - first * 40);
+            } else {
+                parts.push(n);
+            }
+            n = 0;
+        }
+    }
+    parts.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let long = vec![0xab; 300];
+        let mut writer = Writer::new();
+        writer.write_sequence(|w| {
+            w.write_integer(&[0x00, 0x00, 0x80]);
+            w.write_integer(&[0x00]);
+            w.write_bit_string(&long);
+            w.write_null();
+            w.write_bit_string(&[1, 2, 3]);
+            w.write_octet_string(&[4, 5]);
+        });
+        let out = writer.into_vec();
+        assert_eq!(&[0x30, 0x82, 0x01, 0x44, 0x02, 0x02, 0x00, 0x80, 0x02, 0x01, 0x00], &out[..11]);
+
+        let mut reader = Reader::new(&out);
+        let mut seq = reader.read_sequence().unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(&[0x80], seq.read_integer().unwrap());
+        assert_eq!(&[0x00], seq.read_integer().unwrap());
+        assert_eq!(&long[..], seq.read_bit_string().unwrap());
+        seq.read_null().unwrap();
+        assert_eq!(&[1, 2, 3], seq.read_bit_string().unwrap());
+        assert_eq!(Some(OCTET_STRING), seq.peek_tag());
+        assert_eq!(&[4, 5], seq.read_octet_string().unwrap());
+        assert!(seq.is_empty());
+        assert_eq!(None, seq.peek_tag());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Reader::new(&[0x30, 0x05, 0x00]).read_sequence().is_err());
+        assert!(Reader::new(&[0x02, 0x01, 0x80]).read_integer().is_err());
+        assert!(Reader::new(&[0x02, 0x01, 0x01]).read_null().is_err());
+    }
+
+    #[test]
+    fn oid_string() {
+        assert_eq!("1.2.840.10045.3.1.7", oid_to_string(&[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07]));
+        assert_eq!("1.3.101.112", oid_to_string(&[0x2b, 0x65, 0x70]));
+    }
+}
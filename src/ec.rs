@@ -0,0 +1,142 @@
+//! ec
+//!
+//! this module provides the elliptic curve arithmetic needed for ecdsa
+//! private keys, which openssh stores as just the private scalar.
+
+use errors::*;
+
+use Curve;
+
+use ecdsa::hazmat::{bits2field, sign_prehashed, VerifyPrimitive};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p521::elliptic_curve::PrimeField;
+use p521::NistP521;
+use rand::{CryptoRng, RngCore};
+use rfc6979::HmacDrbg;
+use sha2::Sha512;
+
+/// field_size returns the size in bytes of the field elements of curve,
+/// which is also the size of its scalars.
+pub fn field_size(curve: &Curve) -> usize {
+    match *curve {
+        Curve::Nistp256 => 32,
+        Curve::Nistp384 => 48,
+        Curve::Nistp521 => 66,
+    }
+}
+
+/// pad left pads a big-endian integer with zeros to len bytes, or returns
+/// None if it doesn't fit.
+pub fn pad(num: &[u8], len: usize) -> Option<Vec<u8>> {
+    if num.len() > len {
+        return None;
+    }
+    let mut out = vec![0; len - num.len()];
+    out.extend_from_slice(num);
+    Some(out)
+}
+
+/// public_point returns the uncompressed encoding of the public point for
+/// the private scalar d on curve, in the format ssh uses for ecdsa keys.
+pub fn public_point(curve: &Curve, d: &[u8]) -> Result<Vec<u8>> {
+    let d = pad(d, field_size(curve)).ok_or(ErrorKind::InvalidFormat)?;
+    let point = match *curve {
+        Curve::Nistp256 => p256::SecretKey::from_slice(&d).map(|k| k.public_key().to_encoded_point(false).as_bytes().to_vec()),
+        Curve::Nistp384 => p384::SecretKey::from_slice(&d).map(|k| k.public_key().to_encoded_point(false).as_bytes().to_vec()),
+        Curve::Nistp521 => p521::SecretKey::from_slice(&d).map(|k| k.public_key().to_encoded_point(false).as_bytes().to_vec()),
+    };
+    // zero, or a scalar that isn't less than the order of the curve
+    point.map_err(|_| ErrorKind::InvalidFormat.into())
+}
+
+/// decode_point checks that point is on curve and returns it in the
+/// uncompressed form ssh uses. point can be compressed or uncompressed, as
+/// described in section 2.3.4 of https://www.secg.org/sec1-v2.pdf
+pub fn decode_point(curve: &Curve, point: &[u8]) -> Result<Vec<u8>> {
+    let point = match *curve {
+        Curve::Nistp256 => p256::PublicKey::from_sec1_bytes(point).map(|k| k.to_encoded_point(false).as_bytes().to_vec()),
+        Curve::Nistp384 => p384::PublicKey::from_sec1_bytes(point).map(|k| k.to_encoded_point(false).as_bytes().to_vec()),
+        Curve::Nistp521 => p521::PublicKey::from_sec1_bytes(point).map(|k| k.to_encoded_point(false).as_bytes().to_vec()),
+    };
+    point.map_err(|_| ErrorKind::InvalidFormat.into())
+}
+
+/// generate returns a random private scalar for curve, without leading
+/// zeros the way openssh stores it, and the matching public point.
+pub fn generate<R: RngCore + CryptoRng>(curve: &Curve, rng: &mut R) -> (Vec<u8>, Vec<u8>) {
+    let d = match *curve {
+        Curve::Nistp256 => p256::SecretKey::random(rng).to_bytes().to_vec(),
+        Curve::Nistp384 => p384::SecretKey::random(rng).to_bytes().to_vec(),
+        Curve::Nistp521 => p521::SecretKey::random(rng).to_bytes().to_vec(),
+    };
+    let d: Vec<u8> = d.into_iter().skip_while(|&b| b == 0).collect();
+    // the scalar came from the curve's own key generation, so it's valid
+    let point = public_point(curve, &d).unwrap();
+    (d, point)
+}
+
+/// sign_p521 makes an ecdsa signature on p-521 of a sha-512 hash with the
+/// private scalar d, and returns r and s as 66 byte integers. the signature
+/// is checked before it's returned.
+///
+/// k is generated as described in section 3.2 of
+/// https://tools.ietf.org/html/rfc6979. rfc6979::generate_k only takes
+/// hashes the size of the scalars, which sha-512 isn't for p-521, so its
+/// hmac_drbg is used directly.
+pub fn sign_p521(d: &[u8], hashed: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let key = p521::SecretKey::from_slice(d).map_err(|_| ErrorKind::InvalidFormat)?;
+    // sha-512 hashes are shorter than the order, so bits2int leaves them
+    // as they are and they never need reducing
+    let z = bits2field::<NistP521>(hashed).map_err(|_| ErrorKind::InvalidFormat)?;
+    let mut drbg = HmacDrbg::<Sha512>::new(&key.to_bytes(), &z, &[]);
+    loop {
+        let mut t = p521::FieldBytes::default();
+        drbg.fill_bytes(&mut t);
+        // bits2int keeps the leftmost 521 of the 528 bits
+        for i in (0..t.len()).rev() {
+            t[i] = (t[i] >> 7) | if i > 0 { t[i - 1] << 1 } else { 0 };
+        }
+        // zero, or not less than the order
+        let k = match Option::<p521::Scalar>::from(p521::Scalar::from_repr(t)) {
+            Some(k) if !bool::from(k.is_zero()) => k,
+            _ => continue,
+        };
+        // r or s came out zero
+        let sig = match sign_prehashed::<NistP521, _>(&key.to_nonzero_scalar(), k, &z) {
+            Ok((sig, _)) => sig,
+            Err(_) => continue,
+        };
+        key.public_key()
+            .as_affine()
+            .verify_prehashed(&z, &sig)
+            .map_err(|_| ErrorKind::InvalidFormat)?;
+        let (r, s) = sig.split_bytes();
+        return Ok((r.to_vec(), s.to_vec()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto::digest::Digest;
+    use crypto::sha2::Sha512;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn rfc6979_p521() {
+        // the P-521 and SHA-512 test vector from
+        // https://tools.ietf.org/html/rfc6979#appendix-A.2.7
+        let d = unhex("00FAD06DAA62BA3B25D2FB40133DA757205DE67F5BB0018FEE8C86E1B68C7E75CAA896EB32F1F47C70855836A6D16FCC1466F6D8FBEC67DB89EC0C08B0E996B83538");
+        let mut hasher = Sha512::new();
+        hasher.input(b"sample");
+        let mut hashed = [0; 64];
+        hasher.result(&mut hashed);
+
+        let (r, s) = sign_p521(&d, &hashed).unwrap();
+        assert_eq!(unhex("00C328FAFCBD79DD77850370C46325D987CB525569FB63C5D3BC53950E6D4C5F174E25A1EE9017B5D450606ADD152B534931D7D4E8455CC91F9B15BF05EC36E377FA"), r);
+        assert_eq!(unhex("00617CCE7CF5064806C467F678D3B4080D6F1CC50AF26CA209417308281B68AF282623EAA63E5B5C0723D8B8C37FF0777B1A20F8CCB1DCCC43997F1EE0E44DA4A67A"), s);
+    }
+}
@@ -0,0 +1,402 @@
+//! fingerprint
+//!
+//! this module provides the hashes and encodings ssh-keygen can print key
+//! fingerprints with. see fingerprint_b64 and fingerprint_hex in
+//! https://github.com/openssh/openssh-portable/blob/master/sshkey.c.
+//! `Fingerprint` reads them back in any of the forms they get pasted in.
+
+use errors::*;
+
+use base64;
+use signature::hash;
+use PublicKey;
+
+use crypto::md5::Md5;
+use crypto::sha1::Sha1;
+use crypto::sha2::{Sha256, Sha512};
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+const MD5: &str = "MD5";
+const SHA1: &str = "SHA1";
+const SHA256: &str = "SHA256";
+const SHA512: &str = "SHA512";
+
+const ALGORITHMS: &[FingerprintAlgorithm] = &[
+    FingerprintAlgorithm::Md5,
+    FingerprintAlgorithm::Sha1,
+    FingerprintAlgorithm::Sha256,
+    FingerprintAlgorithm::Sha512,
+];
+
+/// FingerprintAlgorithm is the hash a fingerprint is made with, which is
+/// chosen with the `-E` option of ssh-keygen
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FingerprintAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// FingerprintEncoding is how the hash is written out in a fingerprint
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FingerprintEncoding {
+    /// lowercase hex bytes separated by colons, like `MD5:e9:a1:5b:..`
+    Hex,
+    /// base64 without padding, like `SHA256:YTw/JyJm..`
+    Base64,
+}
+
+impl FingerprintAlgorithm {
+    /// name returns the name fingerprints made with the algorithm start with
+    pub fn name(self) -> &'static str {
+        match self {
+            FingerprintAlgorithm::Md5 => MD5,
+            FingerprintAlgorithm::Sha1 => SHA1,
+            FingerprintAlgorithm::Sha256 => SHA256,
+            FingerprintAlgorithm::Sha512 => SHA512,
+        }
+    }
+
+    /// default_encoding returns the encoding ssh-keygen uses with the
+    /// algorithm, which is hex for MD5 and base64 for the others
+    pub fn default_encoding(self) -> FingerprintEncoding {
+        match self {
+            FingerprintAlgorithm::Md5 => FingerprintEncoding::Hex,
+            _ => FingerprintEncoding::Base64,
+        }
+    }
+
+    /// size returns the length of the digest in bytes
+    fn size(self) -> usize {
+        match self {
+            FingerprintAlgorithm::Md5 => 16,
+            FingerprintAlgorithm::Sha1 => 20,
+            FingerprintAlgorithm::Sha256 => 32,
+            FingerprintAlgorithm::Sha512 => 64,
+        }
+    }
+
+    /// hash returns the digest of data
+    pub(crate) fn hash(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            FingerprintAlgorithm::Md5 => hash(Md5::new(), data),
+            FingerprintAlgorithm::Sha1 => hash(Sha1::new(), data),
+            FingerprintAlgorithm::Sha256 => hash(Sha256::new(), data),
+            FingerprintAlgorithm::Sha512 => hash(Sha512::new(), data),
+        }
+    }
+}
+
+impl fmt::Display for FingerprintAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Fingerprint is a parsed key fingerprint. two fingerprints are equal when
+/// they have the same algorithm and hash, whichever encoding they were
+/// written in.
+#[derive(Clone, Debug)]
+pub struct Fingerprint {
+    algorithm: FingerprintAlgorithm,
+    encoding: FingerprintEncoding,
+    hash: Vec<u8>,
+}
+
+impl Fingerprint {
+    /// new makes a fingerprint of hash, which prints with the algorithm's
+    /// default encoding
+    pub(crate) fn new(algorithm: FingerprintAlgorithm, hash: Vec<u8>) -> Self {
+        Fingerprint {
+            algorithm,
+            encoding: algorithm.default_encoding(),
+            hash,
+        }
+    }
+
+    /// parse reads a fingerprint like `SHA256:YTw/JyJm..` or
+    /// `MD5:e9:a1:5b:..`. the algorithm name is optional and isn't case
+    /// sensitive, and hex may be written without the colons. without a name
+    /// the algorithm is worked out from the length of the hash, so a bare
+    /// base64 hash of 43 characters is SHA256 and 32 hex digits are MD5.
+    ///
+    /// ```
+    /// # use openssh_keys::{Fingerprint, FingerprintAlgorithm};
+    /// let fp = Fingerprint::parse("89b46a6c7d71f0b97daea3982567e5f7").unwrap();
+    /// assert_eq!(FingerprintAlgorithm::Md5, fp.algorithm());
+    /// assert_eq!("MD5:89:b4:6a:6c:7d:71:f0:b9:7d:ae:a3:98:25:67:e5:f7", fp.to_string());
+    /// ```
+    pub fn parse(fp: &str) -> Result<Self> {
+        let (algorithm, text) = split_algorithm(fp.trim());
+        let sizes = match algorithm {
+            Some(algorithm) => vec![algorithm.size()],
+            None => ALGORITHMS.iter().map(|a| a.size()).collect(),
+        };
+
+        let (encoding, hash) = if text.contains(':') {
+            let mut hash = Vec::new();
+            for byte in text.split(':') {
+                if byte.len() != 2 {
+                    return Err(ErrorKind::InvalidFormat.into());
+                }
+                hash.push(u8::from_str_radix(byte, 16).chain_err(|| ErrorKind::InvalidFormat)?);
+            }
+            (FingerprintEncoding::Hex, hash)
+        } else if is_hex(text) && sizes.contains(&(text.len() / 2)) && text.len() % 2 == 0 {
+            let hash = (0..text.len()).step_by(2)
+                .map(|i| u8::from_str_radix(&text[i..i + 2], 16))
+                .collect::<::std::result::Result<Vec<_>, _>>()
+                .chain_err(|| ErrorKind::InvalidFormat)?;
+            (FingerprintEncoding::Hex, hash)
+        } else {
+            // base64 0.6 wants the padding that ssh-keygen leaves off
+            let mut padded = text.trim_end_matches('=').to_string();
+            while padded.len() % 4 != 0 {
+                padded.push('=');
+            }
+            let hash = base64::decode(&padded).chain_err(|| ErrorKind::InvalidFormat)?;
+            (FingerprintEncoding::Base64, hash)
+        };
+
+        let algorithm = match algorithm {
+            Some(algorithm) if algorithm.size() == hash.len() => algorithm,
+            Some(_) => return Err(ErrorKind::InvalidFormat.into()),
+            None => *ALGORITHMS.iter().find(|a| a.size() == hash.len())
+                .ok_or(ErrorKind::InvalidFormat)?,
+        };
+        Ok(Fingerprint { algorithm, encoding, hash })
+    }
+
+    /// algorithm returns the hash the fingerprint was made with
+    pub fn algorithm(&self) -> FingerprintAlgorithm {
+        self.algorithm
+    }
+
+    /// encoding returns the encoding the fingerprint is printed with, which is
+    /// the one it was parsed from
+    pub fn encoding(&self) -> FingerprintEncoding {
+        self.encoding
+    }
+
+    /// hash returns the raw bytes of the hash
+    pub fn hash(&self) -> &[u8] {
+        &self.hash
+    }
+
+    /// matches returns whether this is the fingerprint of key, hashing the
+    /// key with the fingerprint's algorithm
+    pub fn matches(&self, key: &PublicKey) -> bool {
+        self.algorithm.hash(&key.data()) == self.hash
+    }
+
+    /// matches_prefix returns whether the fingerprint of key starts with
+    /// prefix, which is written in any of the forms `parse` accepts. without
+    /// an algorithm name every algorithm is tried.
+    pub fn matches_prefix(prefix: &str, key: &PublicKey) -> bool {
+        let (algorithm, text) = split_algorithm(prefix.trim());
+        let text = text.trim_end_matches('=');
+        if text.is_empty() {
+            return false;
+        }
+        let hex = if is_hex(&text.replace(':', "")) {
+            Some(text.replace(':', "").to_ascii_lowercase())
+        } else {
+            None
+        };
+        let algorithms = match algorithm {
+            Some(ref algorithm) => ::std::slice::from_ref(algorithm),
+            None => ALGORITHMS,
+        };
+
+        let data = key.data();
+        algorithms.iter().any(|&algorithm| {
+            let hash = algorithm.hash(&data);
+            let hex_matches = hex.as_ref().is_some_and(|hex| {
+                hash.iter().map(|b| format!("{:02x}", b)).collect::<String>().starts_with(hex.as_str())
+            });
+            hex_matches || base64::encode(&hash).starts_with(text)
+        })
+    }
+
+    /// find_prefix returns the key in keys whose fingerprint starts with
+    /// prefix, so a key can be confirmed from part of its fingerprint. it's
+    /// an error if keys that aren't the same match, and `None` if nothing
+    /// does.
+    pub fn find_prefix<'a, I>(prefix: &str, keys: I) -> Result<Option<&'a PublicKey>>
+        where I: IntoIterator<Item = &'a PublicKey>
+    {
+        let mut found: Option<&PublicKey> = None;
+        for key in keys {
+            if !Fingerprint::matches_prefix(prefix, key) {
+                continue;
+            }
+            match found {
+                Some(other) if other.data() != key.data() => {
+                    return Err(ErrorKind::AmbiguousFingerprint(prefix.into()).into());
+                },
+                Some(_) => {},
+                None => found = Some(key),
+            }
+        }
+        Ok(found)
+    }
+}
+
+impl PartialEq for Fingerprint {
+    fn eq(&self, other: &Fingerprint) -> bool {
+        self.algorithm == other.algorithm && self.hash == other.hash
+    }
+}
+
+impl Eq for Fingerprint {}
+
+impl Hash for Fingerprint {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Hash::hash(&self.algorithm, state);
+        self.hash.hash(state);
+    }
+}
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format(self.algorithm, self.encoding, &self.hash))
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Fingerprint::parse(s)
+    }
+}
+
+/// split_algorithm splits the algorithm name off the front of a fingerprint,
+/// if it has one
+fn split_algorithm(fp: &str) -> (Option<FingerprintAlgorithm>, &str) {
+    if let Some(i) = fp.find(':') {
+        let name = &fp[..i];
+        if let Some(&algorithm) = ALGORITHMS.iter().find(|a| a.name().eq_ignore_ascii_case(name)) {
+            return (Some(algorithm), &fp[i + 1..]);
+        }
+    }
+    (None, fp)
+}
+
+fn is_hex(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// format writes out a fingerprint made of hashed, prefixed with the name of
+/// the algorithm
+pub(crate) fn format(algorithm: FingerprintAlgorithm, encoding: FingerprintEncoding, hashed: &[u8]) -> String {
+    let encoded = match encoding {
+        FingerprintEncoding::Hex => hashed.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":"),
+        FingerprintEncoding::Base64 => base64::encode(hashed).trim_end_matches('=').to_string(),
+    };
+    format!("{}:{}", algorithm, encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const TEST_RSA_FINGERPRINTS: &[&str] = &[
+        "SHA256:YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o",
+        "sha256:YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o",
+        "YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o",
+        "YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o=",
+        "MD5:e9:a1:5b:cd:a3:69:d2:d9:17:cb:09:3e:78:e1:0d:dd",
+        "e9:a1:5b:cd:a3:69:d2:d9:17:cb:09:3e:78:e1:0d:dd",
+        "E9A15BCDA369D2D917CB093E78E10DDD",
+        "MD5:e9a15bcda369d2d917cb093e78e10ddd",
+        "SHA1:6Px8oSYHDFsV2hCHWmtEge7fD+A",
+        "SHA512:koex//Ftc5Ldr6npqvKcMKjZ2jKGhM4mCYFJ56XEXKMXKaX66aYYt7pwqHIlf/sPl/2a6kiHp/p3bPI+cLeWrg",
+    ];
+
+    fn keys() -> Vec<PublicKey> {
+        vec![
+            PublicKey::parse(include_str!("../fixtures/rsa.pub")).unwrap(),
+            PublicKey::parse(include_str!("../fixtures/dsa.pub")).unwrap(),
+            PublicKey::parse(include_str!("../fixtures/ed25519.pub")).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn parse() {
+        let keys = keys();
+        for fp in TEST_RSA_FINGERPRINTS {
+            let fp = Fingerprint::parse(fp).unwrap();
+            assert!(fp.matches(&keys[0]), "{}", fp);
+            assert!(!fp.matches(&keys[1]));
+            assert_eq!(keys[0].fingerprint_with(fp.algorithm(), fp.encoding()), fp.to_string());
+        }
+
+        let fp: Fingerprint = "e9a15bcda369d2d917cb093e78e10ddd".parse().unwrap();
+        assert_eq!(FingerprintAlgorithm::Md5, fp.algorithm());
+        assert_eq!("MD5:e9:a1:5b:cd:a3:69:d2:d9:17:cb:09:3e:78:e1:0d:dd", fp.to_string());
+        let fp = Fingerprint::parse("YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o").unwrap();
+        assert_eq!(FingerprintAlgorithm::Sha256, fp.algorithm());
+        assert_eq!("SHA256:YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o", fp.to_string());
+    }
+
+    #[test]
+    fn equality() {
+        let key = &keys()[0];
+        let base64 = Fingerprint::parse(&key.fingerprint()).unwrap();
+        let hex = Fingerprint::parse(&key.fingerprint_with(FingerprintAlgorithm::Sha256, FingerprintEncoding::Hex)).unwrap();
+        assert_eq!(base64, hex);
+        assert_eq!(base64, key.to_fingerprint(FingerprintAlgorithm::Sha256));
+        assert!(base64 != key.to_fingerprint(FingerprintAlgorithm::Md5));
+
+        let set: HashSet<Fingerprint> = TEST_RSA_FINGERPRINTS.iter().map(|fp| fp.parse().unwrap()).collect();
+        assert_eq!(4, set.len());
+        assert!(set.contains(&hex));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let invalid = &[
+            "",
+            "SHA256:",
+            "MD5:e9:a1:5b",
+            "MD5:e9:a1:5b:cd:a3:69:d2:d9:17:cb:09:3e:78:e1:0d:zz",
+            "MD5:e9a:15b:cd:a3:69:d2:d9:17:cb:09:3e:78:e1:0d:dd",
+            "SHA1:YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o",
+            "YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2",
+            "YTw/JyJme*Ale1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o",
+        ];
+        for fp in invalid {
+            assert!(Fingerprint::parse(fp).is_err(), "{}", fp);
+        }
+    }
+
+    #[test]
+    fn prefix() {
+        let keys = keys();
+        let find = |prefix| Fingerprint::find_prefix(prefix, &keys).unwrap().map(|key| key.keytype());
+        assert_eq!(Some("ssh-rsa"), find("SHA256:YTw/"));
+        assert_eq!(Some("ssh-rsa"), find("YTw"));
+        assert_eq!(Some("ssh-rsa"), find("MD5:E9:A1"));
+        assert_eq!(Some("ssh-rsa"), find("e9a15b"));
+        assert_eq!(Some("ssh-dss"), find("91:60"));
+        assert_eq!(Some("ssh-dss"), find("SHA256:/P"));
+        assert_eq!(Some("ssh-ed25519"), find("sha256:A/lHzX"));
+        assert_eq!(None, find("SHA256:a/lhzx"));
+        assert_eq!(None, find("SHA256:"));
+        assert_eq!(None, find("MD5:YTw"));
+
+        // e is the start of rsa's MD5 and dsa's SHA512 fingerprints
+        assert!(Fingerprint::find_prefix("e", &keys).is_err());
+        assert_eq!(Some("ssh-dss"), find("SHA512:e"));
+
+        // the same key more than once isn't ambiguous
+        let same = vec![keys[0].clone(), keys[0].clone()];
+        assert!(Fingerprint::find_prefix("e", &same).unwrap().is_some());
+    }
+}
@@ -0,0 +1,396 @@
+//! git
+//!
+//! this module provides verification of git commits and tags signed with ssh
+//! keys, the way git does it when `gpg.format` is `ssh`. the signature is an
+//! SSHSIG signature in the `git` namespace, stored in the `gpgsig` header of
+//! a commit, or `gpgsig-sha256` in sha256 repositories, or appended to the
+//! message of a tag. it works on the raw objects, as printed by `git cat-file
+//! commit` or `git cat-file tag`, so git itself isn't needed.
+
+use errors::*;
+
+use allowed_signers::AllowedSigners;
+use sshsig::SshSig;
+use PublicKey;
+
+/// NAMESPACE is the SSHSIG namespace git signs objects in
+pub const NAMESPACE: &str = "git";
+
+// the commit signature headers of sha1 and sha256 repositories. a commit can
+// have both, each signing the commit as it is in that repository.
+const GPGSIG: &[u8] = b"gpgsig ";
+const GPGSIG_SHA256: &[u8] = b"gpgsig-sha256 ";
+// the length of a sha256 object id in hex
+const SHA256_HEX_LEN: usize = 64;
+const SIGNATURE_START: &[u8] = b"-----BEGIN SSH SIGNATURE-----";
+
+/// SignedObject is a signed git commit or tag, split into the signature and
+/// the payload it signs
+#[derive(Clone, Debug)]
+pub struct SignedObject {
+    payload: Vec<u8>,
+    signature: SshSig,
+    timestamp: Option<u64>,
+}
+
+/// Signer is the identity that made a valid signature on a git object
+#[derive(Clone, Debug)]
+pub struct Signer {
+    principal: String,
+    key: PublicKey,
+}
+
+impl Signer {
+    /// principal returns the principal from the allowed_signers file that
+    /// the key was allowed for
+    pub fn principal(&self) -> &str {
+        &self.principal
+    }
+
+    /// key returns the key that made the signature
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// fingerprint returns the fingerprint of the key that made the
+    /// signature, which is what `git verify-commit` reports
+    pub fn fingerprint(&self) -> String {
+        self.key.fingerprint()
+    }
+}
+
+impl SignedObject {
+    /// from_commit reads a raw commit object. the signature is taken out of
+    /// the `gpgsig` header and its continuation lines, or the
+    /// `gpgsig-sha256` header if the commit is from a sha256 repository, and
+    /// everything else but those headers is the payload, the same as
+    /// parse_buffer_signed_by_header in
+    /// https://github.com/git/git/blob/master/commit.c. the object can start
+    /// with the `commit <size>\0` header of loose objects. a commit without a
+    /// signature gives a NotSigned error.
+    pub fn from_commit(commit: &[u8]) -> Result<Self> {
+        let commit = strip_object_header(commit, b"commit");
+        let mut payload = vec![];
+        let mut signatures = [vec![], vec![]];
+        let mut in_headers = true;
+        let mut in_signature = None;
+        for line in commit.split_inclusive(|&b| b == b'\n') {
+            if in_headers {
+                if line == b"\n" {
+                    in_headers = false;
+                } else if let Some(sig) = line.strip_prefix(GPGSIG) {
+                    in_signature = Some(0);
+                    signatures[0].extend_from_slice(sig);
+                    continue;
+                } else if let Some(sig) = line.strip_prefix(GPGSIG_SHA256) {
+                    in_signature = Some(1);
+                    signatures[1].extend_from_slice(sig);
+                    continue;
+                } else if let (Some(i), Some(sig)) = (in_signature, line.strip_prefix(b" ")) {
+                    signatures[i].extend_from_slice(sig);
+                    continue;
+                } else {
+                    in_signature = None;
+                }
+            }
+            payload.extend_from_slice(line);
+        }
+        // the length of the tree id tells which kind of repository the commit
+        // is from
+        let sha256 = payload
+            .split(|&b| b == b'\n')
+            .next()
+            .and_then(|l| l.strip_prefix(b"tree "))
+            .is_some_and(|id| id.len() == SHA256_HEX_LEN);
+        let signature = &signatures[sha256 as usize];
+        if signature.is_empty() {
+            return Err(ErrorKind::NotSigned.into());
+        }
+        let timestamp = header_timestamp(&payload, b"committer ");
+        SignedObject::new(payload, signature, timestamp)
+    }
+
+    /// from_tag reads a raw tag object. the signature is the last armored
+    /// signature at the end of the message, and the payload is everything
+    /// before it. the object can start with the `tag <size>\0` header of
+    /// loose objects. a tag without an ssh signature gives a NotSigned error.
+    pub fn from_tag(tag: &[u8]) -> Result<Self> {
+        let tag = strip_object_header(tag, b"tag");
+        let start = (0..tag.len())
+            .rev()
+            .find(|&i| (i == 0 || tag[i - 1] == b'\n') && tag[i..].starts_with(SIGNATURE_START))
+            .ok_or(ErrorKind::NotSigned)?;
+        let (payload, signature) = tag.split_at(start);
+        let timestamp = header_timestamp(payload, b"tagger ");
+        SignedObject::new(payload.to_vec(), signature, timestamp)
+    }
+
+    fn new(payload: Vec<u8>, signature: &[u8], timestamp: Option<u64>) -> Result<Self> {
+        let signature = ::std::str::from_utf8(signature).chain_err(|| ErrorKind::InvalidFormat)?;
+        Ok(SignedObject {
+            payload,
+            signature: SshSig::parse(signature)?,
+            timestamp,
+        })
+    }
+
+    /// payload returns the data that was signed
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// signature returns the signature of the object
+    pub fn signature(&self) -> &SshSig {
+        &self.signature
+    }
+
+    /// timestamp returns the committer time of a commit or the tagger time
+    /// of a tag, in seconds since the unix epoch. git checks the validity
+    /// period of allowed signers at this time rather than the current time.
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+
+    /// verify checks the signature the way `git verify-commit` does with an
+    /// allowed_signers file: it has to be a valid signature of the payload in
+    /// the git namespace, and one of the principals signers has for its key
+    /// at the time now has to be allowed to sign in that namespace. negated
+    /// principals are skipped, since they can't be who signed. for a
+    /// signature made with a certificate, the principals are those of the
+    /// certificate, checked against the cert-authority entries. it returns
+    /// that principal and the key. a valid signature by a key that isn't
+    /// allowed gives a SignerNotAllowed error with its fingerprint.
+    pub fn verify(&self, signers: &AllowedSigners, now: u64) -> Result<Signer> {
+        self.signature.verify(NAMESPACE, &self.payload)?;
+        let key = self.signature.key();
+        let principal = match self.signature.certificate() {
+            Some(cert) => cert
+                .valid_principals()
+                .iter()
+                .map(|p| p.as_str())
+                .find(|principal| signers.find_certificate(principal, cert, NAMESPACE, now).is_some()),
+            None => signers
+                .find_principals(key, now)
+                .into_iter()
+                .filter(|principal| !principal.starts_with('!'))
+                .find(|principal| signers.find(principal, key, NAMESPACE, now).is_some()),
+        };
+        principal
+            .map(|principal| Signer {
+                principal: principal.to_string(),
+                key: key.clone(),
+            })
+            .ok_or_else(|| ErrorKind::SignerNotAllowed(self.signature.fingerprint()).into())
+    }
+}
+
+/// strip_object_header removes the `<kind> <size>\0` header that loose
+/// objects start with, if there is one
+fn strip_object_header<'a>(object: &'a [u8], kind: &[u8]) -> &'a [u8] {
+    let nul = object.iter().position(|&b| b == 0);
+    let newline = object.iter().position(|&b| b == b'\n');
+    match nul {
+        Some(nul) if object.starts_with(kind) && newline.is_none_or(|n| nul < n) => &object[nul + 1..],
+        _ => object,
+    }
+}
+
+/// header_timestamp returns the time from a committer or tagger header,
+/// which ends with the time in seconds and the time zone
+fn header_timestamp(object: &[u8], name: &[u8]) -> Option<u64> {
+    let line = object
+        .split(|&b| b == b'\n')
+        .take_while(|l| !l.is_empty())
+        .find_map(|l| l.strip_prefix(name))?;
+    let line = ::std::str::from_utf8(line).ok()?;
+    line.split_whitespace().rev().nth(1)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // generated with `git commit -S` using fixtures/ed25519, and printed with
+    // `git cat-file commit HEAD`
+    const TEST_COMMIT: &str = "tree c49897f29f9819a0ab6850d7e22443508a1a29d5
+author Demos <demos@example.com> 1700000000 +0000
+committer Demos <demos@example.com> 1700000000 +0000
+gpgsig -----BEGIN SSH SIGNATURE-----
+ U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgCEGvr74VBcHySSA4xt3EG7KsfO
+ 5fkeQSWzCs3oNCg38AAAADZ2l0AAAAAAAAAAZzaGE1MTIAAABTAAAAC3NzaC1lZDI1NTE5
+ AAAAQIlIFttCqO5jfICgVoaAkHEICm56YDgU94eBEQf3hp8fqdWzEqf5A62VC8iJcQxtQ8
+ FNMTajdD64ODSvGcGMAAY=
+ -----END SSH SIGNATURE-----
+
+signed commit
+";
+    // the same commit in a sha256 repository, made with
+    // `git init --object-format=sha256`
+    const TEST_COMMIT_SHA256: &str = "tree 6ef19b41225c5369f1c104d45d8d85efa9b057b53b14b4b9b939dd74decc5321
+author Demos <demos@example.com> 1700000000 +0000
+committer Demos <demos@example.com> 1700000000 +0000
+gpgsig-sha256 -----BEGIN SSH SIGNATURE-----
+ U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgCEGvr74VBcHySSA4xt3EG7KsfO
+ 5fkeQSWzCs3oNCg38AAAADZ2l0AAAAAAAAAAZzaGE1MTIAAABTAAAAC3NzaC1lZDI1NTE5
+ AAAAQKGq4D8M7dGCJISOsjkN5M9P7oCb4SjXEH0cPfErVJdfi4WMqQyvGhqj335t5SyA0t
+ DMHdXGX89cOSVUjhZYEAM=
+ -----END SSH SIGNATURE-----
+
+signed commit
+";
+    // the same commit signed with fixtures/ed25519.pub certified for
+    // demos@example.com from 2020 to 2030 by TEST_CA_KEY, with
+    // `git config user.signingkey ed25519-cert.pub`
+    const TEST_CERT_COMMIT: &str = "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904
+author Demos <demos@example.com> 1700000000 +0000
+committer Demos <demos@example.com> 1700000000 +0000
+gpgsig -----BEGIN SSH SIGNATURE-----
+ U1NIU0lHAAAAAQAAAcYAAAAgc3NoLWVkMjU1MTktY2VydC12MDFAb3BlbnNzaC5jb20AAA
+ AgJOEZq2pqDO6BHyDHMVIInu7XULpF8LLdbTC/2Rk9BZYAAAAgCEGvr74VBcHySSA4xt3E
+ G7KsfO5fkeQSWzCs3oNCg38AAAAAAAAAAAAAAAEAAAAFZGVtb3MAAAAVAAAAEWRlbW9zQG
+ V4YW1wbGUuY29tAAAAAF4L4QAAAAAAcNvYgAAAAAAAAACCAAAAFXBlcm1pdC1YMTEtZm9y
+ d2FyZGluZwAAAAAAAAAXcGVybWl0LWFnZW50LWZvcndhcmRpbmcAAAAAAAAAFnBlcm1pdC
+ 1wb3J0LWZvcndhcmRpbmcAAAAAAAAACnBlcm1pdC1wdHkAAAAAAAAADnBlcm1pdC11c2Vy
+ LXJjAAAAAAAAAAAAAAAzAAAAC3NzaC1lZDI1NTE5AAAAIPX7f3nb/jAR/0/j4rnqzHyZWg
+ 1YPDd1SBIUPA4rmETCAAAAUwAAAAtzc2gtZWQyNTUxOQAAAECBXz1ZVyNEHEBp01WA4yNj
+ F2wOetLzqd6MQOxuyQacMZJmsISRWfZDlpYf/s2/VDllb6vJF9YcAa+UWnBW/awKAAAAA2
+ dpdAAAAAAAAAAGc2hhNTEyAAAAUwAAAAtzc2gtZWQyNTUxOQAAAECMlTyW0elnpmYi0Zeu
+ Z4Jtuzo96+0BvJuDuJ31NMk/y/gLr2WTqjYBVafmdjMaNM+87blRpXxqvBHZvZI+m7UJ
+ -----END SSH SIGNATURE-----
+
+signed commit
+";
+    const TEST_CA_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIPX7f3nb/jAR/0/j4rnqzHyZWg1YPDd1SBIUPA4rmETC ca";
+    // generated with `git tag -s v1.0` on the commit above
+    const TEST_TAG: &str = "object b469340efa1676de8bc896977deace5bc75f96de
+type commit
+tag v1.0
+tagger Demos <demos@example.com> 1700000100 +0000
+
+release 1.0
+-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgCEGvr74VBcHySSA4xt3EG7KsfO
+5fkeQSWzCs3oNCg38AAAADZ2l0AAAAAAAAAAZzaGE1MTIAAABTAAAAC3NzaC1lZDI1NTE5
+AAAAQP5ff1DnPuU7yGd8BFuHZhpbhzCZ1poOcWcBxsj7RicwedM/p99PpeL3a75BA/kcPP
+6pdg1k0CV34bH1xuyiaAc=
+-----END SSH SIGNATURE-----
+";
+    const TEST_ALLOWED_SIGNERS: &str = "demos@example.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/\n";
+    const TEST_FINGERPRINT: &str = "SHA256:A/lHzXxsgbp11dcKKfSDyNQIdep7EQgZEoRYVDBfNdI";
+
+    #[test]
+    fn commit() {
+        let commit = SignedObject::from_commit(TEST_COMMIT.as_bytes()).unwrap();
+        assert_eq!(Some(1700000000), commit.timestamp());
+        let payload = ::std::str::from_utf8(commit.payload()).unwrap();
+        assert!(payload.starts_with("tree "));
+        assert!(payload.contains("+0000\n\nsigned commit\n"));
+        assert!(!payload.contains("SSH SIGNATURE"));
+
+        let signers = AllowedSigners::parse(TEST_ALLOWED_SIGNERS);
+        let signer = commit.verify(&signers, 1700000000).unwrap();
+        assert_eq!("demos@example.com", signer.principal());
+        assert_eq!(TEST_FINGERPRINT, signer.fingerprint());
+
+        // loose objects start with a header
+        let loose = format!("commit {}\0{}", TEST_COMMIT.len(), TEST_COMMIT);
+        SignedObject::from_commit(loose.as_bytes()).unwrap().verify(&signers, 0).unwrap();
+
+        // changing the commit breaks the signature
+        let modified = TEST_COMMIT.replace("signed commit", "signed commits");
+        assert!(SignedObject::from_commit(modified.as_bytes()).unwrap().verify(&signers, 0).is_err());
+    }
+
+    #[test]
+    fn commit_sha256() {
+        let signers = AllowedSigners::parse(TEST_ALLOWED_SIGNERS);
+        let commit = SignedObject::from_commit(TEST_COMMIT_SHA256.as_bytes()).unwrap();
+        assert!(!commit.payload().windows(6).any(|w| w == b"gpgsig"));
+        assert_eq!("demos@example.com", commit.verify(&signers, 1700000000).unwrap().principal());
+
+        // a commit with both headers is verified with the one for its
+        // repository, and the other is left out of the payload
+        let start = TEST_COMMIT_SHA256.find("gpgsig-sha256").unwrap();
+        let end = TEST_COMMIT_SHA256.find("\n\n").unwrap();
+        let sha256_header = &TEST_COMMIT_SHA256[start..end];
+        let both = TEST_COMMIT.replace("\n\n", &format!("\n{}\n\n", sha256_header));
+        let commit = SignedObject::from_commit(both.as_bytes()).unwrap();
+        assert!(!commit.payload().windows(6).any(|w| w == b"gpgsig"));
+        commit.verify(&signers, 1700000000).unwrap();
+
+        // a sha1 commit with only a sha256 signature isn't signed
+        let sha256_only = TEST_COMMIT_SHA256.replace("6ef19b41225c5369f1c104d45d8d85efa9b057b53b14b4b9b939dd74decc5321", "c49897f29f9819a0ab6850d7e22443508a1a29d5");
+        match SignedObject::from_commit(sha256_only.as_bytes()) {
+            Err(Error(ErrorKind::NotSigned, _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn cert_commit() {
+        let commit = SignedObject::from_commit(TEST_CERT_COMMIT.as_bytes()).unwrap();
+        assert!(commit.signature().certificate().is_some());
+        let signers = AllowedSigners::parse(&format!("*@example.com cert-authority {}\n", TEST_CA_KEY));
+        let signer = commit.verify(&signers, 1700000000).unwrap();
+        assert_eq!("demos@example.com", signer.principal());
+        assert_eq!(TEST_FINGERPRINT, signer.fingerprint());
+
+        // the principal has to be allowed by the entry, and the certified
+        // key alone doesn't allow certificate signatures
+        for signers in &[format!("ops@example.com cert-authority {}\n", TEST_CA_KEY), TEST_ALLOWED_SIGNERS.to_string()] {
+            match commit.verify(&AllowedSigners::parse(signers), 1700000000) {
+                Err(Error(ErrorKind::SignerNotAllowed(ref f), _)) if f == TEST_FINGERPRINT => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn negated_principals() {
+        let commit = SignedObject::from_commit(TEST_COMMIT.as_bytes()).unwrap();
+        // "!root@example.com" matches *@example.com, but it's not a principal
+        let signers = AllowedSigners::parse(&TEST_ALLOWED_SIGNERS.replace("demos@example.com", "!root@example.com,*@example.com"));
+        assert_eq!("*@example.com", commit.verify(&signers, 1700000000).unwrap().principal());
+    }
+
+    #[test]
+    fn tag() {
+        let tag = SignedObject::from_tag(TEST_TAG.as_bytes()).unwrap();
+        assert_eq!(Some(1700000100), tag.timestamp());
+        assert!(tag.payload().ends_with(b"\nrelease 1.0\n"));
+        let signer = tag.verify(&AllowedSigners::parse(TEST_ALLOWED_SIGNERS), 1700000100).unwrap();
+        assert_eq!("demos@example.com", signer.principal());
+        assert_eq!(TEST_FINGERPRINT, signer.key().fingerprint());
+    }
+
+    #[test]
+    fn not_allowed() {
+        let commit = SignedObject::from_commit(TEST_COMMIT.as_bytes()).unwrap();
+        let signers = [
+            "",
+            "demos@example.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINcpQ9CVied9hMxFn+IBOAosk7sYHeg4a8hU1/2dU6fZ\n",
+            &format!("demos@example.com namespaces=\"file\" {}", TEST_ALLOWED_SIGNERS),
+            &format!("demos@example.com valid-before=\"20230101\" {}", TEST_ALLOWED_SIGNERS),
+        ];
+        for signers in &signers {
+            match commit.verify(&AllowedSigners::parse(signers), 1700000000) {
+                Err(Error(ErrorKind::SignerNotAllowed(ref f), _)) if f == TEST_FINGERPRINT => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn unsigned() {
+        let commit = "tree c49897f29f9819a0ab6850d7e22443508a1a29d5\nauthor Demos <demos@example.com> 1700000000 +0000\ncommitter Demos <demos@example.com> 1700000000 +0000\n\ngpgsig in the message\n";
+        match SignedObject::from_commit(commit.as_bytes()) {
+            Err(Error(ErrorKind::NotSigned, _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+        let tag = &TEST_TAG[..TEST_TAG.find("-----BEGIN").unwrap()];
+        match SignedObject::from_tag(tag.as_bytes()) {
+            Err(Error(ErrorKind::NotSigned, _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
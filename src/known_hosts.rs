@@ -0,0 +1,701 @@
+//! known_hosts
+//!
+//! this module provides parsing of `~/.ssh/known_hosts` files and lookup of
+//! the host keys stored in them. each line of the file is an optional marker,
+//! a list of host patterns (or a single hashed hostname), and a public key. the
+//! format is described in the SSH_KNOWN_HOSTS FILE FORMAT section of sshd(8).
+
+use errors::*;
+
+use lines::{self, Lines};
+use PublicKey;
+
+use base64;
+use crypto::hmac::Hmac;
+use crypto::mac::{Mac, MacResult};
+use crypto::sha1::Sha1;
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+const CERT_AUTHORITY: &str = "@cert-authority";
+const REVOKED: &str = "@revoked";
+const HASH_MAGIC: &str = "|1|";
+const DEFAULT_PORT: u16 = 22;
+// the salt is the same length as the output of SHA1, which is what ssh uses
+const SALT_LEN: usize = 20;
+
+/// Marker is the optional marker at the start of a known_hosts line that
+/// changes the meaning of the key on that line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Marker {
+    /// the key is a certificate authority trusted to sign host certificates
+    /// for the matching hosts
+    CertAuthority,
+    /// the key is revoked and must never be accepted
+    Revoked,
+}
+
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Marker::CertAuthority => write!(f, "{}", CERT_AUTHORITY),
+            Marker::Revoked => write!(f, "{}", REVOKED),
+        }
+    }
+}
+
+/// HostPattern is a single entry of the comma-separated hostnames field. the
+/// pattern may contain the `*` and `?` wildcards, and is of the form
+/// `[host]:port` for hosts on a non-standard port.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostPattern {
+    pub negated: bool,
+    pub pattern: String,
+}
+
+impl fmt::Display for HostPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negated {
+            write!(f, "!")?;
+        }
+        write!(f, "{}", self.pattern)
+    }
+}
+
+/// Hosts is the hostnames field of a known_hosts line. it is either a list of
+/// patterns or a single hostname hashed with HMAC-SHA1, as written by
+/// `ssh-keygen -H` or when `HashKnownHosts yes` is set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Hosts {
+    Patterns(Vec<HostPattern>),
+    Hashed {
+        salt: Vec<u8>,
+        hash: Vec<u8>,
+    },
+}
+
+impl Hosts {
+    /// host returns the hosts field for a single host on port
+    pub fn host(host: &str, port: u16) -> Self {
+        Hosts::Patterns(vec![HostPattern {
+            negated: false,
+            pattern: host_name(host, port),
+        }])
+    }
+
+    /// hashed returns the hosts field for a single host on port, hashed with
+    /// a random salt the same way ssh does when `HashKnownHosts yes` is set.
+    pub fn hashed(host: &str, port: u16) -> Self {
+        hash_name(&host_name(host, port))
+    }
+
+    fn parse(hosts: &str) -> Result<Self> {
+        // hashed hosts are of the form |1|base64(salt)|base64(hash)
+        if let Some(hashed) = hosts.strip_prefix(HASH_MAGIC) {
+            let mut parts = hashed.splitn(2, '|');
+            let salt = parts.next().ok_or(ErrorKind::InvalidFormat)?;
+            let hash = parts.next().ok_or(ErrorKind::InvalidFormat)?;
+            let salt = base64::decode(salt).chain_err(|| ErrorKind::InvalidFormat)?;
+            let hash = base64::decode(hash).chain_err(|| ErrorKind::InvalidFormat)?;
+            return Ok(Hosts::Hashed { salt, hash });
+        }
+        let patterns = hosts
+            .split(',')
+            .map(|p| {
+                let negated = p.starts_with('!');
+                let pattern = if negated { &p[1..] } else { p };
+                if pattern.is_empty() {
+                    return Err(ErrorKind::InvalidFormat.into());
+                }
+                Ok(HostPattern {
+                    negated,
+                    pattern: pattern.to_string(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Hosts::Patterns(patterns))
+    }
+
+    /// matches checks whether name, as returned by host_name, matches these
+    /// hosts. a name matching a negated pattern never matches, even if another
+    /// pattern matches it. see match_hostname in
+    /// https://github.com/openssh/openssh-portable/blob/master/match.c
+    fn matches(&self, name: &str) -> bool {
+        match *self {
+            Hosts::Hashed { ref salt, ref hash } => {
+                let computed = MacResult::new(&hash_host(salt, name));
+                computed == MacResult::new(hash)
+            }
+            Hosts::Patterns(ref patterns) => {
+                let name = name.to_lowercase();
+                let mut found = false;
+                for p in patterns {
+                    if wildcard_match(&p.pattern.to_lowercase(), &name) {
+                        if p.negated {
+                            return false;
+                        }
+                        found = true;
+                    }
+                }
+                found
+            }
+        }
+    }
+}
+
+impl fmt::Display for Hosts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Hosts::Patterns(ref patterns) => {
+                let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+                write!(f, "{}", patterns.join(","))
+            }
+            Hosts::Hashed { ref salt, ref hash } => {
+                write!(f, "{}{}|{}", HASH_MAGIC, base64::encode(salt), base64::encode(hash))
+            }
+        }
+    }
+}
+
+/// host_name returns the name a host is stored under in known_hosts. hosts on
+/// the default port are stored by name alone, others as [host]:port.
+fn host_name(host: &str, port: u16) -> String {
+    if port == DEFAULT_PORT {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+/// hash_host computes the HMAC-SHA1 of name keyed with salt, which is how
+/// hashed hostnames are stored.
+fn hash_host(salt: &[u8], name: &str) -> Vec<u8> {
+    let mut hmac = Hmac::new(Sha1::new(), salt);
+    hmac.input(name.as_bytes());
+    hmac.result().code().to_vec()
+}
+
+/// hash_name hashes a name as it is stored in known_hosts with a new random
+/// salt.
+fn hash_name(name: &str) -> Hosts {
+    let mut salt = vec![0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let hash = hash_host(&salt, name);
+    Hosts::Hashed { salt, hash }
+}
+
+/// wildcard_match matches s against a pattern where `*` matches any number of
+/// characters and `?` matches exactly one.
+pub(crate) fn wildcard_match(pattern: &str, s: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+    // star is the position in the pattern just after the last `*` we saw, and
+    // the position in s that it has been matched up to.
+    let (mut pi, mut si) = (0, 0);
+    let mut star = None;
+    while si < s.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == s[si]) {
+            pi += 1;
+            si += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            pi += 1;
+            star = Some((pi, si));
+        } else if let Some((sp, ss)) = star {
+            pi = sp;
+            si = ss + 1;
+            star = Some((sp, ss + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// KnownHost is a single entry of a known_hosts file.
+#[derive(Clone, Debug)]
+pub struct KnownHost {
+    marker: Option<Marker>,
+    hosts: Hosts,
+    key: PublicKey,
+    // the key as it was written, like AuthorizedKey keeps it
+    key_text: String,
+}
+
+impl fmt::Display for KnownHost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref marker) = self.marker {
+            write!(f, "{} ", marker)?;
+        }
+        write!(f, "{} {}", self.hosts, self.key_text)
+    }
+}
+
+impl KnownHost {
+    /// new creates a known_hosts entry with no marker
+    pub fn new(hosts: Hosts, key: PublicKey) -> Self {
+        KnownHost {
+            marker: None,
+            hosts,
+            key_text: key.to_key_file().trim_end().to_string(),
+            key,
+        }
+    }
+
+    /// parse reads a known_hosts entry from a single line
+    ///
+    /// ```
+    /// # use openssh_keys::known_hosts::KnownHost;
+    /// let line = "@cert-authority *.example.com,!bad.example.com ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril";
+    /// let entry = KnownHost::parse(line).unwrap();
+    /// assert!(entry.matches("www.example.com", 22));
+    /// assert!(!entry.matches("bad.example.com", 22));
+    /// assert_eq!(line, entry.to_string());
+    /// ```
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut line = line.trim_start();
+        let marker = if line.starts_with('@') {
+            let end = line.find(char::is_whitespace).ok_or(ErrorKind::InvalidFormat)?;
+            let marker = match &line[..end] {
+                CERT_AUTHORITY => Marker::CertAuthority,
+                REVOKED => Marker::Revoked,
+                _ => return Err(ErrorKind::InvalidFormat.into()),
+            };
+            line = line[end..].trim_start();
+            Some(marker)
+        } else {
+            None
+        };
+
+        let end = line.find(char::is_whitespace).ok_or(ErrorKind::InvalidFormat)?;
+        let hosts = Hosts::parse(&line[..end])?;
+        let key_text = line[end..].trim().to_string();
+        let key = PublicKey::parse(&key_text)?;
+
+        Ok(KnownHost { marker, hosts, key, key_text })
+    }
+
+    /// marker returns the marker of this entry, if it has one
+    pub fn marker(&self) -> Option<&Marker> {
+        self.marker.as_ref()
+    }
+
+    /// hosts returns the hosts this entry applies to
+    pub fn hosts(&self) -> &Hosts {
+        &self.hosts
+    }
+
+    /// key returns the host key, or certificate authority key, of this entry
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// matches checks whether this entry applies to host when connecting on
+    /// port.
+    pub fn matches(&self, host: &str, port: u16) -> bool {
+        self.hosts.matches(&host_name(host, port))
+    }
+}
+
+/// HostKeyStatus is the result of looking up the key a host presented in a
+/// known_hosts file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HostKeyStatus {
+    /// the key is known for this host
+    Match,
+    /// the host has a different key of the same type, so the key may have
+    /// been replaced or the connection may be intercepted
+    Mismatch,
+    /// the key is marked as revoked for this host
+    Revoked,
+    /// the key is a certificate authority trusted to sign certificates for
+    /// this host
+    TrustedByCA,
+    /// there are no keys of this type known for this host
+    Unknown,
+}
+
+/// Line is a single line of a known_hosts file. lines that ssh would skip are
+/// kept so that the file can be written back out unchanged.
+pub type Line = lines::Line<KnownHost>;
+
+impl lines::Entry for KnownHost {
+    fn parse(text: &str) -> Result<Self> {
+        KnownHost::parse(text)
+    }
+}
+
+/// KnownHosts is the contents of a whole known_hosts file. like
+/// AuthorizedKeysFile, every line is kept so that printing it produces the
+/// same contents that were read.
+#[derive(Debug, Default)]
+pub struct KnownHosts {
+    lines: Lines<KnownHost>,
+}
+
+impl fmt::Display for KnownHosts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.lines)
+    }
+}
+
+impl KnownHosts {
+    /// new creates an empty known_hosts file
+    pub fn new() -> Self {
+        KnownHosts::default()
+    }
+
+    /// parse reads the contents of a known_hosts file. this can't fail, lines
+    /// that aren't valid are kept as Line::Invalid and ignored for lookups.
+    pub fn parse(contents: &str) -> Self {
+        KnownHosts {
+            lines: Lines::parse(contents),
+        }
+    }
+
+    /// read reads a known_hosts file from r. the contents must be valid
+    /// utf-8.
+    pub fn read<R: Read>(mut r: R) -> Result<Self> {
+        let mut contents = String::new();
+        r.read_to_string(&mut contents)?;
+        Ok(KnownHosts::parse(&contents))
+    }
+
+    /// write writes the known_hosts file out to w
+    pub fn write<W: Write>(&self, mut w: W) -> Result<()> {
+        w.write_all(self.to_string().as_bytes())?;
+        Ok(())
+    }
+
+    /// save atomically replaces the file at path with the contents of this
+    /// known_hosts file. the contents are written to a temporary file in the
+    /// same directory which is then renamed over path, so readers only ever
+    /// see either the old or the new file. the permissions of an existing file
+    /// are kept.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "known_hosts path has no file name"))?;
+        let tmp = path.with_file_name(format!(".{}.{:016x}", name.to_string_lossy(), OsRng.next_u64()));
+
+        let result = (|| -> io::Result<()> {
+            let mut file = OpenOptions::new().write(true).create_new(true).open(&tmp)?;
+            if let Ok(metadata) = fs::metadata(path) {
+                file.set_permissions(metadata.permissions())?;
+            }
+            file.write_all(self.to_string().as_bytes())?;
+            file.sync_all()?;
+            fs::rename(&tmp, path)
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        Ok(result?)
+    }
+
+    /// lines returns every line of the file in order
+    pub fn lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter()
+    }
+
+    /// entries returns the successfully parsed entries of the file in order
+    pub fn entries(&self) -> impl Iterator<Item = &KnownHost> {
+        self.lines.entries()
+    }
+
+    /// find returns every entry that applies to host on port, the same
+    /// entries `ssh-keygen -F` prints.
+    pub fn find<'a>(&'a self, host: &'a str, port: u16) -> impl Iterator<Item = &'a KnownHost> + 'a {
+        self.entries().filter(move |e| e.matches(host, port))
+    }
+
+    /// check looks up the key presented by host when connecting on port. for
+    /// hosts presenting a certificate, pass the key of the authority that
+    /// signed the certificate to see if it is trusted for this host.
+    ///
+    /// ```
+    /// # use openssh_keys::PublicKey;
+    /// # use openssh_keys::known_hosts::{KnownHosts, HostKeyStatus};
+    /// let known_hosts = KnownHosts::parse("[git.example.com]:2222 ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/\n");
+    /// let key = PublicKey::parse("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/").unwrap();
+    /// assert_eq!(HostKeyStatus::Match, known_hosts.check("git.example.com", 2222, &key));
+    /// assert_eq!(HostKeyStatus::Unknown, known_hosts.check("git.example.com", 22, &key));
+    /// ```
+    pub fn check(&self, host: &str, port: u16, key: &PublicKey) -> HostKeyStatus {
+        let data = key.data();
+        let (mut matched, mut trusted, mut mismatched) = (false, false, false);
+        for entry in self.find(host, port) {
+            let same_key = entry.key.data() == data;
+            match entry.marker {
+                Some(Marker::Revoked) if same_key => return HostKeyStatus::Revoked,
+                Some(Marker::Revoked) => {}
+                Some(Marker::CertAuthority) => trusted |= same_key,
+                None if same_key => matched = true,
+                None => mismatched |= entry.key.keytype() == key.keytype(),
+            }
+        }
+        if matched {
+            HostKeyStatus::Match
+        } else if trusted {
+            HostKeyStatus::TrustedByCA
+        } else if mismatched {
+            HostKeyStatus::Mismatch
+        } else {
+            HostKeyStatus::Unknown
+        }
+    }
+
+    /// push adds an entry to the end of the file. if the file didn't end with
+    /// a newline, one is added to the current last line.
+    pub fn push(&mut self, entry: KnownHost) {
+        self.lines.push(entry)
+    }
+
+    /// add adds an entry for key on host and port to the end of the file. if
+    /// hash is set the hostname is hashed like it is with `HashKnownHosts yes`.
+    pub fn add(&mut self, host: &str, port: u16, key: PublicKey, hash: bool) {
+        let hosts = if hash {
+            Hosts::hashed(host, port)
+        } else {
+            Hosts::host(host, port)
+        };
+        self.push(KnownHost::new(hosts, key));
+    }
+
+    /// replace removes the keys of the same type as key known for host and
+    /// port and adds key in their place. this is what to do once a changed
+    /// host key has been confirmed to be legitimate.
+    pub fn replace(&mut self, host: &str, port: u16, key: PublicKey, hash: bool) {
+        self.lines.retain(|e| !(e.marker.is_none() && e.key.keytype() == key.keytype() && e.matches(host, port)));
+        self.add(host, port, key, hash);
+    }
+
+    /// remove removes every entry for host on port like `ssh-keygen -R`, and
+    /// returns the number of entries removed. entries with a marker are kept
+    /// since they can apply to many hosts.
+    pub fn remove(&mut self, host: &str, port: u16) -> usize {
+        self.lines.retain(|e| !(e.marker.is_none() && e.matches(host, port)))
+    }
+
+    /// hash hashes every hostname in the file like `ssh-keygen -H`. entries
+    /// listing several hosts are split into one hashed entry per host.
+    /// entries with a marker, or with patterns using wildcards or negation,
+    /// can't be hashed without changing what they match and are left alone.
+    pub fn hash(&mut self) {
+        self.lines.replace(|entry| match *entry {
+            KnownHost { marker: None, hosts: Hosts::Patterns(ref patterns), ref key, ref key_text } => {
+                if patterns.iter().any(|p| p.negated || p.pattern.contains(['*', '?'])) {
+                    return None;
+                }
+                let hashed = patterns.iter().map(|p| KnownHost {
+                    marker: None,
+                    hosts: hash_name(&p.pattern),
+                    key: key.clone(),
+                    key_text: key_text.clone(),
+                });
+                Some(hashed.collect())
+            }
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_ED25519_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril";
+    const TEST_OTHER_ED25519_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINcpQ9CVied9hMxFn+IBOAosk7sYHeg4a8hU1/2dU6fZ";
+    const TEST_ECDSA256_KEY: &str = "ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBIhfLQrww4DlhYzbSWXoX3ctOQ0jVosvfHfW+QWVotksbPzM2YgkIikTpoHUfZrYpJKWx7WYs5aqeLkdCDdk+jk= demos@siril";
+    // generated with `ssh-keygen -H` from an entry for host.example.com
+    const TEST_HASHED: &str = "|1|CCH95RMRjga2/A3vrG/FykyQh7Y=|LxmjWeTLUWqWqf74ShUFv/jfhOY=";
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("*.example.com", "www.example.com"));
+        assert!(!wildcard_match("*.example.com", "example.com"));
+        assert!(wildcard_match("host?.example.com", "host1.example.com"));
+        assert!(!wildcard_match("host?.example.com", "host12.example.com"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("a*b*c", "aXbYbc"));
+        assert!(!wildcard_match("a*b*c", "aXbYbd"));
+    }
+
+    #[test]
+    fn parse_patterns() {
+        let line = format!("example.com,[example.com]:2222,!*.bad.example.com,10.0.0.? {}", TEST_ED25519_KEY);
+        let entry = KnownHost::parse(&line).unwrap();
+        assert_eq!(None, entry.marker());
+        assert_eq!(&Hosts::Patterns(vec![
+            HostPattern { negated: false, pattern: "example.com".into() },
+            HostPattern { negated: false, pattern: "[example.com]:2222".into() },
+            HostPattern { negated: true, pattern: "*.bad.example.com".into() },
+            HostPattern { negated: false, pattern: "10.0.0.?".into() },
+        ]), entry.hosts());
+        assert_eq!(line, entry.to_string());
+        assert!(entry.matches("EXAMPLE.com", 22));
+        assert!(entry.matches("example.com", 2222));
+        assert!(!entry.matches("example.com", 2200));
+        assert!(entry.matches("10.0.0.7", 22));
+        assert!(!entry.matches("10.0.0.17", 22));
+    }
+
+    #[test]
+    fn parse_hashed() {
+        let line = format!("{} {}", TEST_HASHED, TEST_ED25519_KEY);
+        let entry = KnownHost::parse(&line).unwrap();
+        assert_eq!(line, entry.to_string());
+        assert!(entry.matches("host.example.com", 22));
+        assert!(!entry.matches("other.example.com", 22));
+        assert!(!entry.matches("host.example.com", 2222));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(KnownHost::parse(&format!("@bogus example.com {}", TEST_ED25519_KEY)).is_err());
+        assert!(KnownHost::parse(&format!("|1|!!!|abc {}", TEST_ED25519_KEY)).is_err());
+        assert!(KnownHost::parse(&format!("a,,b {}", TEST_ED25519_KEY)).is_err());
+        assert!(KnownHost::parse("example.com").is_err());
+    }
+
+    #[test]
+    fn check() {
+        let contents = format!(
+            "# known hosts\nexample.com,!evil.example.com {ed}\n{hashed} {ed}\n@revoked * {other}\n@cert-authority *.example.com {ecdsa}\nwww.example.com {ed}\nbroken line\n",
+            ed = TEST_ED25519_KEY,
+            hashed = TEST_HASHED,
+            other = TEST_OTHER_ED25519_KEY,
+            ecdsa = TEST_ECDSA256_KEY,
+        );
+        let known_hosts = KnownHosts::read(contents.as_bytes()).unwrap();
+        assert_eq!(5, known_hosts.entries().count());
+        assert_eq!(7, known_hosts.lines().count());
+        assert_eq!(contents, known_hosts.to_string());
+
+        let ed = PublicKey::parse(TEST_ED25519_KEY).unwrap();
+        let other = PublicKey::parse(TEST_OTHER_ED25519_KEY).unwrap();
+        let ecdsa = PublicKey::parse(TEST_ECDSA256_KEY).unwrap();
+
+        assert_eq!(HostKeyStatus::Match, known_hosts.check("example.com", 22, &ed));
+        assert_eq!(HostKeyStatus::Match, known_hosts.check("host.example.com", 22, &ed));
+        assert_eq!(HostKeyStatus::Unknown, known_hosts.check("evil.example.com", 22, &ed));
+        assert_eq!(HostKeyStatus::Revoked, known_hosts.check("example.com", 22, &other));
+        assert_eq!(HostKeyStatus::TrustedByCA, known_hosts.check("www.example.com", 22, &ecdsa));
+        assert_eq!(HostKeyStatus::Unknown, known_hosts.check("example.com", 22, &ecdsa));
+        assert_eq!(HostKeyStatus::Unknown, known_hosts.check("nowhere.org", 22, &ed));
+        assert_eq!(3, known_hosts.find("www.example.com", 22).count());
+    }
+
+    #[test]
+    fn add_and_check() {
+        let mut known_hosts = KnownHosts::parse("# pinned hosts");
+        let ed = PublicKey::parse(TEST_ED25519_KEY).unwrap();
+        known_hosts.add("plain.example.com", 22, ed.clone(), false);
+        known_hosts.add("hashed.example.com", 2222, ed.clone(), true);
+        assert_eq!(HostKeyStatus::Match, known_hosts.check("plain.example.com", 22, &ed));
+        assert_eq!(HostKeyStatus::Match, known_hosts.check("hashed.example.com", 2222, &ed));
+        assert_eq!(HostKeyStatus::Unknown, known_hosts.check("hashed.example.com", 22, &ed));
+
+        let out = known_hosts.to_string();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(3, lines.len());
+        assert_eq!("# pinned hosts", lines[0]);
+        assert_eq!(format!("plain.example.com {}", TEST_ED25519_KEY), lines[1]);
+        assert!(lines[2].starts_with("|1|"));
+        assert!(out.ends_with('\n'));
+    }
+
+    #[test]
+    fn replace_and_remove() {
+        let contents = format!(
+            "a.example.com,b.example.com {ed}\n@revoked a.example.com {other}\na.example.com {ecdsa}\nc.example.com {ed}\n",
+            ed = TEST_ED25519_KEY,
+            other = TEST_OTHER_ED25519_KEY,
+            ecdsa = TEST_ECDSA256_KEY,
+        );
+        let mut known_hosts = KnownHosts::parse(&contents);
+        let other = PublicKey::parse(TEST_OTHER_ED25519_KEY).unwrap();
+        let ecdsa = PublicKey::parse(TEST_ECDSA256_KEY).unwrap();
+        let new_key = PublicKey::parse("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/").unwrap();
+
+        known_hosts.replace("c.example.com", 22, other.clone(), false);
+        assert_eq!(HostKeyStatus::Match, known_hosts.check("c.example.com", 22, &other));
+        assert_eq!(HostKeyStatus::Mismatch, known_hosts.check("c.example.com", 22, &new_key));
+
+        assert_eq!(2, known_hosts.remove("a.example.com", 22));
+        assert_eq!(HostKeyStatus::Unknown, known_hosts.check("a.example.com", 22, &ecdsa));
+        // the revocation is kept
+        assert_eq!(HostKeyStatus::Revoked, known_hosts.check("a.example.com", 22, &other));
+        assert_eq!(HostKeyStatus::Unknown, known_hosts.check("b.example.com", 22, &new_key));
+        assert_eq!(0, known_hosts.remove("a.example.com", 22));
+    }
+
+    #[test]
+    fn hash() {
+        let contents = format!(
+            "# comment\r\nexample.com,[example.com]:2222 {ed}\r\n*.example.org {ed}\r\n@cert-authority ca.example.com {ecdsa}\r\n{hashed} {ed}",
+            ed = TEST_ED25519_KEY,
+            ecdsa = TEST_ECDSA256_KEY,
+            hashed = TEST_HASHED,
+        );
+        let mut known_hosts = KnownHosts::parse(&contents);
+        known_hosts.hash();
+        let out = known_hosts.to_string();
+        let lines: Vec<&str> = out.split("\r\n").collect();
+        assert_eq!(6, lines.len());
+        assert_eq!("# comment", lines[0]);
+        assert!(lines[1].starts_with("|1|"));
+        assert!(lines[2].starts_with("|1|"));
+        assert_eq!(format!("*.example.org {}", TEST_ED25519_KEY), lines[3]);
+        assert_eq!(format!("@cert-authority ca.example.com {}", TEST_ECDSA256_KEY), lines[4]);
+        assert_eq!(format!("{} {}", TEST_HASHED, TEST_ED25519_KEY), lines[5]);
+
+        let ed = PublicKey::parse(TEST_ED25519_KEY).unwrap();
+        assert_eq!(HostKeyStatus::Match, known_hosts.check("example.com", 22, &ed));
+        assert_eq!(HostKeyStatus::Match, known_hosts.check("example.com", 2222, &ed));
+        assert_eq!(HostKeyStatus::Match, known_hosts.check("www.example.org", 22, &ed));
+    }
+
+    #[test]
+    fn hash_keeps_key_text() {
+        let key = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ web server key";
+        let mut known_hosts = KnownHosts::parse(&format!("a.example.com,b.example.com {}\n", key));
+        known_hosts.hash();
+        let out = known_hosts.to_string();
+        assert_eq!(2, out.lines().count());
+        assert!(out.lines().all(|l| l.starts_with("|1|") && l.ends_with(key)));
+
+        // a key without a comment doesn't get a trailing space
+        let other = PublicKey::parse(TEST_OTHER_ED25519_KEY).unwrap();
+        known_hosts.replace("c.example.com", 22, other, false);
+        assert!(known_hosts.to_string().ends_with(&format!("\nc.example.com {}\n", TEST_OTHER_ED25519_KEY)));
+    }
+
+    #[test]
+    fn save() {
+        let path = ::std::env::temp_dir().join(format!("openssh-keys-known-hosts-{}", ::std::process::id()));
+        let mut known_hosts = KnownHosts::parse(&format!("example.com {}\n", TEST_ED25519_KEY));
+        known_hosts.save(&path).unwrap();
+        known_hosts.add("example.org", 22, PublicKey::parse(TEST_ECDSA256_KEY).unwrap(), false);
+        known_hosts.save(&path).unwrap();
+        let read = KnownHosts::read(fs::File::open(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(known_hosts.to_string(), read.to_string());
+        assert_eq!(2, read.entries().count());
+    }
+
+    #[test]
+    fn check_mismatch() {
+        let known_hosts = KnownHosts::parse(&format!("example.com {}\n", TEST_OTHER_ED25519_KEY));
+        let ed = PublicKey::parse(TEST_ED25519_KEY).unwrap();
+        let ecdsa = PublicKey::parse(TEST_ECDSA256_KEY).unwrap();
+        assert_eq!(HostKeyStatus::Mismatch, known_hosts.check("example.com", 22, &ed));
+        assert_eq!(HostKeyStatus::Unknown, known_hosts.check("example.com", 22, &ecdsa));
+    }
+}
@@ -4,25 +4,61 @@
 //! validation of ssh keys. it provides a struct for encapsulation of ssh keys
 //! in projects.
 //!
-//! ssh-keys doesn't have the ability to generate ssh-keys. however, it does
-//! allow you to construct rsa and dsa keys from their components, so if you
-//! generate the keys with another library (say, rust-openssl), then you can
-//! output the ssh public keys with this library.
-#![allow(unused_doc_comment)]
+//! ssh-keys can generate ed25519, ecdsa, and rsa keys with
+//! `private_key::PrivateKey::generate`, like `ssh-keygen -t`. it also allows
+//! you to construct keys of any type from their components, so if you
+//! generate the keys with another library (say, rust-openssl) or a hardware
+//! security module, then you can output the ssh public keys with this
+//! library.
+#![allow(unused_doc_comments)]
 
 extern crate base64;
 extern crate byteorder;
+extern crate dsa;
 extern crate crypto;
+extern crate ecdsa;
 #[macro_use]
 extern crate error_chain;
-
+extern crate num_bigint;
+extern crate p256;
+extern crate p384;
+extern crate p521;
+extern crate rand;
+extern crate rfc6979;
+extern crate rsa as rsa_crate;
+extern crate sha2;
+
+mod cipher;
+mod der;
+mod ec;
+mod fingerprint;
+mod kdf;
+mod lines;
+mod pem;
+mod pkcs;
+mod pkcs8;
 mod reader;
+mod rfc4716;
+mod rsa;
+mod signature;
 mod writer;
-
+mod x509;
+
+pub mod allowed_signers;
+pub mod attestation;
+pub mod authorized_keys;
+pub mod cert;
+pub mod git;
+pub mod known_hosts;
+pub mod private_key;
+pub mod sshsig;
+
+#[allow(deprecated)]
 pub mod errors {
     error_chain! {
         foreign_links {
             Utf8(::std::str::Utf8Error);
+            Io(::std::io::Error);
         }
         errors {
             InvalidFormat {
@@ -37,29 +73,107 @@ pub mod errors {
                 description("unsupported curve")
                     display("unsupported curve: {}", t)
             }
+            UnsupportedCipher(t: String) {
+                description("unsupported cipher")
+                    display("unsupported cipher: {}", t)
+            }
+            UnsupportedKdf(t: String) {
+                description("unsupported kdf")
+                    display("unsupported kdf: {}", t)
+            }
+            UnsupportedHash(t: String) {
+                description("unsupported hash algorithm")
+                    display("unsupported hash algorithm: {}", t)
+            }
+            UnsupportedKeySize(bits: usize) {
+                description("unsupported key size")
+                    display("unsupported key size: {} bits", bits)
+            }
+            InvalidKdfRounds(rounds: u32) {
+                description("invalid number of kdf rounds")
+                    display("invalid number of kdf rounds: {}", rounds)
+            }
+            PassphraseRequired {
+                description("private key is encrypted and needs a passphrase")
+                    display("private key is encrypted and needs a passphrase")
+            }
+            IncorrectPassphrase {
+                description("incorrect passphrase")
+                    display("incorrect passphrase")
+            }
+            InvalidSignature {
+                description("invalid signature")
+                    display("invalid signature")
+            }
+            DisallowedSignatureAlgorithm(t: String) {
+                description("signature algorithm not allowed")
+                    display("signature algorithm not allowed: {}", t)
+            }
+            UserPresenceRequired {
+                description("signature was made without user presence")
+                    display("signature was made without user presence")
+            }
+            UserVerificationRequired {
+                description("signature was made without user verification")
+                    display("signature was made without user verification")
+            }
+            NamespaceMismatch(t: String) {
+                description("signature was made for another namespace")
+                    display("signature was made for another namespace: {}", t)
+            }
+            SignerNotAllowed(t: String) {
+                description("signer not allowed")
+                    display("signer not allowed: {}", t)
+            }
+            NotSigned {
+                description("object isn't signed")
+                    display("object isn't signed")
+            }
+            AttestationMismatch {
+                description("attestation is for a different key")
+                    display("attestation is for a different key")
+            }
+            UntrustedAttestation {
+                description("attestation certificate isn't issued by a trusted root")
+                    display("attestation certificate isn't issued by a trusted root")
+            }
+            CertificateRejected(r: ::cert::Rejection) {
+                description("certificate rejected")
+                    display("certificate rejected: {}", r)
+            }
+            InvalidOption(t: String) {
+                description("invalid authorized_keys option")
+                    display("invalid authorized_keys option: {}", t)
+            }
+            AmbiguousFingerprint(t: String) {
+                description("fingerprint matches more than one key")
+                    display("fingerprint matches more than one key: {}", t)
+            }
         }
     }
 }
 
 use errors::*;
 
-use crypto::digest::Digest;
-use crypto::sha2::Sha256;
-
 use reader::Reader;
 use writer::Writer;
 
+pub use fingerprint::{Fingerprint, FingerprintAlgorithm, FingerprintEncoding};
+pub use signature::{SignatureAlgorithm, SkSignature};
+
 use std::fmt;
 
-const SSH_RSA: &'static str = "ssh-rsa";
-const SSH_DSA: &'static str = "ssh-dss";
-const SSH_ED25519: &'static str = "ssh-ed25519";
-const SSH_ECDSA_256: &'static str = "ecdsa-sha2-nistp256";
-const SSH_ECDSA_384: &'static str = "ecdsa-sha2-nistp384";
-const SSH_ECDSA_521: &'static str = "ecdsa-sha2-nistp521";
-const NISTP_256: &'static str = "nistp256";
-const NISTP_384: &'static str = "nistp384";
-const NISTP_521: &'static str = "nistp521";
+const SSH_RSA: &str = "ssh-rsa";
+const SSH_DSA: &str = "ssh-dss";
+const SSH_ED25519: &str = "ssh-ed25519";
+const SSH_ECDSA_256: &str = "ecdsa-sha2-nistp256";
+const SSH_ECDSA_384: &str = "ecdsa-sha2-nistp384";
+const SSH_ECDSA_521: &str = "ecdsa-sha2-nistp521";
+const SK_SSH_ED25519: &str = "sk-ssh-ed25519@openssh.com";
+const SK_ECDSA_256: &str = "sk-ecdsa-sha2-nistp256@openssh.com";
+const NISTP_256: &str = "nistp256";
+const NISTP_384: &str = "nistp384";
+const NISTP_521: &str = "nistp521";
 
 /// Curves for ECDSA
 #[derive(Clone, Debug)]
@@ -121,6 +235,17 @@ pub enum Data {
         curve: Curve,
         key: Vec<u8>,
     },
+    /// an ed25519 key on a FIDO security key. application is the FIDO
+    /// application the key was made for, which is usually `ssh:`
+    SkEd25519 {
+        key: Vec<u8>,
+        application: String,
+    },
+    /// a nistp256 ecdsa key on a FIDO security key
+    SkEcdsa {
+        key: Vec<u8>,
+        application: String,
+    },
 }
 
 /// PublicKey is the struct representation of an ssh public key.
@@ -143,6 +268,7 @@ impl PublicKey {
     ///
     /// You can parse and output ssh keys like this
     /// ```
+    /// # use openssh_keys::PublicKey;
     /// let rsa_key = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQCcMCOEryBa8IkxXacjIawaQPp08hR5h7+4vZePZ7DByTG3tqKgZYRJ86BaR+4fmdikFoQjvLJVUmwniq3wixhkP7VLCbqip3YHzxXrzxkbPC3w3O1Bdmifwn9cb8RcZXfXncCsSu+h5XCtQ5BOi41Iit3d13gIe/rfXVDURmRanV6R7Voljxdjmp/zyReuzc2/w5SI6Boi4tmcUlxAI7sFuP1kA3pABDhPtc3TDgAcPUIBoDCoY8q2egI197UuvbgsW2qraUcuQxbMvJOMSFg2FQrE2bpEqC4CtBn7+HiJrkVOHjV7bvSv7jd1SuX5XqkwMCRtdMuRpJr7CyZoFL5n demos@anduin";
     /// let key = PublicKey::parse(rsa_key).unwrap();
     /// let out = key.to_string();
@@ -167,7 +293,28 @@ impl PublicKey {
             return Err(ErrorKind::InvalidFormat.into());
         }
 
-        let data = match keytype {
+        Ok(PublicKey {
+            data: PublicKey::read_data(keytype, &mut reader)?,
+            comment,
+        })
+    }
+
+    /// from_blob reads a public key from the binary encoding returned by
+    /// data(), which is the part of the key that gets base64 encoded. the
+    /// resulting key has no comment.
+    pub(crate) fn from_blob(blob: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(blob);
+        let keytype = reader.read_string()?;
+        Ok(PublicKey {
+            data: PublicKey::read_data(keytype, &mut reader)?,
+            comment: None,
+        })
+    }
+
+    /// read_data reads the data section of a key of the given keytype, which
+    /// has already been read from the reader.
+    fn read_data(keytype: &str, reader: &mut Reader) -> Result<Data> {
+        Ok(match keytype {
             SSH_RSA => {
                 // the data for an rsa key consists of three pieces:
                 //    ssh-rsa public-exponent modulus
@@ -201,12 +348,10 @@ impl PublicKey {
                 }
             },
             SSH_ED25519 => {
-                // the data stored for an ed25519 is just the point on the curve
-                // for now the exact specification of the point on that curve is
-                // a mystery to me, instead of having to compute it, we just
-                // assume the key we got is correct and copy that verbatim. this
-                // also means we have to disallow arbitrary construction until
-                // furthur notice.
+                // the data stored for an ed25519 is just the 32 byte encoding
+                // of the point on the curve from
+                // https://tools.ietf.org/html/rfc8032#section-5.1.2, which is
+                // copied verbatim.
                 // see https://github.com/openssh/openssh-portable/blob/master/sshkey.c#L772
                 let key = reader.read_bytes()?;
                 Data::Ed25519 {
@@ -217,10 +362,10 @@ impl PublicKey {
                 // ecdsa is of the form
                 //    ecdsa-sha2-[identifier] [identifier] [data]
                 // the identifier is one of nistp256, nistp384, nistp521
-                // the data is some weird thing described in section 2.3.4 and
-                // 2.3.4 of https://www.secg.org/sec1-v2.pdf so for now we
-                // aren't going to bother actually computing it and instead we
-                // will just not let you construct them.
+                // the data is the point on the curve, encoded as described in
+                // section 2.3.3 of https://www.secg.org/sec1-v2.pdf. openssh
+                // always uses the uncompressed form, which is copied verbatim
+                // here. from_ecdsa checks points and converts them to it.
                 //
                 // see the data definition at
                 // https://tools.ietf.org/html/rfc5656#section-3.1
@@ -237,12 +382,31 @@ impl PublicKey {
                     key: key.into(),
                 }
             },
+            SK_SSH_ED25519 => {
+                // security keys are the same as the plain key type, followed
+                // by the application string. see
+                // https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.u2f
+                let key = reader.read_bytes()?;
+                let application = reader.read_string()?;
+                Data::SkEd25519 {
+                    key: key.into(),
+                    application: application.into(),
+                }
+            },
+            SK_ECDSA_256 => {
+                // only nistp256 is supported by security keys
+                let curve = reader.read_string()?;
+                if curve != NISTP_256 {
+                    return Err(ErrorKind::UnsupportedCurve(curve.to_string()).into());
+                }
+                let key = reader.read_bytes()?;
+                let application = reader.read_string()?;
+                Data::SkEcdsa {
+                    key: key.into(),
+                    application: application.into(),
+                }
+            },
             _ => return Err(ErrorKind::UnsupportedKeytype(keytype.into()).into()),
-        };
-
-        Ok(PublicKey {
-            data: data,
-            comment: comment,
         })
     }
 
@@ -261,15 +425,41 @@ impl PublicKey {
     pub fn from_dsa(p: Vec<u8>, q: Vec<u8>, g: Vec<u8>, pkey: Vec<u8>) -> Self {
         PublicKey {
             data: Data::Dsa {
-                p: p,
-                q: q,
-                g: g,
+                p,
+                q,
+                g,
                 pub_key: pkey,
             },
             comment: None,
         }
     }
 
+    /// get an ssh public key from the 32 byte encoding of an ed25519 point
+    pub fn from_ed25519(key: &[u8; 32]) -> Self {
+        PublicKey {
+            data: Data::Ed25519 {
+                key: key.to_vec(),
+            },
+            comment: None,
+        }
+    }
+
+    /// get an ssh public key from a point on a named curve. the point is
+    /// encoded as described in section 2.3.3 of
+    /// https://www.secg.org/sec1-v2.pdf, either compressed or uncompressed,
+    /// and has to be on the curve. compressed points are expanded to the
+    /// uncompressed form openssh uses.
+    pub fn from_ecdsa(curve: Curve, point: &[u8]) -> Result<Self> {
+        let key = ec::decode_point(&curve, point)?;
+        Ok(PublicKey {
+            data: Data::Ecdsa {
+                curve,
+                key,
+            },
+            comment: None,
+        })
+    }
+
     /// keytype returns the type of key in the format described by rfc4253
     /// The output will be ssh-{type} where type is [rsa,ed25519,ecdsa,dsa]
     pub fn keytype(&self) -> &'static str {
@@ -282,6 +472,8 @@ impl PublicKey {
                 Curve::Nistp384 => SSH_ECDSA_384,
                 Curve::Nistp521 => SSH_ECDSA_521,
             },
+            Data::SkEd25519{..} => SK_SSH_ED25519,
+            Data::SkEcdsa{..} => SK_ECDSA_256,
         }
     }
 
@@ -293,7 +485,14 @@ impl PublicKey {
     pub fn data(&self) -> Vec<u8> {
         let mut writer = Writer::new();
         writer.write_string(self.keytype());
-        match self.data {
+        PublicKey::write_data(&self.data, &mut writer);
+        writer.into_vec()
+    }
+
+    /// write_data writes the data section of a key, without the keytype
+    /// that comes before it.
+    fn write_data(data: &Data, writer: &mut Writer) {
+        match *data {
             Data::Rsa{ref exponent, ref modulus} => {
                 // the data for an rsa key consists of three pieces:
                 //    ssh-rsa public-exponent modulus
@@ -314,8 +513,21 @@ impl PublicKey {
                 writer.write_string(curve.curvetype());
                 writer.write_bytes(key.clone());
             }
+            Data::SkEd25519{ref key, ref application} => {
+                writer.write_bytes(key.clone());
+                writer.write_string(application);
+            }
+            Data::SkEcdsa{ref key, ref application} => {
+                writer.write_string(NISTP_256);
+                writer.write_bytes(key.clone());
+                writer.write_string(application);
+            }
         }
-        writer.to_vec()
+    }
+
+    /// comment returns the comment of the key, if it has one
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
     }
 
     pub fn set_comment(&mut self, comment: &str) {
@@ -346,7 +558,8 @@ impl PublicKey {
                 Curve::Nistp256 => 256,
                 Curve::Nistp384 => 384,
                 Curve::Nistp521 => 521,
-            }
+            },
+            Data::SkEd25519{..} | Data::SkEcdsa{..} => 256,
         }
     }
 
@@ -355,20 +568,28 @@ impl PublicKey {
     /// https://tools.ietf.org/html/rfc4716#page-6. This uses the ssh-keygen
     /// defaults of a base64 encoded SHA256 hash.
     pub fn fingerprint(&self) -> String {
-        let data = self.data();
-        let mut hasher = Sha256::new();
-        hasher.input(&data);
-        let mut hashed: [u8; 32] = [0; 32];
-        hasher.result(&mut hashed);
-        let mut fingerprint = base64::encode(&hashed);
-        // trim padding characters off the end. I'm not clear on exactly what
-        // this is doing but they do it here and the test fails without it
-        // https://github.com/openssh/openssh-portable/blob/643c2ad82910691b2240551ea8b14472f60b5078/sshkey.c#L918
-        match fingerprint.find('=') {
-            Some(l) => { fingerprint.split_off(l); },
-            None => {},
-        }
-        format!("SHA256:{}", fingerprint)
+        self.fingerprint_with(FingerprintAlgorithm::Sha256, FingerprintEncoding::Base64)
+    }
+
+    /// fingerprint_with returns the fingerprint of the key made with the given
+    /// hash and encoding. `ssh-keygen -E md5` fingerprints are
+    /// `fingerprint_with(FingerprintAlgorithm::Md5, FingerprintEncoding::Hex)`.
+    ///
+    /// ```
+    /// # use openssh_keys::{FingerprintAlgorithm, FingerprintEncoding, PublicKey};
+    /// let key = PublicKey::parse("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril").unwrap();
+    /// let md5 = FingerprintAlgorithm::Md5;
+    /// assert_eq!("MD5:89:b4:6a:6c:7d:71:f0:b9:7d:ae:a3:98:25:67:e5:f7", key.fingerprint_with(md5, md5.default_encoding()));
+    /// ```
+    pub fn fingerprint_with(&self, algorithm: FingerprintAlgorithm, encoding: FingerprintEncoding) -> String {
+        fingerprint::format(algorithm, encoding, &algorithm.hash(&self.data()))
+    }
+
+    /// to_fingerprint returns the fingerprint of the key made with the given
+    /// hash as a `Fingerprint`, which prints with the algorithm's default
+    /// encoding
+    pub fn to_fingerprint(&self, algorithm: FingerprintAlgorithm) -> Fingerprint {
+        Fingerprint::new(algorithm, algorithm.hash(&self.data()))
     }
 
     /// to_fingerprint_string prints out the fingerprint in the same format used
@@ -377,14 +598,23 @@ impl PublicKey {
     /// right now it just sticks with the defaults of a base64 encoded SHA256
     /// hash.
     pub fn to_fingerprint_string(&self) -> String {
+        self.to_fingerprint_string_with(FingerprintAlgorithm::Sha256, FingerprintEncoding::Base64)
+    }
+
+    /// to_fingerprint_string_with is to_fingerprint_string with the
+    /// fingerprint made with the given hash and encoding. with the default
+    /// encoding of the algorithm it's the same as `ssh-keygen -l -E <hash>`.
+    pub fn to_fingerprint_string_with(&self, algorithm: FingerprintAlgorithm, encoding: FingerprintEncoding) -> String {
         let keytype = match self.data {
             Data::Rsa{..} => "RSA",
             Data::Dsa{..} => "DSA",
             Data::Ed25519{..} => "ED25519",
             Data::Ecdsa{..} => "ECDSA",
+            Data::SkEd25519{..} => "ED25519-SK",
+            Data::SkEcdsa{..} => "ECDSA-SK",
         };
 
-        format!("{} {} {} ({})", self.size(), self.fingerprint(), self.comment.clone().unwrap_or("no comment".to_string()), keytype)
+        format!("{} {} {} ({})", self.size(), self.fingerprint_with(algorithm, encoding), self.comment.clone().unwrap_or("no comment".to_string()), keytype)
     }
 }
 
@@ -392,11 +622,15 @@ impl PublicKey {
 mod tests {
     use super::*;
 
-    const TEST_RSA_KEY: &'static str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQCYH3vPUJThzriVlVKmKOg71EOVYm274oRa5KLWEoK0HmjMc9ru0j4ofouoeW/AVmRVujxfaIGR/8en/lUPkiv5DSeM6aXnDz5cExNptrAy/sMPLQhVALRrqQ+dkS9Ct/YA+A1Le5LPh4MJu79hCDLTwqSdKqDuUcYQzR0M7APslaDCR96zY+VUL4lKObUUd4wsP3opdTQ6G20qXEer14EPGr9N53S/u+JJGLoPlb1uPIH96oKY4t/SeLIRQsocdViRaiF/Aq7kPzWd/yCLVdXJSRt3CftboV4kLBHGteTS551J32MJoqjEi4Q/DucWYrQfx5H3qXVB+/G2HurKPIHL demos@siril";
-    const TEST_RSA_COMMENT_KEY: &'static str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQCYH3vPUJThzriVlVKmKOg71EOVYm274oRa5KLWEoK0HmjMc9ru0j4ofouoeW/AVmRVujxfaIGR/8en/lUPkiv5DSeM6aXnDz5cExNptrAy/sMPLQhVALRrqQ+dkS9Ct/YA+A1Le5LPh4MJu79hCDLTwqSdKqDuUcYQzR0M7APslaDCR96zY+VUL4lKObUUd4wsP3opdTQ6G20qXEer14EPGr9N53S/u+JJGLoPlb1uPIH96oKY4t/SeLIRQsocdViRaiF/Aq7kPzWd/yCLVdXJSRt3CftboV4kLBHGteTS551J32MJoqjEi4Q/DucWYrQfx5H3qXVB+/G2HurKPIHL test";
-    const TEST_DSA_KEY: &'static str = "ssh-dss AAAAB3NzaC1kc3MAAACBAIkd9CkqldM2St8f53rfJT7kPgiA8leZaN7hdZd48hYJyKzVLoPdBMaGFuOwGjv0Im3JWqWAewANe0xeLceQL0rSFbM/mZV+1gc1nm1WmtVw4KJIlLXl3gS7NYfQ9Ith4wFnZd/xhRz9Q+MBsA1DgXew1zz4dLYI46KmFivJ7XDzAAAAFQC8z4VIhI4HlHTvB7FdwAfqWsvcOwAAAIBEqPIkW3HHDTSEhUhhV2AlIPNwI/bqaCXy2zYQ6iTT3oUh+N4xlRaBSvW+h2NC97U8cxd7Y0dXIbQKPzwNzRX1KA1F9WAuNzrx9KkpCg2TpqXShhp+Sseb+l6uJjthIYM6/0dvr9cBDMeExabPPgBo3Eii2NLbFSqIe86qav8hZAAAAIBk5AetZrG8varnzv1khkKh6Xq/nX9r1UgIOCQos2XOi2ErjlB9swYCzReo1RT7dalITVi7K9BtvJxbutQEOvN7JjJnPJs+M3OqRMMF+anXPdCWUIBxZUwctbkAD5joEjGDrNXHQEw9XixZ9p3wudbISnPFgZhS1sbS9Rlw5QogKg== demos@siril";
-    const TEST_ED25519_KEY: &'static str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril";
-    const TEST_ECDSA256_KEY: &'static str = "ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBIhfLQrww4DlhYzbSWXoX3ctOQ0jVosvfHfW+QWVotksbPzM2YgkIikTpoHUfZrYpJKWx7WYs5aqeLkdCDdk+jk= demos@siril";
+    const TEST_RSA_KEY: &str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQCYH3vPUJThzriVlVKmKOg71EOVYm274oRa5KLWEoK0HmjMc9ru0j4ofouoeW/AVmRVujxfaIGR/8en/lUPkiv5DSeM6aXnDz5cExNptrAy/sMPLQhVALRrqQ+dkS9Ct/YA+A1Le5LPh4MJu79hCDLTwqSdKqDuUcYQzR0M7APslaDCR96zY+VUL4lKObUUd4wsP3opdTQ6G20qXEer14EPGr9N53S/u+JJGLoPlb1uPIH96oKY4t/SeLIRQsocdViRaiF/Aq7kPzWd/yCLVdXJSRt3CftboV4kLBHGteTS551J32MJoqjEi4Q/DucWYrQfx5H3qXVB+/G2HurKPIHL demos@siril";
+    const TEST_RSA_COMMENT_KEY: &str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQCYH3vPUJThzriVlVKmKOg71EOVYm274oRa5KLWEoK0HmjMc9ru0j4ofouoeW/AVmRVujxfaIGR/8en/lUPkiv5DSeM6aXnDz5cExNptrAy/sMPLQhVALRrqQ+dkS9Ct/YA+A1Le5LPh4MJu79hCDLTwqSdKqDuUcYQzR0M7APslaDCR96zY+VUL4lKObUUd4wsP3opdTQ6G20qXEer14EPGr9N53S/u+JJGLoPlb1uPIH96oKY4t/SeLIRQsocdViRaiF/Aq7kPzWd/yCLVdXJSRt3CftboV4kLBHGteTS551J32MJoqjEi4Q/DucWYrQfx5H3qXVB+/G2HurKPIHL test";
+    const TEST_DSA_KEY: &str = "ssh-dss AAAAB3NzaC1kc3MAAACBAIkd9CkqldM2St8f53rfJT7kPgiA8leZaN7hdZd48hYJyKzVLoPdBMaGFuOwGjv0Im3JWqWAewANe0xeLceQL0rSFbM/mZV+1gc1nm1WmtVw4KJIlLXl3gS7NYfQ9Ith4wFnZd/xhRz9Q+MBsA1DgXew1zz4dLYI46KmFivJ7XDzAAAAFQC8z4VIhI4HlHTvB7FdwAfqWsvcOwAAAIBEqPIkW3HHDTSEhUhhV2AlIPNwI/bqaCXy2zYQ6iTT3oUh+N4xlRaBSvW+h2NC97U8cxd7Y0dXIbQKPzwNzRX1KA1F9WAuNzrx9KkpCg2TpqXShhp+Sseb+l6uJjthIYM6/0dvr9cBDMeExabPPgBo3Eii2NLbFSqIe86qav8hZAAAAIBk5AetZrG8varnzv1khkKh6Xq/nX9r1UgIOCQos2XOi2ErjlB9swYCzReo1RT7dalITVi7K9BtvJxbutQEOvN7JjJnPJs+M3OqRMMF+anXPdCWUIBxZUwctbkAD5joEjGDrNXHQEw9XixZ9p3wudbISnPFgZhS1sbS9Rlw5QogKg== demos@siril";
+    const TEST_ED25519_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril";
+    const TEST_ECDSA256_KEY: &str = "ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBIhfLQrww4DlhYzbSWXoX3ctOQ0jVosvfHfW+QWVotksbPzM2YgkIikTpoHUfZrYpJKWx7WYs5aqeLkdCDdk+jk= demos@siril";
+    // the points of TEST_ED25519_KEY and TEST_ECDSA256_KEY, as security keys
+    // for the ssh: application
+    const TEST_SK_ED25519_KEY: &str = "sk-ssh-ed25519@openssh.com AAAAGnNrLXNzaC1lZDI1NTE5QG9wZW5zc2guY29tAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAABHNzaDo= demos@siril";
+    const TEST_SK_ECDSA256_KEY: &str = "sk-ecdsa-sha2-nistp256@openssh.com AAAAInNrLWVjZHNhLXNoYTItbmlzdHAyNTZAb3BlbnNzaC5jb20AAAAIbmlzdHAyNTYAAABBBIhfLQrww4DlhYzbSWXoX3ctOQ0jVosvfHfW+QWVotksbPzM2YgkIikTpoHUfZrYpJKWx7WYs5aqeLkdCDdk+jkAAAAEc3NoOg== demos@siril";
 
     #[test]
     fn rsa_parse_to_string() {
@@ -423,6 +657,64 @@ mod tests {
         assert_eq!("SHA256:YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o", key.fingerprint());
     }
 
+    // generated with `ssh-keygen -l -E <hash>` for each key
+    const TEST_FINGERPRINT_STRINGS: &[(&str, &[&str])] = &[
+        (TEST_RSA_KEY, &[
+            "2048 MD5:e9:a1:5b:cd:a3:69:d2:d9:17:cb:09:3e:78:e1:0d:dd demos@siril (RSA)",
+            "2048 SHA1:6Px8oSYHDFsV2hCHWmtEge7fD+A demos@siril (RSA)",
+            "2048 SHA256:YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o demos@siril (RSA)",
+            "2048 SHA512:koex//Ftc5Ldr6npqvKcMKjZ2jKGhM4mCYFJ56XEXKMXKaX66aYYt7pwqHIlf/sPl/2a6kiHp/p3bPI+cLeWrg demos@siril (RSA)",
+        ]),
+        (TEST_DSA_KEY, &[
+            "1024 MD5:91:60:5d:57:5a:38:d9:95:b6:b7:67:c1:bc:1e:9a:fa demos@siril (DSA)",
+            "1024 SHA1:wLvykRxonFmWv92BiT0rHT/6d+E demos@siril (DSA)",
+            "1024 SHA256:/Pyxrjot1Hs5PN2Dpg/4pK2wxxtP9Igc3sDTAWIEXT4 demos@siril (DSA)",
+            "1024 SHA512:eRD/Vzveo2+b+I0PfZrGEj1pUksHUm+QU1jRKcmWNM9JcqxptX9iBE+nhROIJcU+ZctGhuIshMurDz0Zdwd4JQ demos@siril (DSA)",
+        ]),
+        (TEST_ED25519_KEY, &[
+            "256 MD5:89:b4:6a:6c:7d:71:f0:b9:7d:ae:a3:98:25:67:e5:f7 demos@siril (ED25519)",
+            "256 SHA1:GbskGP2Rsfgcw3+pR4b78JwrFjA demos@siril (ED25519)",
+            "256 SHA256:A/lHzXxsgbp11dcKKfSDyNQIdep7EQgZEoRYVDBfNdI demos@siril (ED25519)",
+            "256 SHA512:3Ixu45sALw0fJTi1xiCUMWweg3ti6WVn1u8xmzFJ+ajOtgIhr/BUwjj+dDwVTv3LMwhDKWhOCNxLJLDCTzwApQ demos@siril (ED25519)",
+        ]),
+        (TEST_ECDSA256_KEY, &[
+            "256 MD5:b4:54:ec:b2:24:80:81:5b:75:22:3a:2b:f0:17:43:34 demos@siril (ECDSA)",
+            "256 SHA1:keCIjZhWolsLQRaO1WrS0osme/Y demos@siril (ECDSA)",
+            "256 SHA256:BzS5YXMW/d2vFk8Oqh+nKmvKr8X/FTLBfJgDGLu5GAs demos@siril (ECDSA)",
+            "256 SHA512:fADRCNII/uO4CzKYQTD38TmryiRmnRvRL+9fBbvKT9LlHGyQ9bgSdTRg/9QDa8lgxbkx2tJpjWQ7EsdOeISQGw demos@siril (ECDSA)",
+        ]),
+        (TEST_SK_ECDSA256_KEY, &[
+            "256 MD5:b3:7b:90:f8:e1:e6:4a:87:1b:11:c9:1e:b4:27:45:b0 demos@siril (ECDSA-SK)",
+            "256 SHA1:2AxI9IdmWFhIWMG0D+9tReLFpVA demos@siril (ECDSA-SK)",
+            "256 SHA256:Jk/GLeWgcocnr8FUIOjxkFsbRwFl6m1XqDFWJSvNLJI demos@siril (ECDSA-SK)",
+            "256 SHA512:K4HLhpcr7lf4MD3cRnA3RucIJyWTHlb7rMantJSdJMU1JK5do4QpYtqA+SyD7XHrxLsiTvaF56y2UVRpr/EFKw demos@siril (ECDSA-SK)",
+        ]),
+    ];
+
+    #[test]
+    fn fingerprint_algorithms() {
+        let algorithms = [FingerprintAlgorithm::Md5, FingerprintAlgorithm::Sha1, FingerprintAlgorithm::Sha256, FingerprintAlgorithm::Sha512];
+        for &(key, expected) in TEST_FINGERPRINT_STRINGS {
+            let key = PublicKey::parse(key).unwrap();
+            for (&algorithm, &expected) in algorithms.iter().zip(expected) {
+                assert_eq!(expected, key.to_fingerprint_string_with(algorithm, algorithm.default_encoding()));
+                assert!(expected.contains(&key.fingerprint_with(algorithm, algorithm.default_encoding())));
+            }
+        }
+    }
+
+    #[test]
+    fn fingerprint_encodings() {
+        let key = PublicKey::parse(TEST_ED25519_KEY).unwrap();
+        assert_eq!(
+            "SHA256:03:f9:47:cd:7c:6c:81:ba:75:d5:d7:0a:29:f4:83:c8:d4:08:75:ea:7b:11:08:19:12:84:58:54:30:5f:35:d2",
+            key.fingerprint_with(FingerprintAlgorithm::Sha256, FingerprintEncoding::Hex)
+        );
+        assert_eq!("MD5:ibRqbH1x8Ll9rqOYJWfl9w", key.fingerprint_with(FingerprintAlgorithm::Md5, FingerprintEncoding::Base64));
+        assert_eq!(key.fingerprint(), key.fingerprint_with(FingerprintAlgorithm::Sha256, FingerprintEncoding::Base64));
+        assert_eq!("MD5", FingerprintAlgorithm::Md5.to_string());
+    }
+
     #[test]
     fn rsa_fingerprint_string() {
         let key = PublicKey::parse(TEST_RSA_KEY).unwrap();
@@ -529,4 +821,73 @@ mod tests {
         let key = PublicKey::parse(TEST_ECDSA256_KEY).unwrap();
         assert_eq!("256 SHA256:BzS5YXMW/d2vFk8Oqh+nKmvKr8X/FTLBfJgDGLu5GAs demos@siril (ECDSA)", key.to_fingerprint_string());
     }
+
+    #[test]
+    fn ed25519_from_components() {
+        let point = [
+            0x08, 0x41, 0xaf, 0xaf, 0xbe, 0x15, 0x05, 0xc1, 0xf2, 0x49, 0x20, 0x38, 0xc6, 0xdd, 0xc4, 0x1b,
+            0xb2, 0xac, 0x7c, 0xee, 0x5f, 0x91, 0xe4, 0x12, 0x5b, 0x30, 0xac, 0xde, 0x83, 0x42, 0x83, 0x7f,
+        ];
+        let mut key = PublicKey::from_ed25519(&point);
+        key.set_comment("demos@siril");
+        assert_eq!(TEST_ED25519_KEY, key.to_string());
+    }
+
+    #[test]
+    fn ecdsa_from_components() {
+        let parsed = PublicKey::parse(TEST_ECDSA256_KEY).unwrap();
+        let point = match parsed.data {
+            Data::Ecdsa { ref key, .. } => key.clone(),
+            _ => panic!("wrong key data"),
+        };
+        let mut key = PublicKey::from_ecdsa(Curve::Nistp256, &point).unwrap();
+        key.set_comment("demos@siril");
+        assert_eq!(TEST_ECDSA256_KEY, key.to_string());
+
+        // the compressed point is x with the parity of y, which is odd
+        let mut compressed = vec![0x03];
+        compressed.extend_from_slice(&point[1..33]);
+        let key = PublicKey::from_ecdsa(Curve::Nistp256, &compressed).unwrap();
+        assert_eq!(parsed.fingerprint(), key.fingerprint());
+
+        // the wrong parity, a point that's not on the curve, and a point for
+        // the wrong curve
+        compressed[0] = 0x02;
+        let key = PublicKey::from_ecdsa(Curve::Nistp256, &compressed).unwrap();
+        assert_ne!(parsed.fingerprint(), key.fingerprint());
+        let mut bad = point.clone();
+        bad[64] ^= 1;
+        assert!(PublicKey::from_ecdsa(Curve::Nistp256, &bad).is_err());
+        assert!(PublicKey::from_ecdsa(Curve::Nistp384, &point).is_err());
+        assert!(PublicKey::from_ecdsa(Curve::Nistp256, &[0]).is_err());
+    }
+
+    #[test]
+    fn sk_ed25519() {
+        let key = PublicKey::parse(TEST_SK_ED25519_KEY).unwrap();
+        assert_eq!(TEST_SK_ED25519_KEY, key.to_string());
+        assert_eq!("sk-ssh-ed25519@openssh.com", key.keytype());
+        match key.data {
+            Data::SkEd25519 { ref key, ref application } => {
+                assert_eq!(32, key.len());
+                assert_eq!("ssh:", application);
+            },
+            _ => panic!("wrong key data"),
+        }
+        assert_eq!("256 SHA256:I8hm/KSr7cBbkxgHo+4nWclmxCIk8zpsFt5rBTt/Fsg demos@siril (ED25519-SK)", key.to_fingerprint_string());
+    }
+
+    #[test]
+    fn sk_ecdsa() {
+        let key = PublicKey::parse(TEST_SK_ECDSA256_KEY).unwrap();
+        assert_eq!(TEST_SK_ECDSA256_KEY, key.to_string());
+        assert_eq!("sk-ecdsa-sha2-nistp256@openssh.com", key.keytype());
+        assert_eq!("256 SHA256:Jk/GLeWgcocnr8FUIOjxkFsbRwFl6m1XqDFWJSvNLJI demos@siril (ECDSA-SK)", key.to_fingerprint_string());
+        // security keys can't be converted to the pem formats
+        assert!(key.to_spki_der().is_err());
+
+        // the application is part of the key
+        let plain = PublicKey::parse(TEST_ECDSA256_KEY).unwrap();
+        assert_ne!(plain.fingerprint(), key.fingerprint());
+    }
 }
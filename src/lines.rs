@@ -0,0 +1,233 @@
+//! lines
+//!
+//! this module provides the line-preserving model of the line based files,
+//! authorized_keys, known_hosts and allowed_signers. every line is kept,
+//! including comments, blank lines, and lines that failed to parse, and
+//! entries keep their original text until they are modified, so a file that
+//! is written back out without changes is byte for byte the same.
+
+use errors::*;
+
+use std::fmt;
+
+/// Entry is a line of a file that can be parsed into something meaningful
+pub trait Entry: Sized {
+    fn parse(text: &str) -> Result<Self>;
+}
+
+/// Line is a single line of a file. lines that would be skipped are kept so
+/// that the file can be written back out unchanged.
+#[derive(Debug)]
+pub enum Line<T> {
+    Entry(T),
+    /// a line starting with a '#', possibly after some whitespace
+    Comment(String),
+    /// a line containing nothing but whitespace
+    Blank(String),
+    /// a line that couldn't be parsed, along with the reason why
+    Invalid(String, Error),
+}
+
+impl<T: Entry> Line<T> {
+    fn parse(text: &str) -> Self {
+        let trimmed = text.trim_start();
+        if trimmed.is_empty() {
+            Line::Blank(text.to_string())
+        } else if trimmed.starts_with('#') {
+            Line::Comment(text.to_string())
+        } else {
+            match T::parse(text) {
+                Ok(entry) => Line::Entry(entry),
+                Err(e) => Line::Invalid(text.to_string(), e),
+            }
+        }
+    }
+}
+
+/// FileLine keeps the original text of an entry for as long as it hasn't been
+/// modified, along with the line ending that followed it.
+#[derive(Debug)]
+struct FileLine<T> {
+    line: Line<T>,
+    original: Option<String>,
+    ending: String,
+}
+
+impl<T> FileLine<T> {
+    fn new(line: Line<T>, ending: &str) -> Self {
+        FileLine {
+            line,
+            original: None,
+            ending: ending.to_string(),
+        }
+    }
+}
+
+impl<T: fmt::Display> FileLine<T> {
+    fn text(&self) -> String {
+        if let Some(ref original) = self.original {
+            return original.clone();
+        }
+        match self.line {
+            Line::Entry(ref entry) => entry.to_string(),
+            Line::Comment(ref text) | Line::Blank(ref text) | Line::Invalid(ref text, _) => text.clone(),
+        }
+    }
+}
+
+/// Lines is every line of a file, in order
+#[derive(Debug)]
+pub struct Lines<T> {
+    lines: Vec<FileLine<T>>,
+}
+
+impl<T> Default for Lines<T> {
+    fn default() -> Self {
+        Lines { lines: vec![] }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Lines<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            write!(f, "{}{}", line.text(), line.ending)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Entry> Lines<T> {
+    /// parse reads the lines of contents. this can't fail, lines that aren't
+    /// valid are kept as Line::Invalid.
+    pub fn parse(contents: &str) -> Self {
+        let lines = split_lines(contents)
+            .into_iter()
+            .map(|(text, ending)| {
+                let line = Line::parse(text);
+                let original = match line {
+                    Line::Entry(..) => Some(text.to_string()),
+                    _ => None,
+                };
+                FileLine {
+                    line,
+                    original,
+                    ending: ending.to_string(),
+                }
+            })
+            .collect();
+        Lines { lines }
+    }
+
+    /// iter returns every line in order
+    pub fn iter(&self) -> impl Iterator<Item = &Line<T>> {
+        self.lines.iter().map(|l| &l.line)
+    }
+
+    /// entries returns the successfully parsed entries in order
+    pub fn entries(&self) -> impl Iterator<Item = &T> {
+        self.iter().filter_map(|l| match *l {
+            Line::Entry(ref entry) => Some(entry),
+            _ => None,
+        })
+    }
+
+    /// entry_mut returns the entry on line n (counting from zero) for
+    /// modification, or None if that line isn't an entry. the line will be
+    /// printed from the modified entry from then on.
+    pub fn entry_mut(&mut self, n: usize) -> Option<&mut T> {
+        let line = self.lines.get_mut(n)?;
+        match line.line {
+            Line::Entry(ref mut entry) => {
+                line.original = None;
+                Some(entry)
+            }
+            _ => None,
+        }
+    }
+
+    /// ending returns the line ending used by the file, defaulting to a plain
+    /// newline.
+    fn ending(&self) -> String {
+        self.lines
+            .iter()
+            .rev()
+            .map(|l| l.ending.as_str())
+            .find(|e| !e.is_empty())
+            .unwrap_or("\n")
+            .to_string()
+    }
+
+    /// push adds an entry to the end of the file. if the file didn't end with
+    /// a newline, one is added to the current last line.
+    pub fn push(&mut self, entry: T) {
+        let ending = self.ending();
+        if let Some(last) = self.lines.last_mut() {
+            if last.ending.is_empty() {
+                last.ending = ending.clone();
+            }
+        }
+        self.lines.push(FileLine::new(Line::Entry(entry), &ending));
+    }
+
+    /// remove removes line n (counting from zero) and returns it
+    ///
+    /// # Panics
+    ///
+    /// Panics if n is out of bounds.
+    pub fn remove(&mut self, n: usize) -> Line<T> {
+        self.lines.remove(n).line
+    }
+
+    /// retain removes every entry for which f returns false, and returns the
+    /// number of entries removed. lines that aren't entries are always kept.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) -> usize {
+        let before = self.lines.len();
+        self.lines.retain(|l| match l.line {
+            Line::Entry(ref entry) => f(entry),
+            _ => true,
+        });
+        before - self.lines.len()
+    }
+
+    /// replace replaces every entry for which f returns some entries with
+    /// those entries, each on its own line.
+    pub fn replace<F: FnMut(&T) -> Option<Vec<T>>>(&mut self, mut f: F) {
+        let ending = self.ending();
+        let mut lines = Vec::with_capacity(self.lines.len());
+        for line in self.lines.drain(..) {
+            let entries = match line.line {
+                Line::Entry(ref entry) => f(entry),
+                _ => None,
+            };
+            match entries {
+                Some(entries) => {
+                    let last = entries.len().saturating_sub(1);
+                    for (i, entry) in entries.into_iter().enumerate() {
+                        let ending = if i == last { &line.ending } else { &ending };
+                        lines.push(FileLine::new(Line::Entry(entry), ending));
+                    }
+                }
+                None => lines.push(line),
+            }
+        }
+        self.lines = lines;
+    }
+}
+
+/// split_lines splits text into lines, returning each line along with the
+/// line ending that terminated it. the last line has an empty ending if the
+/// text doesn't end with a newline.
+pub fn split_lines(text: &str) -> Vec<(&str, &str)> {
+    let mut lines = vec![];
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (line, ending, next) = match rest.find('\n') {
+            Some(i) if i > 0 && rest.as_bytes()[i - 1] == b'\r' => (&rest[..i - 1], "\r\n", &rest[i + 1..]),
+            Some(i) => (&rest[..i], "\n", &rest[i + 1..]),
+            None => (rest, "", ""),
+        };
+        lines.push((line, ending));
+        rest = next;
+    }
+    lines
+}
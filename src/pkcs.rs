@@ -0,0 +1,354 @@
+//! pkcs
+//!
+//! this module provides conversions between public keys and the DER and PEM
+//! encodings used outside of ssh: the SubjectPublicKeyInfo structure from
+//! X.509 (https://tools.ietf.org/html/rfc5280#section-4.1), which appears as
+//! `-----BEGIN PUBLIC KEY-----`, and the PKCS#1 RSAPublicKey structure
+//! (https://tools.ietf.org/html/rfc8017#appendix-A.1.1), which appears as
+//! `-----BEGIN RSA PUBLIC KEY-----`. these are the formats written by
+//! `ssh-keygen -e -m PKCS8` and `ssh-keygen -e -m PEM` respectively.
+
+use errors::*;
+
+use der;
+use ec;
+use pem;
+use {Curve, Data, PublicKey};
+
+const SPKI_LABEL: &str = "PUBLIC KEY";
+const PKCS1_LABEL: &str = "RSA PUBLIC KEY";
+pub const PEM_LINE_LEN: usize = 64;
+
+// rsaEncryption, 1.2.840.113549.1.1.1
+pub const OID_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+// id-dsa, 1.2.840.10040.4.1
+pub const OID_DSA: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x38, 0x04, 0x01];
+// id-ecPublicKey, 1.2.840.10045.2.1
+pub const OID_EC: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+// id-Ed25519, 1.3.101.112 from https://tools.ietf.org/html/rfc8410
+pub const OID_ED25519: &[u8] = &[0x2b, 0x65, 0x70];
+// the named curves from https://tools.ietf.org/html/rfc5480#section-2.1.1.1
+// secp256r1 1.2.840.10045.3.1.7, secp384r1 1.3.132.0.34, and secp521r1
+// 1.3.132.0.35
+const OID_NISTP256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_NISTP384: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+const OID_NISTP521: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x23];
+
+impl Curve {
+    /// oid returns the encoded object identifier of the named curve
+    pub(crate) fn oid(&self) -> &'static [u8] {
+        match *self {
+            Curve::Nistp256 => OID_NISTP256,
+            Curve::Nistp384 => OID_NISTP384,
+            Curve::Nistp521 => OID_NISTP521,
+        }
+    }
+
+    /// from_oid returns the curve with the given encoded object identifier
+    pub(crate) fn from_oid(oid: &[u8]) -> Result<Self> {
+        Ok(match oid {
+            OID_NISTP256 => Curve::Nistp256,
+            OID_NISTP384 => Curve::Nistp384,
+            OID_NISTP521 => Curve::Nistp521,
+            _ => return Err(ErrorKind::UnsupportedCurve(der::oid_to_string(oid)).into()),
+        })
+    }
+}
+
+impl PublicKey {
+    /// from_spki_der reads a public key from a DER encoded
+    /// SubjectPublicKeyInfo structure. all of the supported key algorithms
+    /// can be read this way, with ecdsa keys using named curves and ed25519
+    /// keys using the identifier from https://tools.ietf.org/html/rfc8410.
+    pub fn from_spki_der(der: &[u8]) -> Result<Self> {
+        let mut reader = der::Reader::new(der);
+        let key = PublicKey::read_spki(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        Ok(key)
+    }
+
+    /// to_spki_der returns the key as a DER encoded SubjectPublicKeyInfo
+    /// structure. security keys have no SubjectPublicKeyInfo encoding, so
+    /// this fails for them.
+    pub fn to_spki_der(&self) -> Result<Vec<u8>> {
+        let mut writer = der::Writer::new();
+        self.write_spki(&mut writer)?;
+        Ok(writer.into_vec())
+    }
+
+    /// from_pkcs1_der reads an rsa public key from a DER encoded PKCS#1
+    /// RSAPublicKey structure.
+    pub fn from_pkcs1_der(der: &[u8]) -> Result<Self> {
+        let mut reader = der::Reader::new(der);
+        let mut seq = reader.read_sequence()?;
+        let n = seq.read_integer()?;
+        let e = seq.read_integer()?;
+        if !seq.is_empty() || !reader.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        Ok(PublicKey::from_rsa(e.to_vec(), n.to_vec()))
+    }
+
+    /// to_pkcs1_der returns the key as a DER encoded PKCS#1 RSAPublicKey
+    /// structure. PKCS#1 only describes rsa keys, so this fails for every
+    /// other type of key.
+    pub fn to_pkcs1_der(&self) -> Result<Vec<u8>> {
+        match self.data {
+            Data::Rsa { ref exponent, ref modulus } => {
+                let mut writer = der::Writer::new();
+                writer.write_sequence(|w| {
+                    w.write_integer(modulus);
+                    w.write_integer(exponent);
+                });
+                Ok(writer.into_vec())
+            }
+            _ => Err(ErrorKind::UnsupportedKeytype(self.keytype().into()).into()),
+        }
+    }
+
+    /// from_pem reads a public key from either a `-----BEGIN PUBLIC KEY-----`
+    /// or a `-----BEGIN RSA PUBLIC KEY-----` PEM block.
+    ///
+    /// ```
+    /// # use openssh_keys::PublicKey;
+    /// let key = PublicKey::from_pem("-----BEGIN PUBLIC KEY-----
+    /// MCowBQYDK2VwAyEACEGvr74VBcHySSA4xt3EG7KsfO5fkeQSWzCs3oNCg38=
+    /// -----END PUBLIC KEY-----
+    /// ").unwrap();
+    /// assert_eq!("SHA256:A/lHzXxsgbp11dcKKfSDyNQIdep7EQgZEoRYVDBfNdI", key.fingerprint());
+    /// ```
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let pem = pem::parse(pem)?;
+        match pem.label.as_str() {
+            SPKI_LABEL => PublicKey::from_spki_der(&pem.data),
+            PKCS1_LABEL => PublicKey::from_pkcs1_der(&pem.data),
+            _ => Err(ErrorKind::InvalidFormat.into()),
+        }
+    }
+
+    /// to_spki_pem returns the key as a `-----BEGIN PUBLIC KEY-----` PEM
+    /// block, like `ssh-keygen -e -m PKCS8`.
+    pub fn to_spki_pem(&self) -> Result<String> {
+        Ok(pem::encode(SPKI_LABEL, &self.to_spki_der()?, PEM_LINE_LEN))
+    }
+
+    /// to_pkcs1_pem returns an rsa key as a `-----BEGIN RSA PUBLIC KEY-----`
+    /// PEM block, like `ssh-keygen -e -m PEM`.
+    pub fn to_pkcs1_pem(&self) -> Result<String> {
+        Ok(pem::encode(PKCS1_LABEL, &self.to_pkcs1_der()?, PEM_LINE_LEN))
+    }
+
+    /// read_spki reads a SubjectPublicKeyInfo structure from reader
+    pub(crate) fn read_spki(reader: &mut der::Reader) -> Result<Self> {
+        let mut spki = reader.read_sequence()?;
+        let mut algorithm = spki.read_sequence()?;
+        let oid = algorithm.read_oid()?;
+        let key = spki.read_bit_string()?;
+        if !spki.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+
+        let data = match oid {
+            OID_RSA => {
+                // the parameters are required to be NULL, but some encoders
+                // leave them out entirely
+                if !algorithm.is_empty() {
+                    algorithm.read_null()?;
+                }
+                return PublicKey::from_pkcs1_der(key);
+            }
+            OID_DSA => {
+                // the domain parameters p, q, and g are stored with the
+                // algorithm, and the key itself is just the public integer.
+                // see https://tools.ietf.org/html/rfc3279#section-2.3.2
+                let mut params = algorithm.read_sequence()?;
+                let p = params.read_integer()?;
+                let q = params.read_integer()?;
+                let g = params.read_integer()?;
+                let pub_key = der::Reader::new(key).read_integer()?;
+                Data::Dsa {
+                    p: p.into(),
+                    q: q.into(),
+                    g: g.into(),
+                    pub_key: pub_key.into(),
+                }
+            }
+            OID_EC => {
+                // the curve is stored with the algorithm, and the key is the
+                // encoded point, which may be compressed.
+                let curve = Curve::from_oid(algorithm.read_oid()?)?;
+                Data::Ecdsa {
+                    key: ec::decode_point(&curve, key)?,
+                    curve,
+                }
+            }
+            OID_ED25519 => {
+                if key.len() != 32 {
+                    return Err(ErrorKind::InvalidFormat.into());
+                }
+                Data::Ed25519 { key: key.into() }
+            }
+            _ => return Err(ErrorKind::UnsupportedKeytype(der::oid_to_string(oid)).into()),
+        };
+        if !algorithm.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+
+        Ok(PublicKey {
+            data,
+            comment: None,
+        })
+    }
+
+    /// write_spki writes the key as a SubjectPublicKeyInfo structure
+    pub(crate) fn write_spki(&self, writer: &mut der::Writer) -> Result<()> {
+        if let Data::SkEd25519 { .. } | Data::SkEcdsa { .. } = self.data {
+            return Err(ErrorKind::UnsupportedKeytype(self.keytype().into()).into());
+        }
+        writer.write_sequence(|w| match self.data {
+            Data::Rsa { .. } => {
+                w.write_sequence(|w| {
+                    w.write_oid(OID_RSA);
+                    w.write_null();
+                });
+                w.write_bit_string(&self.to_pkcs1_der().unwrap());
+            }
+            Data::Dsa { ref p, ref q, ref g, ref pub_key } => {
+                w.write_sequence(|w| {
+                    w.write_oid(OID_DSA);
+                    w.write_sequence(|w| {
+                        w.write_integer(p);
+                        w.write_integer(q);
+                        w.write_integer(g);
+                    });
+                });
+                let mut key = der::Writer::new();
+                key.write_integer(pub_key);
+                w.write_bit_string(&key.into_vec());
+            }
+            Data::Ecdsa { ref curve, ref key } => {
+                w.write_sequence(|w| {
+                    w.write_oid(OID_EC);
+                    w.write_oid(curve.oid());
+                });
+                w.write_bit_string(key);
+            }
+            Data::Ed25519 { ref key } => {
+                w.write_sequence(|w| w.write_oid(OID_ED25519));
+                w.write_bit_string(key);
+            }
+            Data::SkEd25519 { .. } | Data::SkEcdsa { .. } => unreachable!(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_RSA_KEY: &str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQCYH3vPUJThzriVlVKmKOg71EOVYm274oRa5KLWEoK0HmjMc9ru0j4ofouoeW/AVmRVujxfaIGR/8en/lUPkiv5DSeM6aXnDz5cExNptrAy/sMPLQhVALRrqQ+dkS9Ct/YA+A1Le5LPh4MJu79hCDLTwqSdKqDuUcYQzR0M7APslaDCR96zY+VUL4lKObUUd4wsP3opdTQ6G20qXEer14EPGr9N53S/u+JJGLoPlb1uPIH96oKY4t/SeLIRQsocdViRaiF/Aq7kPzWd/yCLVdXJSRt3CftboV4kLBHGteTS551J32MJoqjEi4Q/DucWYrQfx5H3qXVB+/G2HurKPIHL demos@siril";
+    const TEST_DSA_KEY: &str = "ssh-dss AAAAB3NzaC1kc3MAAACBAIkd9CkqldM2St8f53rfJT7kPgiA8leZaN7hdZd48hYJyKzVLoPdBMaGFuOwGjv0Im3JWqWAewANe0xeLceQL0rSFbM/mZV+1gc1nm1WmtVw4KJIlLXl3gS7NYfQ9Ith4wFnZd/xhRz9Q+MBsA1DgXew1zz4dLYI46KmFivJ7XDzAAAAFQC8z4VIhI4HlHTvB7FdwAfqWsvcOwAAAIBEqPIkW3HHDTSEhUhhV2AlIPNwI/bqaCXy2zYQ6iTT3oUh+N4xlRaBSvW+h2NC97U8cxd7Y0dXIbQKPzwNzRX1KA1F9WAuNzrx9KkpCg2TpqXShhp+Sseb+l6uJjthIYM6/0dvr9cBDMeExabPPgBo3Eii2NLbFSqIe86qav8hZAAAAIBk5AetZrG8varnzv1khkKh6Xq/nX9r1UgIOCQos2XOi2ErjlB9swYCzReo1RT7dalITVi7K9BtvJxbutQEOvN7JjJnPJs+M3OqRMMF+anXPdCWUIBxZUwctbkAD5joEjGDrNXHQEw9XixZ9p3wudbISnPFgZhS1sbS9Rlw5QogKg== demos@siril";
+    const TEST_ECDSA256_KEY: &str = "ecdsa-sha2-nistp256 AAAAE2VjZHNhLXNoYTItbmlzdHAyNTYAAAAIbmlzdHAyNTYAAABBBIhfLQrww4DlhYzbSWXoX3ctOQ0jVosvfHfW+QWVotksbPzM2YgkIikTpoHUfZrYpJKWx7WYs5aqeLkdCDdk+jk= demos@siril";
+    const TEST_ED25519_KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril";
+
+    // the following are the output of `ssh-keygen -e -m PKCS8` and
+    // `ssh-keygen -e -m PEM` on the keys above. ssh-keygen can't export
+    // ed25519 keys, so that one comes from python's cryptography package.
+    const TEST_RSA_SPKI: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAmB97z1CU4c64lZVSpijo
+O9RDlWJtu+KEWuSi1hKCtB5ozHPa7tI+KH6LqHlvwFZkVbo8X2iBkf/Hp/5VD5Ir
++Q0njOml5w8+XBMTabawMv7DDy0IVQC0a6kPnZEvQrf2APgNS3uSz4eDCbu/YQgy
+08KknSqg7lHGEM0dDOwD7JWgwkfes2PlVC+JSjm1FHeMLD96KXU0OhttKlxHq9eB
+Dxq/Ted0v7viSRi6D5W9bjyB/eqCmOLf0niyEULKHHVYkWohfwKu5D81nf8gi1XV
+yUkbdwn7W6FeJCwRxrXk0uedSd9jCaKoxIuEPw7nFmK0H8eR96l1Qfvxth7qyjyB
+ywIDAQAB
+-----END PUBLIC KEY-----
+";
+    const TEST_RSA_PKCS1: &str = "-----BEGIN RSA PUBLIC KEY-----
+MIIBCgKCAQEAmB97z1CU4c64lZVSpijoO9RDlWJtu+KEWuSi1hKCtB5ozHPa7tI+
+KH6LqHlvwFZkVbo8X2iBkf/Hp/5VD5Ir+Q0njOml5w8+XBMTabawMv7DDy0IVQC0
+a6kPnZEvQrf2APgNS3uSz4eDCbu/YQgy08KknSqg7lHGEM0dDOwD7JWgwkfes2Pl
+VC+JSjm1FHeMLD96KXU0OhttKlxHq9eBDxq/Ted0v7viSRi6D5W9bjyB/eqCmOLf
+0niyEULKHHVYkWohfwKu5D81nf8gi1XVyUkbdwn7W6FeJCwRxrXk0uedSd9jCaKo
+xIuEPw7nFmK0H8eR96l1Qfvxth7qyjyBywIDAQAB
+-----END RSA PUBLIC KEY-----
+";
+    const TEST_DSA_SPKI: &str = "-----BEGIN PUBLIC KEY-----
+MIIBtjCCASsGByqGSM44BAEwggEeAoGBAIkd9CkqldM2St8f53rfJT7kPgiA8leZ
+aN7hdZd48hYJyKzVLoPdBMaGFuOwGjv0Im3JWqWAewANe0xeLceQL0rSFbM/mZV+
+1gc1nm1WmtVw4KJIlLXl3gS7NYfQ9Ith4wFnZd/xhRz9Q+MBsA1DgXew1zz4dLYI
+46KmFivJ7XDzAhUAvM+FSISOB5R07wexXcAH6lrL3DsCgYBEqPIkW3HHDTSEhUhh
+V2AlIPNwI/bqaCXy2zYQ6iTT3oUh+N4xlRaBSvW+h2NC97U8cxd7Y0dXIbQKPzwN
+zRX1KA1F9WAuNzrx9KkpCg2TpqXShhp+Sseb+l6uJjthIYM6/0dvr9cBDMeExabP
+PgBo3Eii2NLbFSqIe86qav8hZAOBhAACgYBk5AetZrG8varnzv1khkKh6Xq/nX9r
+1UgIOCQos2XOi2ErjlB9swYCzReo1RT7dalITVi7K9BtvJxbutQEOvN7JjJnPJs+
+M3OqRMMF+anXPdCWUIBxZUwctbkAD5joEjGDrNXHQEw9XixZ9p3wudbISnPFgZhS
+1sbS9Rlw5QogKg==
+-----END PUBLIC KEY-----
+";
+    const TEST_ECDSA256_SPKI: &str = "-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEiF8tCvDDgOWFjNtJZehfdy05DSNW
+iy98d9b5BZWi2Sxs/MzZiCQiKROmgdR9mtikkpbHtZizlqp4uR0IN2T6OQ==
+-----END PUBLIC KEY-----
+";
+    const TEST_ED25519_SPKI: &str = "-----BEGIN PUBLIC KEY-----
+MCowBQYDK2VwAyEACEGvr74VBcHySSA4xt3EG7KsfO5fkeQSWzCs3oNCg38=
+-----END PUBLIC KEY-----
+";
+
+    fn check_spki(ssh: &str, spki: &str) {
+        let key = PublicKey::parse(ssh).unwrap();
+        assert_eq!(spki, key.to_spki_pem().unwrap());
+        let parsed = PublicKey::from_pem(spki).unwrap();
+        assert_eq!(key.fingerprint(), parsed.fingerprint());
+        assert_eq!(key.data(), PublicKey::from_spki_der(&key.to_spki_der().unwrap()).unwrap().data());
+    }
+
+    #[test]
+    fn rsa_spki() {
+        check_spki(TEST_RSA_KEY, TEST_RSA_SPKI);
+    }
+
+    #[test]
+    fn rsa_pkcs1() {
+        let key = PublicKey::parse(TEST_RSA_KEY).unwrap();
+        assert_eq!(TEST_RSA_PKCS1, key.to_pkcs1_pem().unwrap());
+        let parsed = PublicKey::from_pem(TEST_RSA_PKCS1).unwrap();
+        assert_eq!(key.fingerprint(), parsed.fingerprint());
+    }
+
+    #[test]
+    fn dsa_spki() {
+        check_spki(TEST_DSA_KEY, TEST_DSA_SPKI);
+    }
+
+    #[test]
+    fn ecdsa_spki() {
+        check_spki(TEST_ECDSA256_KEY, TEST_ECDSA256_SPKI);
+    }
+
+    #[test]
+    fn ed25519_spki() {
+        check_spki(TEST_ED25519_KEY, TEST_ED25519_SPKI);
+    }
+
+    #[test]
+    fn pkcs1_only_rsa() {
+        let key = PublicKey::parse(TEST_ED25519_KEY).unwrap();
+        assert!(key.to_pkcs1_der().is_err());
+        assert!(key.to_pkcs1_pem().is_err());
+    }
+
+    #[test]
+    fn unsupported_algorithm() {
+        // an spki structure for an x25519 key, 1.3.101.110
+        let der = [0x30, 0x0a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x6e, 0x03, 0x01, 0x00];
+        match *PublicKey::from_spki_der(&der).unwrap_err().kind() {
+            ErrorKind::UnsupportedKeytype(ref t) => assert_eq!("1.3.101.110", t),
+            ref e => panic!("unexpected error {:?}", e),
+        }
+    }
+}
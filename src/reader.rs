@@ -12,9 +12,9 @@ pub struct Reader<'a> {
 }
 
 impl<'a> Reader<'a> {
-    pub fn new(data: &[u8]) -> Reader {
+    pub fn new(data: &[u8]) -> Reader<'_> {
         Reader {
-            data: data,
+            data,
             offset: 0,
         }
     }
@@ -27,6 +27,36 @@ impl<'a> Reader<'a> {
         Ok(BigEndian::read_u32(&cur[..4]))
     }
 
+    pub fn read_int(&mut self) -> Result<u32> {
+        let val = self.peek_int()?;
+        self.offset += 4;
+        Ok(val)
+    }
+
+    pub fn read_byte(&mut self) -> Result<u8> {
+        let val = *self.data.get(self.offset).ok_or(ErrorKind::InvalidFormat)?;
+        self.offset += 1;
+        Ok(val)
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64> {
+        let cur = &self.data[self.offset..];
+        if cur.len() < 8 {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        self.offset += 8;
+        Ok(BigEndian::read_u64(&cur[..8]))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offset >= self.data.len()
+    }
+
+    /// remaining returns the bytes that haven't been read yet
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.offset..]
+    }
+
     pub fn read_string(&mut self) -> Result<&'a str> {
         ::std::str::from_utf8(self.read_bytes()?)
             .chain_err(|| ErrorKind::InvalidFormat)
@@ -36,7 +66,7 @@ impl<'a> Reader<'a> {
         // mpints might have an extra byte of zeros at the start.
         // if there is, we can just ignore it, since the number is big-endian
         let bytes = self.read_bytes()?;
-        if bytes[0] == 0 {
+        if bytes.first() == Some(&0) {
             Ok(&bytes[1..])
         } else {
             Ok(bytes)
@@ -0,0 +1,200 @@
+//! rfc4716
+//!
+//! this module provides reading and writing of public keys in the SSH2 (or
+//! SECSH) public key file format described in
+//! https://tools.ietf.org/html/rfc4716, which is used by commercial ssh
+//! implementations and PuTTY, and by `ssh-keygen -e` and `ssh-keygen -i`.
+
+use errors::*;
+
+use PublicKey;
+
+use base64;
+
+const BEGIN: &str = "---- BEGIN SSH2 PUBLIC KEY ----";
+const END: &str = "---- END SSH2 PUBLIC KEY ----";
+const COMMENT: &str = "Comment";
+// lines must be no longer than 72 bytes, and the body is wrapped at 70
+// characters the same way ssh-keygen does.
+const MAX_LINE_LEN: usize = 72;
+const BODY_LINE_LEN: usize = 70;
+
+impl PublicKey {
+    /// parse_rfc4716 reads a public key in the format described in
+    /// https://tools.ietf.org/html/rfc4716. the Comment header, if there is
+    /// one, becomes the comment of the key and all other headers are ignored.
+    ///
+    /// ```
+    /// # use openssh_keys::PublicKey;
+    /// let key = PublicKey::parse_rfc4716("---- BEGIN SSH2 PUBLIC KEY ----
+    /// Comment: \"demos@siril\"
+    /// AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/
+    /// ---- END SSH2 PUBLIC KEY ----
+    /// ").unwrap();
+    /// assert_eq!("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ demos@siril", key.to_string());
+    /// ```
+    pub fn parse_rfc4716(key: &str) -> Result<Self> {
+        let mut lines = key
+            .lines()
+            .map(|l| l.trim_end())
+            .skip_while(|l| l.is_empty());
+        if lines.next() != Some(BEGIN) {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+
+        let mut comment = None;
+        let mut body = String::new();
+        let mut in_headers = true;
+        let mut ended = false;
+        while let Some(line) = lines.next() {
+            if line == END {
+                ended = true;
+                break;
+            }
+            // the body can't contain a ':', so the headers end at the first
+            // line without one.
+            if in_headers && line.contains(':') {
+                // a header ending with a backslash continues on the next line
+                let mut header = line.to_string();
+                while header.ends_with('\\') {
+                    header.pop();
+                    header.push_str(lines.next().ok_or(ErrorKind::InvalidFormat)?);
+                }
+                let colon = header.find(':').unwrap();
+                let (tag, value) = (&header[..colon], header[colon + 1..].trim());
+                if tag.eq_ignore_ascii_case(COMMENT) {
+                    comment = Some(unquote(value).to_string());
+                }
+                continue;
+            }
+            in_headers = false;
+            body.push_str(line.trim_start());
+        }
+        if !ended {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+
+        let blob = base64::decode(&body).chain_err(|| ErrorKind::InvalidFormat)?;
+        let mut key = PublicKey::from_blob(&blob)?;
+        key.comment = comment.and_then(|c| if c.is_empty() { None } else { Some(c) });
+        Ok(key)
+    }
+
+    /// to_rfc4716 returns the key in the format described in
+    /// https://tools.ietf.org/html/rfc4716, the same format produced by
+    /// `ssh-keygen -e`. the comment of the key is written as the Comment
+    /// header.
+    pub fn to_rfc4716(&self) -> String {
+        let mut out = format!("{}\n", BEGIN);
+        if let Some(ref comment) = self.comment {
+            let header = format!("{}: \"{}\"", COMMENT, comment);
+            for line in wrap_header(&header) {
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+        let body = base64::encode(&self.data());
+        for chunk in body.as_bytes().chunks(BODY_LINE_LEN) {
+            // base64 is always ascii, so this can't split a character
+            out.push_str(::std::str::from_utf8(chunk).unwrap());
+            out.push('\n');
+        }
+        out.push_str(END);
+        out.push('\n');
+        out
+    }
+}
+
+/// unquote removes the double quotes surrounding a header value, if there are
+/// any.
+fn unquote(value: &str) -> &str {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    }
+}
+
+/// wrap_header splits a header into lines of at most MAX_LINE_LEN bytes, where
+/// every line but the last ends with a backslash to mark that it continues.
+fn wrap_header(header: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut rest = header;
+    while rest.len() > MAX_LINE_LEN {
+        // leave room for the backslash, and don't split in the middle of a
+        // multi-byte character
+        let mut split = MAX_LINE_LEN - 1;
+        while !rest.is_char_boundary(split) {
+            split -= 1;
+        }
+        lines.push(format!("{}\\", &rest[..split]));
+        rest = &rest[split..];
+    }
+    lines.push(rest.to_string());
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_RSA_KEY: &str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAABAQCYH3vPUJThzriVlVKmKOg71EOVYm274oRa5KLWEoK0HmjMc9ru0j4ofouoeW/AVmRVujxfaIGR/8en/lUPkiv5DSeM6aXnDz5cExNptrAy/sMPLQhVALRrqQ+dkS9Ct/YA+A1Le5LPh4MJu79hCDLTwqSdKqDuUcYQzR0M7APslaDCR96zY+VUL4lKObUUd4wsP3opdTQ6G20qXEer14EPGr9N53S/u+JJGLoPlb1uPIH96oKY4t/SeLIRQsocdViRaiF/Aq7kPzWd/yCLVdXJSRt3CftboV4kLBHGteTS551J32MJoqjEi4Q/DucWYrQfx5H3qXVB+/G2HurKPIHL demos@siril";
+    // output of `ssh-keygen -e -f fixtures/rsa.pub`
+    const TEST_RSA_RFC4716: &str = "---- BEGIN SSH2 PUBLIC KEY ----
+Comment: \"2048-bit RSA, converted by root@vm from OpenSSH\"
+AAAAB3NzaC1yc2EAAAADAQABAAABAQCYH3vPUJThzriVlVKmKOg71EOVYm274oRa5KLWEo
+K0HmjMc9ru0j4ofouoeW/AVmRVujxfaIGR/8en/lUPkiv5DSeM6aXnDz5cExNptrAy/sMP
+LQhVALRrqQ+dkS9Ct/YA+A1Le5LPh4MJu79hCDLTwqSdKqDuUcYQzR0M7APslaDCR96zY+
+VUL4lKObUUd4wsP3opdTQ6G20qXEer14EPGr9N53S/u+JJGLoPlb1uPIH96oKY4t/SeLIR
+QsocdViRaiF/Aq7kPzWd/yCLVdXJSRt3CftboV4kLBHGteTS551J32MJoqjEi4Q/DucWYr
+Qfx5H3qXVB+/G2HurKPIHL
+---- END SSH2 PUBLIC KEY ----
+";
+
+    #[test]
+    fn parse_ssh_keygen_output() {
+        let key = PublicKey::parse_rfc4716(TEST_RSA_RFC4716).unwrap();
+        assert_eq!("SHA256:YTw/JyJmeAAle1/7zuZkPP0C73BQ+6XrFEt2/Wy++2o", key.fingerprint());
+        assert_eq!(Some("2048-bit RSA, converted by root@vm from OpenSSH"), key.comment());
+    }
+
+    #[test]
+    fn to_rfc4716() {
+        let mut key = PublicKey::parse(TEST_RSA_KEY).unwrap();
+        key.set_comment("2048-bit RSA, converted by root@vm from OpenSSH");
+        assert_eq!(TEST_RSA_RFC4716, key.to_rfc4716());
+    }
+
+    #[test]
+    fn parse_headers() {
+        let key = "\r\n---- BEGIN SSH2 PUBLIC KEY ----\r
+x-command: /home/me/bin/lock-in-\\\r
+guest.sh\r
+comment: a comment that goes on \\\r
+and on\r
+AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++\r
+FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/\r
+---- END SSH2 PUBLIC KEY ----\r\n";
+        let key = PublicKey::parse_rfc4716(key).unwrap();
+        assert_eq!("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/ a comment that goes on and on", key.to_string());
+    }
+
+    #[test]
+    fn long_comment_round_trip() {
+        let mut key = PublicKey::parse(TEST_RSA_KEY).unwrap();
+        let comment = "a very long comment with some multi-byte characters like é and ü in it, which has to be wrapped";
+        key.set_comment(comment);
+        let out = key.to_rfc4716();
+        assert!(out.lines().all(|l| l.len() <= MAX_LINE_LEN));
+        let parsed = PublicKey::parse_rfc4716(&out).unwrap();
+        assert_eq!(Some(comment), parsed.comment());
+        assert_eq!(key.fingerprint(), parsed.fingerprint());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(PublicKey::parse_rfc4716(TEST_RSA_KEY).is_err());
+        let unterminated = TEST_RSA_RFC4716.replace(END, "");
+        assert!(PublicKey::parse_rfc4716(&unterminated).is_err());
+    }
+}
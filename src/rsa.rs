@@ -0,0 +1,103 @@
+//! rsa
+//!
+//! this module provides rsa key generation, the way openssl does it for
+//! ssh-keygen: two random primes of half the size each, with a public
+//! exponent of 65537. see https://tools.ietf.org/html/rfc8017#section-3. it
+//! also makes RSASSA-PKCS1-v1_5 signatures. the arithmetic on the private key
+//! is done by the RustCrypto rsa crate.
+
+use errors::*;
+
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use rsa_crate::traits::{PrivateKeyParts, PublicKeyParts};
+use rsa_crate::{BigUint, Pkcs1v15Sign, RsaPrivateKey};
+
+const EXPONENT: u32 = 65537;
+
+/// Key holds the components of an rsa key, as big-endian integers
+pub struct Key {
+    pub e: Vec<u8>,
+    pub n: Vec<u8>,
+    pub d: Vec<u8>,
+    pub iqmp: Vec<u8>,
+    pub p: Vec<u8>,
+    pub q: Vec<u8>,
+}
+
+/// generate generates an rsa key with a modulus of exactly bits bits
+pub fn generate<R: RngCore + CryptoRng>(bits: usize, rng: &mut R) -> Result<Key> {
+    let key = RsaPrivateKey::new_with_exp(rng, bits, &BigUint::from(EXPONENT))
+        .map_err(|_| ErrorKind::UnsupportedKeySize(bits))?;
+    let iqmp = key.crt_coefficient().ok_or(ErrorKind::UnsupportedKeySize(bits))?;
+    let primes = key.primes();
+    Ok(Key {
+        e: key.e().to_bytes_be(),
+        n: key.n().to_bytes_be(),
+        d: key.d().to_bytes_be(),
+        iqmp: iqmp.to_bytes_be(),
+        p: primes[0].to_bytes_be(),
+        q: primes[1].to_bytes_be(),
+    })
+}
+
+/// private_key makes a key to sign with out of the components ssh stores.
+/// the components have to belong together: n has to be p*q, d the inverse
+/// of e, and iqmp the inverse of q mod p. the rsa crate computes its own
+/// iqmp, but one that doesn't match means the key has been tampered with.
+pub fn private_key(e: &[u8], n: &[u8], d: &[u8], iqmp: &[u8], p: &[u8], q: &[u8]) -> Result<RsaPrivateKey> {
+    let primes = vec![BigUint::from_bytes_be(p), BigUint::from_bytes_be(q)];
+    let key = RsaPrivateKey::from_components(BigUint::from_bytes_be(n), BigUint::from_bytes_be(e), BigUint::from_bytes_be(d), primes)
+        .map_err(|_| ErrorKind::InvalidFormat)?;
+    if key.crt_coefficient() != Some(BigUint::from_bytes_be(iqmp)) {
+        return Err(ErrorKind::InvalidFormat.into());
+    }
+    Ok(key)
+}
+
+/// sign makes an RSASSA-PKCS1-v1_5 signature, as described in
+/// https://tools.ietf.org/html/rfc8017#section-8.2.1, of a hash with the
+/// given DigestInfo prefix. the private key operation is blinded, and the
+/// signature is verified before it's returned so a fault can't leak the
+/// key. it's padded to the size of the modulus like openssh does.
+pub fn sign(key: &RsaPrivateKey, prefix: &[u8], hashed: &[u8]) -> Result<Vec<u8>> {
+    let padding = || Pkcs1v15Sign {
+        hash_len: Some(hashed.len()),
+        prefix: prefix.into(),
+    };
+    let signature = key.sign_with_rng(&mut OsRng, padding(), hashed).map_err(|e| match e {
+        rsa_crate::Error::MessageTooLong => ErrorKind::UnsupportedKeySize(key.size() * 8),
+        _ => ErrorKind::InvalidFormat,
+    })?;
+    key.to_public_key().verify(padding(), hashed, &signature)
+        .map_err(|_| ErrorKind::InvalidFormat)?;
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn generate_key() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let key = generate(1024, &mut rng).unwrap();
+        let p = BigUint::from_bytes_be(&key.p);
+        let q = BigUint::from_bytes_be(&key.q);
+        let n = BigUint::from_bytes_be(&key.n);
+        assert_eq!(n, &p * &q);
+        assert_eq!(1024, n.bits());
+        assert_eq!(BigUint::from(EXPONENT), BigUint::from_bytes_be(&key.e));
+
+        // d and iqmp are the inverses of e and q
+        let one = BigUint::from(1u32);
+        let e = BigUint::from_bytes_be(&key.e);
+        let d = BigUint::from_bytes_be(&key.d);
+        assert_eq!(one, (&e * &d) % (&p - 1u32));
+        assert_eq!(one, (&e * &d) % (&q - 1u32));
+        assert_eq!(one, (q * BigUint::from_bytes_be(&key.iqmp)) % &p);
+    }
+}
@@ -0,0 +1,872 @@
+//! signature
+//!
+//! this module makes and verifies signatures in the ssh signature encoding, which is
+//! the signature algorithm name followed by an algorithm specific blob. see
+//! https://tools.ietf.org/html/rfc4253#section-6.6 for ssh-rsa and ssh-dss,
+//! https://tools.ietf.org/html/rfc8332 for rsa-sha2-256 and rsa-sha2-512,
+//! https://tools.ietf.org/html/rfc5656#section-3.1.2 for ecdsa, and
+//! https://tools.ietf.org/html/rfc8709#section-6 for ssh-ed25519. the
+//! signatures made by security keys are described in
+//! https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.u2f.
+
+use errors::*;
+
+use ec;
+use rsa;
+use private_key::{PrivateData, PrivateKey};
+use reader::Reader;
+use writer::Writer;
+use base64;
+use {Curve, Data, PublicKey};
+
+use crypto::digest::Digest;
+use crypto::ed25519;
+use crypto::sha1::Sha1;
+use crypto::sha2::{Sha256, Sha512};
+use num_bigint::BigUint;
+use dsa::signature::hazmat::{PrehashVerifier, RandomizedPrehashSigner};
+use p256::ecdsa::signature::{Signer, Verifier};
+use rand::rngs::OsRng;
+
+use std::fmt;
+
+const SSH_RSA: &str = "ssh-rsa";
+const RSA_SHA2_256: &str = "rsa-sha2-256";
+const RSA_SHA2_512: &str = "rsa-sha2-512";
+const SSH_DSS: &str = "ssh-dss";
+const ECDSA_SHA2_NISTP256: &str = "ecdsa-sha2-nistp256";
+const ECDSA_SHA2_NISTP384: &str = "ecdsa-sha2-nistp384";
+const ECDSA_SHA2_NISTP521: &str = "ecdsa-sha2-nistp521";
+const SSH_ED25519: &str = "ssh-ed25519";
+const SK_ECDSA_SHA2_NISTP256: &str = "sk-ecdsa-sha2-nistp256@openssh.com";
+const WEBAUTHN_SK_ECDSA_SHA2_NISTP256: &str = "webauthn-sk-ecdsa-sha2-nistp256@openssh.com";
+const SK_SSH_ED25519: &str = "sk-ssh-ed25519@openssh.com";
+
+/// SignatureAlgorithm is the algorithm a signature was made with. rsa keys
+/// can make signatures with three of them, and ecdsa security keys with two.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    SshRsa,
+    RsaSha2_256,
+    RsaSha2_512,
+    SshDss,
+    EcdsaSha2Nistp256,
+    EcdsaSha2Nistp384,
+    EcdsaSha2Nistp521,
+    SshEd25519,
+    SkEcdsaSha2Nistp256,
+    WebauthnSkEcdsaSha2Nistp256,
+    SkSshEd25519,
+}
+
+impl SignatureAlgorithm {
+    /// ALL is every signature algorithm that can be verified
+    pub const ALL: &'static [SignatureAlgorithm] = &[
+        SignatureAlgorithm::SshRsa,
+        SignatureAlgorithm::RsaSha2_256,
+        SignatureAlgorithm::RsaSha2_512,
+        SignatureAlgorithm::SshDss,
+        SignatureAlgorithm::EcdsaSha2Nistp256,
+        SignatureAlgorithm::EcdsaSha2Nistp384,
+        SignatureAlgorithm::EcdsaSha2Nistp521,
+        SignatureAlgorithm::SshEd25519,
+        SignatureAlgorithm::SkEcdsaSha2Nistp256,
+        SignatureAlgorithm::WebauthnSkEcdsaSha2Nistp256,
+        SignatureAlgorithm::SkSshEd25519,
+    ];
+
+    /// DEFAULT is the signature algorithms openssh accepts by default since
+    /// OpenSSH 8.8, which is all of them except the ones that use SHA-1.
+    pub const DEFAULT: &'static [SignatureAlgorithm] = &[
+        SignatureAlgorithm::RsaSha2_256,
+        SignatureAlgorithm::RsaSha2_512,
+        SignatureAlgorithm::EcdsaSha2Nistp256,
+        SignatureAlgorithm::EcdsaSha2Nistp384,
+        SignatureAlgorithm::EcdsaSha2Nistp521,
+        SignatureAlgorithm::SshEd25519,
+        SignatureAlgorithm::SkEcdsaSha2Nistp256,
+        SignatureAlgorithm::WebauthnSkEcdsaSha2Nistp256,
+        SignatureAlgorithm::SkSshEd25519,
+    ];
+
+    fn get(name: &str) -> Option<Self> {
+        SignatureAlgorithm::ALL.iter().cloned().find(|a| a.name() == name)
+    }
+
+    /// name returns the name of the algorithm in the signature encoding
+    pub fn name(self) -> &'static str {
+        match self {
+            SignatureAlgorithm::SshRsa => SSH_RSA,
+            SignatureAlgorithm::RsaSha2_256 => RSA_SHA2_256,
+            SignatureAlgorithm::RsaSha2_512 => RSA_SHA2_512,
+            SignatureAlgorithm::SshDss => SSH_DSS,
+            SignatureAlgorithm::EcdsaSha2Nistp256 => ECDSA_SHA2_NISTP256,
+            SignatureAlgorithm::EcdsaSha2Nistp384 => ECDSA_SHA2_NISTP384,
+            SignatureAlgorithm::EcdsaSha2Nistp521 => ECDSA_SHA2_NISTP521,
+            SignatureAlgorithm::SshEd25519 => SSH_ED25519,
+            SignatureAlgorithm::SkEcdsaSha2Nistp256 => SK_ECDSA_SHA2_NISTP256,
+            SignatureAlgorithm::WebauthnSkEcdsaSha2Nistp256 => WEBAUTHN_SK_ECDSA_SHA2_NISTP256,
+            SignatureAlgorithm::SkSshEd25519 => SK_SSH_ED25519,
+        }
+    }
+
+    /// is_sha1 returns whether the algorithm hashes the message with SHA-1,
+    /// which is ssh-rsa and ssh-dss.
+    pub fn is_sha1(self) -> bool {
+        self == SignatureAlgorithm::SshRsa || self == SignatureAlgorithm::SshDss
+    }
+
+    /// is_sk returns whether the algorithm is one of the ones security keys
+    /// sign with, whose signatures carry flags and a counter
+    pub fn is_sk(self) -> bool {
+        self == SignatureAlgorithm::SkEcdsaSha2Nistp256
+            || self == SignatureAlgorithm::WebauthnSkEcdsaSha2Nistp256
+            || self == SignatureAlgorithm::SkSshEd25519
+    }
+}
+
+impl fmt::Display for SignatureAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// SkSignature is what a security key signed along with the message: the
+/// authenticator flags and the signature counter, as described in
+/// https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.u2f.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SkSignature {
+    algorithm: SignatureAlgorithm,
+    flags: u8,
+    counter: u32,
+}
+
+impl SkSignature {
+    /// USER_PRESENT is the flag the key sets when it was touched
+    pub const USER_PRESENT: u8 = 0x01;
+    /// USER_VERIFIED is the flag the key sets when it checked a pin or a
+    /// biometric
+    pub const USER_VERIFIED: u8 = 0x04;
+
+    /// algorithm returns the algorithm the signature was made with
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    /// flags returns the authenticator flags
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    /// counter returns the signature counter, which keys increase with every
+    /// signature they make
+    pub fn counter(&self) -> u32 {
+        self.counter
+    }
+
+    /// user_present returns whether the user touched the key
+    pub fn user_present(&self) -> bool {
+        self.flags & SkSignature::USER_PRESENT != 0
+    }
+
+    /// user_verified returns whether the key verified the user
+    pub fn user_verified(&self) -> bool {
+        self.flags & SkSignature::USER_VERIFIED != 0
+    }
+}
+
+impl PublicKey {
+    /// verify checks that signature, in the ssh signature encoding, is a
+    /// valid signature of message made by this key, and returns the
+    /// algorithm it was made with. signatures that don't verify give an
+    /// InvalidSignature error.
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<SignatureAlgorithm> {
+        verify(self, message, signature)
+    }
+
+    /// verify_allowing is verify, but signatures made with an algorithm
+    /// that isn't in allowed give a DisallowedSignatureAlgorithm error. use
+    /// SignatureAlgorithm::DEFAULT to reject SHA-1 signatures, like openssh.
+    pub fn verify_allowing(&self, message: &[u8], signature: &[u8], allowed: &[SignatureAlgorithm]) -> Result<SignatureAlgorithm> {
+        let mut reader = Reader::new(signature);
+        let sigtype = reader.read_string()?;
+        if !allowed.iter().any(|a| a.name() == sigtype) {
+            return Err(ErrorKind::DisallowedSignatureAlgorithm(sigtype.into()).into());
+        }
+        verify(self, message, signature)
+    }
+
+    /// verify_sk checks a signature made by a security key, like verify, and
+    /// returns the flags and counter the key signed along with the message.
+    /// required are the flags the signature needs to have. sshd requires
+    /// SkSignature::USER_PRESENT unless the key has the no-touch-required
+    /// option, and SkSignature::USER_VERIFIED too if it has verify-required.
+    /// a signature without them gives a UserPresenceRequired or
+    /// UserVerificationRequired error, and a key that isn't a security key
+    /// gives an UnsupportedKeytype error.
+    pub fn verify_sk(&self, message: &[u8], signature: &[u8], required: u8) -> Result<SkSignature> {
+        let sk = match verify_signature(self, message, signature)? {
+            (_, Some(sk)) => sk,
+            (_, None) => return Err(ErrorKind::UnsupportedKeytype(self.keytype().into()).into()),
+        };
+        if required & SkSignature::USER_PRESENT != 0 && !sk.user_present() {
+            return Err(ErrorKind::UserPresenceRequired.into());
+        }
+        if required & SkSignature::USER_VERIFIED != 0 && !sk.user_verified() {
+            return Err(ErrorKind::UserVerificationRequired.into());
+        }
+        Ok(sk)
+    }
+}
+
+// the DER encoded DigestInfo prefixes for the hashes used with rsa, from
+// https://tools.ietf.org/html/rfc8017#section-9.2
+const SHA1_PREFIX: &[u8] = &[0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14];
+const SHA256_PREFIX: &[u8] = &[0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20];
+const SHA512_PREFIX: &[u8] = &[0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03, 0x05, 0x00, 0x04, 0x40];
+
+/// hash returns the digest of data
+pub(crate) fn hash<D: Digest>(mut hasher: D, data: &[u8]) -> Vec<u8> {
+    hasher.input(data);
+    let mut out = vec![0; hasher.output_bytes()];
+    hasher.result(&mut out);
+    out
+}
+
+impl PrivateKey {
+    /// sign signs message and returns the signature in the ssh signature
+    /// encoding. rsa keys sign with rsa-sha2-512, like ssh-keygen and
+    /// ssh-agent do by default, and every other key type only has one
+    /// signature algorithm.
+    pub fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let algorithm = match self.public.data {
+            Data::Rsa { .. } => SignatureAlgorithm::RsaSha2_512,
+            Data::Dsa { .. } => SignatureAlgorithm::SshDss,
+            Data::Ed25519 { .. } => SignatureAlgorithm::SshEd25519,
+            Data::Ecdsa { curve: Curve::Nistp256, .. } => SignatureAlgorithm::EcdsaSha2Nistp256,
+            Data::Ecdsa { curve: Curve::Nistp384, .. } => SignatureAlgorithm::EcdsaSha2Nistp384,
+            Data::Ecdsa { curve: Curve::Nistp521, .. } => SignatureAlgorithm::EcdsaSha2Nistp521,
+            Data::SkEd25519 { .. } | Data::SkEcdsa { .. } => {
+                return Err(ErrorKind::UnsupportedKeytype(self.public.keytype().into()).into());
+            },
+        };
+        self.sign_with_algorithm(message, algorithm)
+    }
+
+    /// sign_with_algorithm signs message with the given signature
+    /// algorithm, which lets rsa keys choose the hash. an algorithm that
+    /// doesn't belong to the key gives a DisallowedSignatureAlgorithm error.
+    /// ecdsa signatures are deterministic, as described in
+    /// https://tools.ietf.org/html/rfc6979, and so are rsa and ed25519 ones.
+    pub fn sign_with_algorithm(&self, message: &[u8], algorithm: SignatureAlgorithm) -> Result<Vec<u8>> {
+        let blob = match (&self.public.data, &self.private, algorithm) {
+            (Data::Rsa { exponent, modulus }, PrivateData::Rsa { d, iqmp, p, q }, SignatureAlgorithm::SshRsa) => {
+                rsa::sign(&rsa::private_key(exponent, modulus, d, iqmp, p, q)?, SHA1_PREFIX, &hash(Sha1::new(), message))?
+            },
+            (Data::Rsa { exponent, modulus }, PrivateData::Rsa { d, iqmp, p, q }, SignatureAlgorithm::RsaSha2_256) => {
+                rsa::sign(&rsa::private_key(exponent, modulus, d, iqmp, p, q)?, SHA256_PREFIX, &hash(Sha256::new(), message))?
+            },
+            (Data::Rsa { exponent, modulus }, PrivateData::Rsa { d, iqmp, p, q }, SignatureAlgorithm::RsaSha2_512) => {
+                rsa::sign(&rsa::private_key(exponent, modulus, d, iqmp, p, q)?, SHA512_PREFIX, &hash(Sha512::new(), message))?
+            },
+            (Data::Dsa { p, q, g, pub_key }, PrivateData::Dsa { x }, SignatureAlgorithm::SshDss) => {
+                sign_dsa(p, q, g, pub_key, x, &hash(Sha1::new(), message))?
+            },
+            (Data::Ed25519 { .. }, PrivateData::Ed25519 { key }, SignatureAlgorithm::SshEd25519) if key.len() == 64 => {
+                ed25519::signature(message, key).to_vec()
+            },
+            (Data::Ecdsa { curve, .. }, PrivateData::Ecdsa { key }, _) if algorithm.name() == self.keytype() => {
+                sign_ecdsa(curve, key, message)?
+            },
+            _ => return Err(ErrorKind::DisallowedSignatureAlgorithm(algorithm.name().into()).into()),
+        };
+        let mut writer = Writer::new();
+        writer.write_string(algorithm.name());
+        writer.write_bytes(blob);
+        Ok(writer.into_vec())
+    }
+}
+
+/// sign_dsa makes a dsa signature with a random k using the dsa crate, and
+/// encodes r and s as 20 byte unsigned integers. the signature is checked
+/// against the public key before it's returned, so a key whose parts don't
+/// belong together gives an InvalidFormat error.
+fn sign_dsa(p: &[u8], q: &[u8], g: &[u8], y: &[u8], x: &[u8], hashed: &[u8]) -> Result<Vec<u8>> {
+    let q = dsa::BigUint::from_bytes_be(q);
+    // ssh-dss only has room for a 160 bit q
+    if q.bits() != 160 {
+        return Err(ErrorKind::InvalidFormat.into());
+    }
+    let components = dsa::Components::from_components(dsa::BigUint::from_bytes_be(p), q, dsa::BigUint::from_bytes_be(g))
+        .map_err(|_| ErrorKind::InvalidFormat)?;
+    let public = dsa::VerifyingKey::from_components(components, dsa::BigUint::from_bytes_be(y))
+        .map_err(|_| ErrorKind::InvalidFormat)?;
+    let key = dsa::SigningKey::from_components(public, dsa::BigUint::from_bytes_be(x))
+        .map_err(|_| ErrorKind::InvalidFormat)?;
+    let sig = key.sign_prehash_with_rng(&mut OsRng, hashed).map_err(|_| ErrorKind::InvalidFormat)?;
+    key.verifying_key().verify_prehash(hashed, &sig).map_err(|_| ErrorKind::InvalidFormat)?;
+    let mut blob = ec::pad(&sig.r().to_bytes_be(), 20).ok_or(ErrorKind::InvalidFormat)?;
+    blob.append(&mut ec::pad(&sig.s().to_bytes_be(), 20).ok_or(ErrorKind::InvalidFormat)?);
+    Ok(blob)
+}
+
+/// sign_ecdsa makes a deterministic ecdsa signature with the hash that goes
+/// with the curve, and encodes r and s as mpints.
+fn sign_ecdsa(curve: &Curve, d: &[u8], message: &[u8]) -> Result<Vec<u8>> {
+    let d = ec::pad(d, ec::field_size(curve)).ok_or(ErrorKind::InvalidFormat)?;
+    let (r, s) = match *curve {
+        Curve::Nistp256 => {
+            let key = p256::ecdsa::SigningKey::from_slice(&d).map_err(|_| ErrorKind::InvalidFormat)?;
+            let sig: p256::ecdsa::Signature = key.sign(message);
+            let (r, s) = sig.split_bytes();
+            (r.to_vec(), s.to_vec())
+        },
+        Curve::Nistp384 => {
+            let key = p384::ecdsa::SigningKey::from_slice(&d).map_err(|_| ErrorKind::InvalidFormat)?;
+            let sig: p384::ecdsa::Signature = key.sign(message);
+            let (r, s) = sig.split_bytes();
+            (r.to_vec(), s.to_vec())
+        },
+        Curve::Nistp521 => ec::sign_p521(&d, &hash(Sha512::new(), message))?,
+    };
+    // r and s are never zero, so there's always something left
+    let mut writer = Writer::new();
+    writer.write_mpint(r.into_iter().skip_while(|&b| b == 0).collect());
+    writer.write_mpint(s.into_iter().skip_while(|&b| b == 0).collect());
+    Ok(writer.into_vec())
+}
+
+/// verify checks that signature is a valid signature of message made by key,
+/// and returns the algorithm it was made with. a signature that doesn't
+/// verify, or that was made with an algorithm that doesn't belong to the key,
+/// gives an InvalidSignature error.
+pub(crate) fn verify(key: &PublicKey, message: &[u8], signature: &[u8]) -> Result<SignatureAlgorithm> {
+    verify_signature(key, message, signature).map(|(algorithm, _)| algorithm)
+}
+
+/// verify_signature is verify, which also returns the flags and counter of
+/// security key signatures
+fn verify_signature(key: &PublicKey, message: &[u8], signature: &[u8]) -> Result<(SignatureAlgorithm, Option<SkSignature>)> {
+    let mut reader = Reader::new(signature);
+    let sigtype = reader.read_string()?;
+    let blob = reader.read_bytes()?;
+    let algorithm = SignatureAlgorithm::get(sigtype).ok_or(ErrorKind::InvalidSignature)?;
+    let sk = if algorithm.is_sk() {
+        Some(SkFields::read(&mut reader, algorithm)?)
+    } else {
+        None
+    };
+    if !reader.is_empty() {
+        return Err(ErrorKind::InvalidFormat.into());
+    }
+
+    let valid = match (&key.data, algorithm, &sk) {
+        (Data::Rsa { exponent, modulus }, SignatureAlgorithm::SshRsa, _) => {
+            verify_rsa(exponent, modulus, SHA1_PREFIX, &hash(Sha1::new(), message), blob)
+        },
+        (Data::Rsa { exponent, modulus }, SignatureAlgorithm::RsaSha2_256, _) => {
+            verify_rsa(exponent, modulus, SHA256_PREFIX, &hash(Sha256::new(), message), blob)
+        },
+        (Data::Rsa { exponent, modulus }, SignatureAlgorithm::RsaSha2_512, _) => {
+            verify_rsa(exponent, modulus, SHA512_PREFIX, &hash(Sha512::new(), message), blob)
+        },
+        (Data::Dsa { p, q, g, pub_key }, SignatureAlgorithm::SshDss, _) => {
+            verify_dsa(p, q, g, pub_key, &hash(Sha1::new(), message), blob)
+        },
+        (Data::Ed25519 { key }, SignatureAlgorithm::SshEd25519, _) => {
+            key.len() == 32 && blob.len() == 64 && ed25519::verify(message, key, blob)
+        },
+        // the ecdsa signature algorithms have the same names as the keys
+        (Data::Ecdsa { curve, key: point }, _, _) if sigtype == key.keytype() => {
+            verify_ecdsa(curve, point, message, blob)?
+        },
+        (Data::SkEd25519 { key, application }, SignatureAlgorithm::SkSshEd25519, Some(sk)) => {
+            key.len() == 32 && blob.len() == 64 && ed25519::verify(&sk.signed_data(application, message)?, key, blob)
+        },
+        (Data::SkEcdsa { key: point, application }, SignatureAlgorithm::SkEcdsaSha2Nistp256, Some(sk))
+        | (Data::SkEcdsa { key: point, application }, SignatureAlgorithm::WebauthnSkEcdsaSha2Nistp256, Some(sk)) => {
+            verify_ecdsa(&Curve::Nistp256, point, &sk.signed_data(application, message)?, blob)?
+        },
+        _ => false,
+    };
+    if !valid {
+        return Err(ErrorKind::InvalidSignature.into());
+    }
+    let sk = sk.map(|sk| SkSignature {
+        algorithm,
+        flags: sk.flags,
+        counter: sk.counter,
+    });
+    Ok((algorithm, sk))
+}
+
+/// SkFields are the fields security key signatures have after the signature
+/// blob. webauthn signatures also have the origin, the clientData json the
+/// browser api signed in place of the message, and the extensions.
+struct SkFields<'a> {
+    flags: u8,
+    counter: u32,
+    webauthn: Option<(&'a str, &'a [u8], &'a [u8])>,
+}
+
+// the authenticator data flags openssh checks on webauthn signatures, from
+// https://www.w3.org/TR/webauthn-2/#sctn-authenticator-data
+const ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+const EXTENSION_DATA: u8 = 0x80;
+
+impl<'a> SkFields<'a> {
+    fn read(reader: &mut Reader<'a>, algorithm: SignatureAlgorithm) -> Result<Self> {
+        let flags = reader.read_byte()?;
+        let counter = reader.read_int()?;
+        let webauthn = if algorithm == SignatureAlgorithm::WebauthnSkEcdsaSha2Nistp256 {
+            Some((reader.read_string()?, reader.read_bytes()?, reader.read_bytes()?))
+        } else {
+            None
+        };
+        Ok(SkFields {
+            flags,
+            counter,
+            webauthn,
+        })
+    }
+
+    /// signed_data returns the data the key signed, which is the hash of the
+    /// application, the flags, the counter, the extensions and the hash of
+    /// the message. for webauthn signatures the clientData is hashed instead
+    /// of the message, and it has to start with the message as the challenge
+    /// and the origin, which is what webauthn_check_prepare_hash in
+    /// https://github.com/openssh/openssh-portable/blob/master/ssh-ecdsa-sk.c
+    /// checks.
+    fn signed_data(&self, application: &str, message: &[u8]) -> Result<Vec<u8>> {
+        let (extensions, hashed) = match self.webauthn {
+            None => (&[][..], hash(Sha256::new(), message)),
+            Some((origin, client_data, extensions)) => {
+                if origin.contains('"')
+                    || self.flags & ATTESTED_CREDENTIAL_DATA != 0
+                    || (self.flags & EXTENSION_DATA != 0) == extensions.is_empty()
+                {
+                    return Err(ErrorKind::InvalidFormat.into());
+                }
+                let prefix = format!(
+                    "{{\"type\":\"webauthn.get\",\"challenge\":\"{}\",\"origin\":\"{}\"",
+                    base64::encode_config(message, base64::URL_SAFE_NO_PAD),
+                    origin
+                );
+                if !client_data.starts_with(prefix.as_bytes()) {
+                    return Err(ErrorKind::InvalidFormat.into());
+                }
+                (extensions, hash(Sha256::new(), client_data))
+            },
+        };
+        let mut signed = hash(Sha256::new(), application.as_bytes());
+        signed.push(self.flags);
+        signed.extend_from_slice(&self.counter.to_be_bytes());
+        signed.extend_from_slice(extensions);
+        signed.extend(hashed);
+        Ok(signed)
+    }
+}
+
+/// verify_rsa checks an RSASSA-PKCS1-v1_5 signature, as described in
+/// https://tools.ietf.org/html/rfc8017#section-8.2.2, of a message with the
+/// given hash.
+fn verify_rsa(exponent: &[u8], modulus: &[u8], prefix: &[u8], hashed: &[u8], sig: &[u8]) -> bool {
+    let n = BigUint::from_bytes_be(modulus);
+    let e = BigUint::from_bytes_be(exponent);
+    let k = (n.bits() as usize).div_ceil(8);
+    // openssh accepts signatures that are shorter than the modulus and pads
+    // them, but never longer ones.
+    if sig.len() > k || n.bits() == 0 {
+        return false;
+    }
+    let s = BigUint::from_bytes_be(sig);
+    if s >= n {
+        return false;
+    }
+    let m = s.modpow(&e, &n).to_bytes_be();
+
+    // the encoded message is 00 01 ff .. ff 00 followed by the DigestInfo
+    let t_len = prefix.len() + hashed.len();
+    if k < t_len + 11 {
+        return false;
+    }
+    let mut expected = vec![0x00, 0x01];
+    expected.resize(k - t_len - 1, 0xff);
+    expected.push(0x00);
+    expected.extend_from_slice(prefix);
+    expected.extend_from_slice(hashed);
+
+    let mut em = vec![0; k - m.len()];
+    em.extend_from_slice(&m);
+    em == expected
+}
+
+/// verify_dsa checks a dsa signature, which ssh encodes as r and s as 20 byte
+/// unsigned integers. see https://tools.ietf.org/html/rfc4253#section-6.6 and
+/// the verification steps in section 4.7 of FIPS 186-4.
+fn verify_dsa(p: &[u8], q: &[u8], g: &[u8], y: &[u8], hashed: &[u8], sig: &[u8]) -> bool {
+    if sig.len() != 40 {
+        return false;
+    }
+    let p = BigUint::from_bytes_be(p);
+    let q = BigUint::from_bytes_be(q);
+    let g = BigUint::from_bytes_be(g);
+    let y = BigUint::from_bytes_be(y);
+    let r = BigUint::from_bytes_be(&sig[..20]);
+    let s = BigUint::from_bytes_be(&sig[20..]);
+    let zero = BigUint::from(0u32);
+    if p == zero || q < BigUint::from(2u32) || r == zero || r >= q || s == zero || s >= q {
+        return false;
+    }
+    // q is prime, so the inverse of s is s^(q-2)
+    let w = s.modpow(&(&q - 2u32), &q);
+    let z = BigUint::from_bytes_be(hashed);
+    let u1 = (z * &w) % &q;
+    let u2 = (&r * &w) % &q;
+    let v = ((g.modpow(&u1, &p) * y.modpow(&u2, &p)) % &p) % &q;
+    v == r
+}
+
+/// verify_ecdsa checks an ecdsa signature, whose blob holds r and s as
+/// mpints. the message is hashed with the hash that goes with the curve.
+fn verify_ecdsa(curve: &Curve, point: &[u8], message: &[u8], blob: &[u8]) -> Result<bool> {
+    let mut reader = Reader::new(blob);
+    let r = reader.read_mpint()?;
+    let s = reader.read_mpint()?;
+    if !reader.is_empty() {
+        return Err(ErrorKind::InvalidFormat.into());
+    }
+    // the ecdsa crates take the signature as r and s concatenated, each
+    // padded to the size of the curve's field.
+    let size = ec::field_size(curve);
+    let rs = match (ec::pad(r, size), ec::pad(s, size)) {
+        (Some(mut r), Some(mut s)) => {
+            r.append(&mut s);
+            r
+        },
+        _ => return Ok(false),
+    };
+    Ok(match *curve {
+        Curve::Nistp256 => match (p256::ecdsa::VerifyingKey::from_sec1_bytes(point), p256::ecdsa::Signature::from_slice(&rs)) {
+            (Ok(key), Ok(sig)) => key.verify(message, &sig).is_ok(),
+            _ => false,
+        },
+        Curve::Nistp384 => match (p384::ecdsa::VerifyingKey::from_sec1_bytes(point), p384::ecdsa::Signature::from_slice(&rs)) {
+            (Ok(key), Ok(sig)) => key.verify(message, &sig).is_ok(),
+            _ => false,
+        },
+        Curve::Nistp521 => match (p521::ecdsa::VerifyingKey::from_sec1_bytes(point), p521::ecdsa::Signature::from_slice(&rs)) {
+            (Ok(key), Ok(sig)) => key.verify(message, &sig).is_ok(),
+            _ => false,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64;
+    use cert::Certificate;
+    use private_key::KeyType;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // certificates for fixtures/ed25519.pub signed by CAs of each key type,
+    // generated with `ssh-keygen -s ca -I sig -n demos -z 1 -O clear`, and
+    // `-t <algorithm>` for the rsa CA.
+    const TEST_CERT_SSH_RSA: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIHiugzr3tIYMKCTi0Pg2lRFNOghZoLXW8V52QGh7tQ8WAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAEAAAABAAAAA3NpZwAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAAAAAAAAAAAAlwAAAAdzc2gtcnNhAAAAAwEAAQAAAIEA8VL+tevx1/L2tfro3Mu6QHHBxOlm9bywDYi3sEQ7OyiBAHmx/6y2A4dauA4vDo5GwsZwsCH7aMocE/XT9kdC2OVPmVUr1qWRZzOL/NOAugRwSAVBWW8o24NMqGp6uli8Zt5eu2uSyMZ+Dyg+ztjWRMDAfidCp29uSlNfwJXTpIEAAACPAAAAB3NzaC1yc2EAAACAaA0UbGxzQ+Zxf9tf3YJXFE6fOp9HSX3o6jPLDgR8DMczWIDNylBq5zLMTBlWQcBkAkjsrohgw9PQHwGC4tczuveCJDxj4pY8JpLoYe6DY939OSIjk7yxxeI2nZ9cGkq6O1wZjkYLMd4qLLNy4/reJDUYm2v89DEp5vz4dGVEkWs=";
+    const TEST_CERT_RSA_SHA2_256: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIFf5qT1d1z3pFQPm3pDezM+joMZOTrdsn1mpcT46tflqAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAEAAAABAAAAA3NpZwAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAAAAAAAAAAAAlwAAAAdzc2gtcnNhAAAAAwEAAQAAAIEA8VL+tevx1/L2tfro3Mu6QHHBxOlm9bywDYi3sEQ7OyiBAHmx/6y2A4dauA4vDo5GwsZwsCH7aMocE/XT9kdC2OVPmVUr1qWRZzOL/NOAugRwSAVBWW8o24NMqGp6uli8Zt5eu2uSyMZ+Dyg+ztjWRMDAfidCp29uSlNfwJXTpIEAAACUAAAADHJzYS1zaGEyLTI1NgAAAICDbMYoRkI11Bq8IN/6e7EuL2D8v07Pu66+UIbK83wfd4R+IuNj2IFuZkgm0KpiaWP4FbH7Vbg4RPeBDhtHd3P+YJO3P8J3YE/tkYuwV5JT4uj1O+kWkApznVgcWGbLDHfujpBecWQgd8BxbXULOGYReyvJ9ffaV9gw5P5nGM2aSw==";
+    const TEST_CERT_RSA_SHA2_512: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIMWu/FTO3Om5LdDWmo3HZMJd78SBJGv+cePHk172AIdqAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAEAAAABAAAAA3NpZwAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAAAAAAAAAAAAlwAAAAdzc2gtcnNhAAAAAwEAAQAAAIEA8VL+tevx1/L2tfro3Mu6QHHBxOlm9bywDYi3sEQ7OyiBAHmx/6y2A4dauA4vDo5GwsZwsCH7aMocE/XT9kdC2OVPmVUr1qWRZzOL/NOAugRwSAVBWW8o24NMqGp6uli8Zt5eu2uSyMZ+Dyg+ztjWRMDAfidCp29uSlNfwJXTpIEAAACUAAAADHJzYS1zaGEyLTUxMgAAAIAC5YQRV7iivcNfENx1kfSxlZF4VZDgzdCvkuqV/ryxzfnuoqhIuMPeYfOt6XXpRQCE40FwblE2XgNO/DSnXXNty1LF036Uzg/u5jwQeC715u0oms2I2ZGiMtgqDoRa0WSZfVtcF+qde41VLnBVD065vBBmTD0lx5mGKHbWlQuHEg==";
+    const TEST_CERT_ECDSA_256: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIGm9OvW4LkYwPg5UIljQc3LvsvUft2Lib0xwSdRYv/QXAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAEAAAABAAAAA3NpZwAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAAAAAAAAAAAAaAAAABNlY2RzYS1zaGEyLW5pc3RwMjU2AAAACG5pc3RwMjU2AAAAQQSwnECdub20F0VCfpWPxr/3Ilei6sYKlecWN42oQ04wZfS6GOaL/W8NAKYvRxQC++nhBt6vSx9Db3qvjqweQv9lAAAAZAAAABNlY2RzYS1zaGEyLW5pc3RwMjU2AAAASQAAACEAhEhHvXSq+6x5YhgOVJ1fr/OMN3+fGHImZeGO1ocNz4AAAAAgENZuhiErsUcu+jKne/pgxibAvN70hSpel8DPof7JJgY=";
+    const TEST_CERT_ECDSA_384: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIHwVTdXlf6x5Cwj2OoJtz01hyFMn40aT+hef32GmhOZzAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAEAAAABAAAAA3NpZwAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAAAAAAAAAAAAiAAAABNlY2RzYS1zaGEyLW5pc3RwMzg0AAAACG5pc3RwMzg0AAAAYQR5FVcHmYR3VCw2Yu4vxKLl9/YSHy0nW6H5XEx7Mmyb/KRQQnYuA6jHUWp8lG4bBsBIDpCHjT7ckGbo5WiXt4O5/81oeWyXKeqEsq1XA2W5hQUeOQchvRPkgOh4SGSRkjgAAACEAAAAE2VjZHNhLXNoYTItbmlzdHAzODQAAABpAAAAMFO91aL6P2m1FWDW58fw3naISIRvoUPPl6pXgg8O7bwSqe6V83RnN6aUYoFuCVbEKgAAADEArKZYNVVC5jzadjMw93RYFbCMimnGaX2rZ1WL3sw61p7lDD81Y+nRLoqkxrj5oYvW";
+    const TEST_CERT_ECDSA_521: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIDUX/SnpPd72eYlhF547Ko8erhZe8/pNv3S9aQhZxSTuAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAEAAAABAAAAA3NpZwAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAAAAAAAAAAAArAAAABNlY2RzYS1zaGEyLW5pc3RwNTIxAAAACG5pc3RwNTIxAAAAhQQAFfdpqWrVaRnMmG3P6Yzlk2E2znsu+03gGtWoyiQDhxpVJ+GV3xqidOLOtATycgVM+4rQb6HOOR35O7R+nwpH4rIBdYR97THAOuWGUeQoZ9kagL0s1qHE8KVD/YBDWyLRv008BDpF8uDSDcgT7vaYDMMo+JEyDjAD99D4v9R7XtxwFEIAAACmAAAAE2VjZHNhLXNoYTItbmlzdHA1MjEAAACLAAAAQVEuaoM0vKEt00AUPoxnsapx8quEOLosZPl3lmywjCarKQ9pHNSaIXdSStlUBWmmlZgZCzHtz9OsdLVxKzj7/WyhAAAAQgCcWnNMLpoEdwj3EqpgaSaIzikPuAimOkwEHEMo0eBAcI32eJmsiEVCorCzt8Ohgd8dMb+h5Xevr8ppeq79/6xlVA==";
+    const TEST_CERT_ED25519: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIM/N6AI8AF9/b48QfzahUO1it9IL7gT9IqfyZCB9z96iAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAEAAAABAAAAA3NpZwAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAAAAAAAAAAAAMwAAAAtzc2gtZWQyNTUxOQAAACC+IALxyAcGiQlqwqWjOmwLGYMvUNcoCCVpS9GwJHOVfQAAAFMAAAALc3NoLWVkMjU1MTkAAABALxbZHlIFoA65KqdYcwO+5tcQum+yNhVLNNWNjjd79fe1DFQEnZnQ9qVofqMq+e/FGGqGeYKyJ7KDb5obgweRDg==";
+    const TEST_CERT_DSA: &str = "ssh-ed25519-cert-v01@openssh.com AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIG/579xLgXaE/wPcKeP4trj6OvJHo/Kc1qJANUBIXgP0AAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAAAAAAAAEAAAABAAAAA3NpZwAAAAkAAAAFZGVtb3MAAAAAAAAAAP//////////AAAAAAAAAAAAAAAAAAABsgAAAAdzc2gtZHNzAAAAgQCYrregYSekLXpXR8lpmYu5ojeIxvlaWF0Gbh2k+cWJ3/qjsewAy+sxU5syZROnmxPdSyrn3mKIUFs6AOiw16kKecGc9xEkVfzLNXlGdkOgMu4CMGNzbVaz+24E30T9G5xN61bmuFUkgQ2jA6c6X2iN8z6FPzidJW0IgVe2v4k/aQAAABUAhMMm8jw3Evj2yKp42BuaGLpA/ZsAAACBAIvXdfYj/29+qiTPi/OmlP2eoYIgseTwW7AIjigKK32WCPNl7yuNqETHfc70ND++8FFgWESlnPQVRXOtcel1APRD6ULoIsx9JQd+e0uFKYCje1TAwN/jt/6P8q3VGMO0r0AUPVNNj70TrbUVcayiD2rw7roY85LzKOfwBdRWmtw5AAAAgBCMAaqY5Ybw7x3tcTan8pRYBZk2Rn4B/8D6F3VhTlXCgtigqwUSEYkk4IHgmhzr3F6NhEQTEKNnDS5TWY4awT11qx6/8AbXtqUgbX9JzzQpFJ8CAg4jKZhLmvbRTSgwKSvCrCMyKMMdgAvKuCHlLoHkCjSKEUURHxf3XDJTaNYtAAAANwAAAAdzc2gtZHNzAAAAKDLWEXMoZxp84nQYJmtKJW+5zSxlXUR+R4B97PUjqLOi6N0eWiM5XvQ=";
+
+    fn check(cert: &str, sigtype: &str) {
+        let cert = Certificate::parse(cert).unwrap();
+        let mut reader = Reader::new(cert.signature());
+        assert_eq!(sigtype, reader.read_string().unwrap());
+        let algorithm = cert.signature_key().verify(&cert.body(), cert.signature()).unwrap();
+        assert_eq!(sigtype, algorithm.name());
+        assert_eq!(sigtype, algorithm.to_string());
+
+        let mut body = cert.body();
+        body[40] ^= 1;
+        match verify(cert.signature_key(), &body, cert.signature()) {
+            Err(Error(ErrorKind::InvalidSignature, _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn rsa() {
+        check(TEST_CERT_SSH_RSA, SSH_RSA);
+        check(TEST_CERT_RSA_SHA2_256, RSA_SHA2_256);
+        check(TEST_CERT_RSA_SHA2_512, RSA_SHA2_512);
+    }
+
+    #[test]
+    fn dsa() {
+        check(TEST_CERT_DSA, SSH_DSS);
+    }
+
+    #[test]
+    fn ecdsa() {
+        check(TEST_CERT_ECDSA_256, "ecdsa-sha2-nistp256");
+        check(TEST_CERT_ECDSA_384, "ecdsa-sha2-nistp384");
+        check(TEST_CERT_ECDSA_521, "ecdsa-sha2-nistp521");
+    }
+
+    #[test]
+    fn ed25519() {
+        check(TEST_CERT_ED25519, SSH_ED25519);
+    }
+
+    #[test]
+    fn allowed() {
+        for cert in &[TEST_CERT_SSH_RSA, TEST_CERT_DSA] {
+            let cert = Certificate::parse(cert).unwrap();
+            let key = cert.signature_key();
+            assert!(key.verify(&cert.body(), cert.signature()).unwrap().is_sha1());
+            match key.verify_allowing(&cert.body(), cert.signature(), SignatureAlgorithm::DEFAULT) {
+                Err(Error(ErrorKind::DisallowedSignatureAlgorithm(_), _)) => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+
+        let cert = Certificate::parse(TEST_CERT_RSA_SHA2_512).unwrap();
+        let key = cert.signature_key();
+        let algorithm = key.verify_allowing(&cert.body(), cert.signature(), SignatureAlgorithm::DEFAULT).unwrap();
+        assert_eq!(SignatureAlgorithm::RsaSha2_512, algorithm);
+        assert!(!algorithm.is_sha1());
+        assert!(key.verify_allowing(&cert.body(), cert.signature(), &[SignatureAlgorithm::RsaSha2_256]).is_err());
+    }
+
+    // security key signatures of MESSAGE by the sk keys with the key material
+    // of fixtures/ed25519 and fixtures/ecdsa, and application "ssh:". there
+    // is no hardware to make them with, so they were made with those keys,
+    // and SSHSIG signatures made the same way pass `ssh-keygen -Y verify`.
+    const MESSAGE: &[u8] = b"hello world\n";
+    const TEST_SK_ED25519_KEY: &str = "sk-ssh-ed25519@openssh.com AAAAGnNrLXNzaC1lZDI1NTE5QG9wZW5zc2guY29tAAAAIAhBr6++FQXB8kkgOMbdxBuyrHzuX5HkElswrN6DQoN/AAAABHNzaDo=";
+    const TEST_SK_ECDSA_KEY: &str = "sk-ecdsa-sha2-nistp256@openssh.com AAAAInNrLWVjZHNhLXNoYTItbmlzdHAyNTZAb3BlbnNzaC5jb20AAAAIbmlzdHAyNTYAAABBBIhfLQrww4DlhYzbSWXoX3ctOQ0jVosvfHfW+QWVotksbPzM2YgkIikTpoHUfZrYpJKWx7WYs5aqeLkdCDdk+jkAAAAEc3NoOg==";
+    // flags 0x01 and counter 7
+    const TEST_SK_ED25519_SIG: &str = "AAAAGnNrLXNzaC1lZDI1NTE5QG9wZW5zc2guY29tAAAAQM3fbxquH7mVcf4mmNTSJzV+9ZuLUzeCTx2bSndlOI764RzLuhydfGSBEpnyDrv78AWKPmlkgaJQYoU3hUE0rQ4BAAAABw==";
+    // flags 0x00 and counter 8, made without touching the key
+    const TEST_SK_ED25519_NO_TOUCH_SIG: &str = "AAAAGnNrLXNzaC1lZDI1NTE5QG9wZW5zc2guY29tAAAAQGDWEVz2fVr3K+k3FdxjkeCXPRlgD0JpS9wJ5PdKxB4HCagH7v0y/aMZuIn4+E/guEkbPd3CcAbWvmFGWP0RTw4AAAAACA==";
+    // flags 0x05 and counter 42
+    const TEST_SK_ECDSA_SIG: &str = "AAAAInNrLWVjZHNhLXNoYTItbmlzdHAyNTZAb3BlbnNzaC5jb20AAABJAAAAIQCyoYFGgFid5WevqMTuiEj6zxVW34oG3sQ5AA45IvJGZAAAACByezO2wnwHb6h2N8RTGc1AmzeA44EHbkQM1IHSr+fCRQUAAAAq";
+    // flags 0x01 and counter 3, origin https://example.com
+    const TEST_WEBAUTHN_SIG: &str = "AAAAK3dlYmF1dGhuLXNrLWVjZHNhLXNoYTItbmlzdHAyNTZAb3BlbnNzaC5jb20AAABJAAAAIQD99RmLRGBDwz9HY926H14OGvsLGQ9KNwQl4M8yO5el9wAAACAHy/2TOX3gH0ZeVjxwkenURaG/wRbQC2BfphUtmcJ59QEAAAADAAAAE2h0dHBzOi8vZXhhbXBsZS5jb20AAABpeyJ0eXBlIjoid2ViYXV0aG4uZ2V0IiwiY2hhbGxlbmdlIjoiYUdWc2JHOGdkMjl5YkdRSyIsIm9yaWdpbiI6Imh0dHBzOi8vZXhhbXBsZS5jb20iLCJjcm9zc09yaWdpbiI6ZmFsc2V9AAAAAA==";
+
+    #[test]
+    fn security_keys() {
+        let ed25519 = PublicKey::parse(TEST_SK_ED25519_KEY).unwrap();
+        let ecdsa = PublicKey::parse(TEST_SK_ECDSA_KEY).unwrap();
+        let cases = [
+            (&ed25519, TEST_SK_ED25519_SIG, SignatureAlgorithm::SkSshEd25519, 0x01, 7),
+            (&ed25519, TEST_SK_ED25519_NO_TOUCH_SIG, SignatureAlgorithm::SkSshEd25519, 0x00, 8),
+            (&ecdsa, TEST_SK_ECDSA_SIG, SignatureAlgorithm::SkEcdsaSha2Nistp256, 0x05, 42),
+            (&ecdsa, TEST_WEBAUTHN_SIG, SignatureAlgorithm::WebauthnSkEcdsaSha2Nistp256, 0x01, 3),
+        ];
+        for &(key, sig, algorithm, flags, counter) in &cases {
+            let sig = base64::decode(sig).unwrap();
+            assert_eq!(algorithm, key.verify_allowing(MESSAGE, &sig, SignatureAlgorithm::DEFAULT).unwrap());
+            let sk = key.verify_sk(MESSAGE, &sig, 0).unwrap();
+            assert_eq!(algorithm, sk.algorithm());
+            assert_eq!(flags, sk.flags());
+            assert_eq!(counter, sk.counter());
+            assert!(algorithm.is_sk());
+            assert!(key.verify(b"hello world", &sig).is_err());
+        }
+    }
+
+    #[test]
+    fn security_key_flags() {
+        let ed25519 = PublicKey::parse(TEST_SK_ED25519_KEY).unwrap();
+        let ecdsa = PublicKey::parse(TEST_SK_ECDSA_KEY).unwrap();
+        let touched = base64::decode(TEST_SK_ED25519_SIG).unwrap();
+        let sk = ed25519.verify_sk(MESSAGE, &touched, SkSignature::USER_PRESENT).unwrap();
+        assert!(sk.user_present());
+        assert!(!sk.user_verified());
+        match ed25519.verify_sk(MESSAGE, &touched, SkSignature::USER_PRESENT | SkSignature::USER_VERIFIED) {
+            Err(Error(ErrorKind::UserVerificationRequired, _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let untouched = base64::decode(TEST_SK_ED25519_NO_TOUCH_SIG).unwrap();
+        match ed25519.verify_sk(MESSAGE, &untouched, SkSignature::USER_PRESENT) {
+            Err(Error(ErrorKind::UserPresenceRequired, _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let verified = base64::decode(TEST_SK_ECDSA_SIG).unwrap();
+        let sk = ecdsa.verify_sk(MESSAGE, &verified, SkSignature::USER_PRESENT | SkSignature::USER_VERIFIED).unwrap();
+        assert!(sk.user_present() && sk.user_verified());
+
+        // changing the flags or the counter breaks the signature
+        for &i in &[verified.len() - 5, verified.len() - 1] {
+            let mut sig = verified.clone();
+            sig[i] ^= 1;
+            match ecdsa.verify_sk(MESSAGE, &sig, 0) {
+                Err(Error(ErrorKind::InvalidSignature, _)) => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+
+        // keys that aren't security keys have no flags
+        let key = PublicKey::parse(include_str!("../fixtures/ed25519.pub")).unwrap();
+        let private = PrivateKey::parse(include_str!("../fixtures/ed25519")).unwrap();
+        match key.verify_sk(MESSAGE, &private.sign(MESSAGE).unwrap(), 0) {
+            Err(Error(ErrorKind::UnsupportedKeytype(_), _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn webauthn() {
+        let key = PublicKey::parse(TEST_SK_ECDSA_KEY).unwrap();
+        let sig = base64::decode(TEST_WEBAUTHN_SIG).unwrap();
+        // the clientData has to have the message as its challenge
+        match key.verify(b"hello world", &sig) {
+            Err(Error(ErrorKind::InvalidFormat, _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+        // and the origin from the signature
+        let mut reader = Reader::new(&sig);
+        reader.read_string().unwrap();
+        reader.read_bytes().unwrap();
+        let origin = sig.len() - reader.remaining().len() + 5 + 4;
+        let mut modified = sig.clone();
+        modified[origin] = b'H';
+        match key.verify(MESSAGE, &modified) {
+            Err(Error(ErrorKind::InvalidFormat, _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn sign() {
+        let message = b"hello world";
+        for key in &[
+            include_str!("../fixtures/rsa"),
+            include_str!("../fixtures/dsa"),
+            include_str!("../fixtures/ecdsa"),
+            include_str!("../fixtures/ed25519"),
+        ] {
+            let key = PrivateKey::parse(key).unwrap();
+            let signature = key.sign(message).unwrap();
+            let algorithm = key.public_key().verify(message, &signature).unwrap();
+            assert_eq!(algorithm, key.public_key().verify_allowing(message, &signature, SignatureAlgorithm::ALL).unwrap());
+            assert!(key.public_key().verify(b"goodbye world", &signature).is_err());
+        }
+
+        for curve in [Curve::Nistp384, Curve::Nistp521] {
+            let key = PrivateKey::generate_with_rng(KeyType::Ecdsa(curve), &mut StdRng::seed_from_u64(0)).unwrap();
+            let signature = key.sign(message).unwrap();
+            key.public_key().verify(message, &signature).unwrap();
+            // the signatures are deterministic
+            assert_eq!(signature, key.sign(message).unwrap());
+        }
+    }
+
+    #[test]
+    fn sign_rsa_algorithms() {
+        let key = PrivateKey::parse(include_str!("../fixtures/rsa")).unwrap();
+        let message = b"hello world";
+        for &algorithm in &[SignatureAlgorithm::SshRsa, SignatureAlgorithm::RsaSha2_256, SignatureAlgorithm::RsaSha2_512] {
+            let signature = key.sign_with_algorithm(message, algorithm).unwrap();
+            assert_eq!(algorithm, key.public_key().verify(message, &signature).unwrap());
+        }
+        assert_eq!(SignatureAlgorithm::RsaSha2_512, key.public_key().verify(message, &key.sign(message).unwrap()).unwrap());
+
+        // `openssl dgst -sha256 -sign fixtures/rsa` of the same message
+        let signature = key.sign_with_algorithm(message, SignatureAlgorithm::RsaSha2_256).unwrap();
+        let mut reader = Reader::new(&signature);
+        assert_eq!(RSA_SHA2_256, reader.read_string().unwrap());
+        assert_eq!("NDGL60oiOACQt/Tuzl9FM3iJyZB3M8XrbGIXgBdd04BqhbuU9NEdHgQMYI33+9Ach6kxaLFWEQoJfZYa1aP5cbdElO1MrbUWIdCOFPQT6KWDiuwGgD9QJY4hieMUuWelO4N45kopSFGOBXMjrNM1vdalfwd7yRE19QUnzjjsp6MfeNyradHjP1775J8hyyIUhI7uFCox60rpeiBPZx+iPRnS8KhSFz8r7AB8jjx3nv0YontsUkcC3EP5rEoOI857PnELAk6tAPR3ia+zmEskmpsu52ts8aYOLAyxAPGZfTcWWamYDLMz3B6mdX2EOu6LRoN3Zz5+km9L5D05Za8tXA==", base64::encode(reader.read_bytes().unwrap()));
+
+        // an algorithm for another type of key
+        match key.sign_with_algorithm(message, SignatureAlgorithm::SshEd25519) {
+            Err(Error(ErrorKind::DisallowedSignatureAlgorithm(_), _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn sign_inconsistent_keys() {
+        // keys whose private parts don't match the public ones are refused
+        // rather than used to make faulty signatures
+        let rsa = PrivateKey::parse(include_str!("../fixtures/rsa")).unwrap();
+        let dsa = PrivateKey::parse(include_str!("../fixtures/dsa")).unwrap();
+        let mut keys = vec![];
+        for i in 0..3 {
+            let mut key = rsa.clone();
+            if let PrivateData::Rsa { ref mut d, ref mut iqmp, ref mut p, .. } = key.private {
+                let part = [iqmp, p, d][i].last_mut().unwrap();
+                *part ^= 2;
+            }
+            keys.push(key);
+        }
+        let mut key = dsa.clone();
+        if let PrivateData::Dsa { ref mut x } = key.private {
+            *x.last_mut().unwrap() ^= 1;
+        }
+        keys.push(key);
+
+        for key in keys {
+            match key.sign(b"hello world") {
+                Err(Error(ErrorKind::InvalidFormat, _)) => (),
+                r => panic!("unexpected result {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn rfc6979() {
+        // the P-256 and SHA-256 test vector from
+        // https://tools.ietf.org/html/rfc6979#appendix-A.2.5
+        let d = vec![
+            0xc9, 0xaf, 0xa9, 0xd8, 0x45, 0xba, 0x75, 0x16, 0x6b, 0x5c, 0x21, 0x57, 0x67, 0xb1, 0xd6, 0x93,
+            0x4e, 0x50, 0xc3, 0xdb, 0x36, 0xe8, 0x9b, 0x12, 0x7b, 0x8a, 0x62, 0x2b, 0x12, 0x0f, 0x67, 0x21,
+        ];
+        let key = PrivateKey {
+            public: PublicKey {
+                data: Data::Ecdsa {
+                    curve: Curve::Nistp256,
+                    key: ec::public_point(&Curve::Nistp256, &d).unwrap(),
+                },
+                comment: None,
+            },
+            private: PrivateData::Ecdsa { key: d },
+        };
+        let signature = key.sign(b"sample").unwrap();
+        let mut reader = Reader::new(&signature);
+        assert_eq!("ecdsa-sha2-nistp256", reader.read_string().unwrap());
+        let mut blob = Reader::new(reader.read_bytes().unwrap());
+        let r = [
+            0xef, 0xd4, 0x8b, 0x2a, 0xac, 0xb6, 0xa8, 0xfd, 0x11, 0x40, 0xdd, 0x9c, 0xd4, 0x5e, 0x81, 0xd6,
+            0x9d, 0x2c, 0x87, 0x7b, 0x56, 0xaa, 0xf9, 0x91, 0xc3, 0x4d, 0x0e, 0xa8, 0x4e, 0xaf, 0x37, 0x16,
+        ];
+        let s = [
+            0xf7, 0xcb, 0x1c, 0x94, 0x2d, 0x65, 0x7c, 0x41, 0xd4, 0x36, 0xc7, 0xa1, 0xb6, 0xe2, 0x9f, 0x65,
+            0xf3, 0xe9, 0x00, 0xdb, 0xb9, 0xaf, 0xf4, 0x06, 0x4d, 0xc4, 0xab, 0x2f, 0x84, 0x3a, 0xcd, 0xa8,
+        ];
+        assert_eq!(&r, blob.read_mpint().unwrap());
+        assert_eq!(&s, blob.read_mpint().unwrap());
+        key.public_key().verify(b"sample", &signature).unwrap();
+    }
+
+    #[test]
+    fn wrong_key() {
+        // a signature is only valid for the key type it was made with
+        let rsa = Certificate::parse(TEST_CERT_RSA_SHA2_256).unwrap();
+        let ecdsa = Certificate::parse(TEST_CERT_ECDSA_256).unwrap();
+        assert!(verify(ecdsa.signature_key(), &rsa.body(), rsa.signature()).is_err());
+        assert!(verify(rsa.signature_key(), &ecdsa.body(), ecdsa.signature()).is_err());
+        // trailing data after the signature isn't allowed
+        let mut sig = rsa.signature().to_vec();
+        sig.push(0);
+        assert!(verify(rsa.signature_key(), &rsa.body(), &sig).is_err());
+    }
+}
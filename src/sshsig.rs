@@ -0,0 +1,414 @@
+//! sshsig
+//!
+//! this module provides creating and verifying the detached signatures made
+//! by `ssh-keygen -Y sign`, in the armored `-----BEGIN SSH SIGNATURE-----`
+//! format. the format is described in
+//! https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.sshsig
+
+use errors::*;
+
+use cert::{self, Certificate};
+use pem;
+use private_key::PrivateKey;
+use reader::Reader;
+use signature::{self, SignatureAlgorithm};
+use writer::Writer;
+use {Data, PublicKey};
+
+use crypto::sha2::{Sha256, Sha512};
+
+use std::fmt;
+
+const MAGIC_PREAMBLE: &[u8] = b"SSHSIG";
+const SIG_VERSION: u32 = 1;
+const PEM_LABEL: &str = "SSH SIGNATURE";
+// ssh-keygen wraps the armored signature at 70 columns
+const LINE_LEN: usize = 70;
+// ssh-keygen doesn't accept ssh-rsa signatures, which use SHA-1
+const RSA_ALGORITHMS: &[SignatureAlgorithm] = &[SignatureAlgorithm::RsaSha2_256, SignatureAlgorithm::RsaSha2_512];
+
+/// HashAlgorithm is the hash of the message that gets signed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    fn get(name: &str) -> Result<Self> {
+        match name {
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            _ => Err(ErrorKind::UnsupportedHash(name.into()).into()),
+        }
+    }
+
+    /// name returns the name of the hash in the signature
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    fn hash(self, message: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => signature::hash(Sha256::new(), message),
+            HashAlgorithm::Sha512 => signature::hash(Sha512::new(), message),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// SshSig is a detached signature of a message, made by `ssh-keygen -Y sign`
+#[derive(Clone, Debug)]
+pub struct SshSig {
+    key: PublicKey,
+    certificate: Option<Certificate>,
+    namespace: String,
+    reserved: Vec<u8>,
+    hash_algorithm: HashAlgorithm,
+    signature: Vec<u8>,
+}
+
+impl fmt::Display for SshSig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", pem::encode(PEM_LABEL, &self.data(), LINE_LEN))
+    }
+}
+
+impl SshSig {
+    /// parse reads an armored signature, like the `.sig` files written by
+    /// `ssh-keygen -Y sign`
+    pub fn parse(armored: &str) -> Result<Self> {
+        let pem = pem::parse(armored)?;
+        if pem.label != PEM_LABEL {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        SshSig::from_blob(&pem.data)
+    }
+
+    /// from_blob reads a signature from the binary encoding returned by
+    /// data()
+    pub fn from_blob(blob: &[u8]) -> Result<Self> {
+        if !blob.starts_with(MAGIC_PREAMBLE) {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        let mut reader = Reader::new(&blob[MAGIC_PREAMBLE.len()..]);
+        if reader.read_int()? != SIG_VERSION {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        // the key can also be a certificate, when it's signed with
+        // `ssh-keygen -Y sign -f id_*-cert.pub`
+        let key_blob = reader.read_bytes()?;
+        let certificate = if Reader::new(key_blob).read_string()?.ends_with(cert::CERT_SUFFIX) {
+            Some(Certificate::from_blob(key_blob)?)
+        } else {
+            None
+        };
+        let key = match certificate {
+            Some(ref cert) => cert.key().clone(),
+            None => PublicKey::from_blob(key_blob)?,
+        };
+        let namespace = reader.read_string()?.to_string();
+        let reserved = reader.read_bytes()?.to_vec();
+        let hash_algorithm = HashAlgorithm::get(reader.read_string()?)?;
+        let signature = reader.read_bytes()?.to_vec();
+        if !reader.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        Ok(SshSig {
+            key,
+            certificate,
+            namespace,
+            reserved,
+            hash_algorithm,
+            signature,
+        })
+    }
+
+    /// data returns the binary encoding of the signature, which is what
+    /// gets armored
+    pub fn data(&self) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.append(&mut MAGIC_PREAMBLE.to_vec());
+        writer.write_int(SIG_VERSION);
+        match self.certificate {
+            Some(ref cert) => writer.write_bytes(cert.data()),
+            None => writer.write_bytes(self.key.data()),
+        }
+        writer.write_string(&self.namespace);
+        writer.write_bytes(self.reserved.clone());
+        writer.write_string(self.hash_algorithm.name());
+        writer.write_bytes(self.signature.clone());
+        writer.into_vec()
+    }
+
+    /// sign signs message with key for use in namespace, hashing it with
+    /// sha512 like ssh-keygen does
+    pub fn sign(key: &PrivateKey, namespace: &str, message: &[u8]) -> Result<Self> {
+        SshSig::sign_with_hash(key, namespace, HashAlgorithm::Sha512, message)
+    }
+
+    /// sign_with_hash signs message with key for use in namespace, hashing
+    /// it with the given hash algorithm, like `ssh-keygen -Y sign -O hashalg`
+    pub fn sign_with_hash(key: &PrivateKey, namespace: &str, hash_algorithm: HashAlgorithm, message: &[u8]) -> Result<Self> {
+        if namespace.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        let mut sig = SshSig {
+            key: PublicKey {
+                data: key.public_key().data.clone(),
+                comment: None,
+            },
+            certificate: None,
+            namespace: namespace.to_string(),
+            reserved: vec![],
+            hash_algorithm,
+            signature: vec![],
+        };
+        sig.signature = key.sign(&sig.signed_data(message))?;
+        Ok(sig)
+    }
+
+    /// verify checks that this is a valid signature of message for use in
+    /// namespace, made by the key returned by key(). it doesn't say anything
+    /// about whether that key should be trusted, which is up to the caller.
+    /// like ssh-keygen, rsa signatures have to use rsa-sha2-256 or
+    /// rsa-sha2-512. a signature for another namespace gives a
+    /// NamespaceMismatch error.
+    pub fn verify(&self, namespace: &str, message: &[u8]) -> Result<()> {
+        if self.namespace != namespace {
+            return Err(ErrorKind::NamespaceMismatch(self.namespace.clone()).into());
+        }
+        let signed = self.signed_data(message);
+        match self.key.data {
+            Data::Rsa { .. } => self.key.verify_allowing(&signed, &self.signature, RSA_ALGORITHMS)?,
+            _ => self.key.verify(&signed, &self.signature)?,
+        };
+        Ok(())
+    }
+
+    /// signed_data returns the data that actually gets signed for message
+    fn signed_data(&self, message: &[u8]) -> Vec<u8> {
+        let mut writer = Writer::new();
+        writer.append(&mut MAGIC_PREAMBLE.to_vec());
+        writer.write_string(&self.namespace);
+        writer.write_bytes(self.reserved.clone());
+        writer.write_string(self.hash_algorithm.name());
+        writer.write_bytes(self.hash_algorithm.hash(message));
+        writer.into_vec()
+    }
+
+    /// key returns the public key that made the signature. for a signature
+    /// made with a certificate, this is the certified key.
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// certificate returns the certificate the signature was made with, if
+    /// there is one. it isn't checked here, see
+    /// AllowedSigners::verify for checking it against cert-authority entries.
+    pub fn certificate(&self) -> Option<&Certificate> {
+        self.certificate.as_ref()
+    }
+
+    /// fingerprint returns the fingerprint of the key that made the
+    /// signature, which is what `ssh-keygen -Y verify` reports
+    pub fn fingerprint(&self) -> String {
+        self.key.fingerprint()
+    }
+
+    /// namespace returns the namespace the signature was made for, like
+    /// `file` or `git`
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+
+    /// signature returns the signature of the signed data, in the ssh
+    /// signature encoding
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the message signed by all of these
+    const MESSAGE: &[u8] = b"hello world\n";
+    // generated with
+    //   ssh-keygen -Y sign -f fixtures/ed25519 -n file msg
+    const TEST_SIG_ED25519: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgCEGvr74VBcHySSA4xt3EG7KsfO
+5fkeQSWzCs3oNCg38AAAAEZmlsZQAAAAAAAAAGc2hhNTEyAAAAUwAAAAtzc2gtZWQyNTUx
+OQAAAEA83Q8aQXjNa0/ZM2QKoln8H20XLgX81fni4PDb/CQLzFZ3sYPH+Gr3IPE/wTzgFw
+nxejns8MjcNBZcba5NlM4J
+-----END SSH SIGNATURE-----
+";
+    // generated with
+    //   ssh-keygen -Y sign -f fixtures/ed25519 -n file -O hashalg=sha256 msg
+    const TEST_SIG_SHA256: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgCEGvr74VBcHySSA4xt3EG7KsfO
+5fkeQSWzCs3oNCg38AAAAEZmlsZQAAAAAAAAAGc2hhMjU2AAAAUwAAAAtzc2gtZWQyNTUx
+OQAAAECrePF58exqeaSjIlzEHsHmK1f6FEjKotqbR+KQPpG89Xs/EpZ1wt13cfxdFjgvmg
+hhGh8PN/wuKl99jugHtfYC
+-----END SSH SIGNATURE-----
+";
+    // generated with
+    //   ssh-keygen -Y sign -f fixtures/rsa -n file msg
+    const TEST_SIG_RSA: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAARcAAAAHc3NoLXJzYQAAAAMBAAEAAAEBAJgfe89QlOHOuJWVUqYo6D
+vUQ5VibbvihFrkotYSgrQeaMxz2u7SPih+i6h5b8BWZFW6PF9ogZH/x6f+VQ+SK/kNJ4zp
+pecPPlwTE2m2sDL+ww8tCFUAtGupD52RL0K39gD4DUt7ks+Hgwm7v2EIMtPCpJ0qoO5Rxh
+DNHQzsA+yVoMJH3rNj5VQviUo5tRR3jCw/eil1NDobbSpcR6vXgQ8av03ndL+74kkYug+V
+vW48gf3qgpji39J4shFCyhx1WJFqIX8CruQ/NZ3/IItV1clJG3cJ+1uhXiQsEca15NLnnU
+nfYwmiqMSLhD8O5xZitB/HkfepdUH78bYe6so8gcsAAAAEZmlsZQAAAAAAAAAGc2hhNTEy
+AAABFAAAAAxyc2Etc2hhMi01MTIAAAEAQlKr2Gz/o5wC2U0FQXI7nT1yNcFnTzyBqCeOei
+Jb5l4OrBSjGd6Xi3buz8zx/lNHCIVa8hKFBvBS2sp4lh0rAxeBeonz6tGSlMQJT156RU+0
+JgJCy51fWMrWDQjjyOO9Uptja81dz7TAZN9ZUik28Cdhystfqos75HHNoUbxRELNXMNkEW
+SDjkdjC0MTfpey3nuhfHLvA6DPzlncs0UMUPiJqhW2l/eSipl+NX3XRELl5P03hAHgTLfg
+Y6MrE+1Oqvf/Gcmei0oQJlXjftTKWwYtQk349Bm/MtJqv9xMIrcXmgr1KmfHQx9fphjquc
+PAAZRgsRSzGc2en1jwbk9Pbw==
+-----END SSH SIGNATURE-----
+";
+    // generated with
+    //   ssh-keygen -Y sign -f fixtures/ecdsa -n git msg
+    const TEST_SIG_ECDSA: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAAGgAAAATZWNkc2Etc2hhMi1uaXN0cDI1NgAAAAhuaXN0cDI1NgAAAE
+EEiF8tCvDDgOWFjNtJZehfdy05DSNWiy98d9b5BZWi2Sxs/MzZiCQiKROmgdR9mtikkpbH
+tZizlqp4uR0IN2T6OQAAAANnaXQAAAAAAAAABnNoYTUxMgAAAGMAAAATZWNkc2Etc2hhMi
+1uaXN0cDI1NgAAAEgAAAAgMjWf0DvfjHKyiQXN3if5K0sjYFeiAe0YCmk5/Ch+9scAAAAg
+dlqEBjnoj2OAdbY2TyPkmgvvG3UiuJJ5TYFa3wGCxAk=
+-----END SSH SIGNATURE-----
+";
+    // a security key signature with the key material of fixtures/ecdsa, which
+    // `ssh-keygen -Y verify` accepts
+    const TEST_SIG_SK_ECDSA: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAAH8AAAAic2stZWNkc2Etc2hhMi1uaXN0cDI1NkBvcGVuc3NoLmNvbQ
+AAAAhuaXN0cDI1NgAAAEEEiF8tCvDDgOWFjNtJZehfdy05DSNWiy98d9b5BZWi2Sxs/MzZ
+iCQiKROmgdR9mtikkpbHtZizlqp4uR0IN2T6OQAAAARzc2g6AAAABGZpbGUAAAAAAAAABn
+NoYTUxMgAAAHcAAAAic2stZWNkc2Etc2hhMi1uaXN0cDI1NkBvcGVuc3NoLmNvbQAAAEgA
+AAAgOXI5mwYwcyBumr22Ez/FlIXtw53/tZNIFznfp4jqPB0AAAAgLlmgW+eoFqma4lTr7F
+u3yilgWKMU8b1+Ya6EP9fDJDQFAAAAKg==
+-----END SSH SIGNATURE-----
+";
+
+    // fixtures/ed25519.pub certified for demos@example.com by a throwaway CA,
+    // generated with
+    //   ssh-keygen -s ca -I demos -n demos@example.com -V 20200101:20300101 ed25519.pub
+    //   ssh-keygen -Y sign -f ed25519-cert.pub -n file msg
+    const TEST_SIG_CERT: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAAcYAAAAgc3NoLWVkMjU1MTktY2VydC12MDFAb3BlbnNzaC5jb20AAA
+AgJOEZq2pqDO6BHyDHMVIInu7XULpF8LLdbTC/2Rk9BZYAAAAgCEGvr74VBcHySSA4xt3E
+G7KsfO5fkeQSWzCs3oNCg38AAAAAAAAAAAAAAAEAAAAFZGVtb3MAAAAVAAAAEWRlbW9zQG
+V4YW1wbGUuY29tAAAAAF4L4QAAAAAAcNvYgAAAAAAAAACCAAAAFXBlcm1pdC1YMTEtZm9y
+d2FyZGluZwAAAAAAAAAXcGVybWl0LWFnZW50LWZvcndhcmRpbmcAAAAAAAAAFnBlcm1pdC
+1wb3J0LWZvcndhcmRpbmcAAAAAAAAACnBlcm1pdC1wdHkAAAAAAAAADnBlcm1pdC11c2Vy
+LXJjAAAAAAAAAAAAAAAzAAAAC3NzaC1lZDI1NTE5AAAAIPX7f3nb/jAR/0/j4rnqzHyZWg
+1YPDd1SBIUPA4rmETCAAAAUwAAAAtzc2gtZWQyNTUxOQAAAECBXz1ZVyNEHEBp01WA4yNj
+F2wOetLzqd6MQOxuyQacMZJmsISRWfZDlpYf/s2/VDllb6vJF9YcAa+UWnBW/awKAAAABG
+ZpbGUAAAAAAAAABnNoYTUxMgAAAFMAAAALc3NoLWVkMjU1MTkAAABAPN0PGkF4zWtP2TNk
+CqJZ/B9tFy4F/NX54uDw2/wkC8xWd7GDx/hq9yDxP8E84BcJ8Xo57PDI3DQWXG2uTZTOCQ
+==
+-----END SSH SIGNATURE-----
+";
+
+    #[test]
+    fn parse_to_string() {
+        for armored in &[TEST_SIG_ED25519, TEST_SIG_SHA256, TEST_SIG_RSA, TEST_SIG_ECDSA, TEST_SIG_SK_ECDSA, TEST_SIG_CERT] {
+            let sig = SshSig::parse(armored).unwrap();
+            assert_eq!(*armored, sig.to_string());
+        }
+    }
+
+    #[test]
+    fn verify() {
+        let sig = SshSig::parse(TEST_SIG_ED25519).unwrap();
+        assert_eq!("file", sig.namespace());
+        assert_eq!(HashAlgorithm::Sha512, sig.hash_algorithm());
+        assert_eq!(PublicKey::parse(include_str!("../fixtures/ed25519.pub")).unwrap().fingerprint(), sig.fingerprint());
+        sig.verify("file", MESSAGE).unwrap();
+        assert!(sig.verify("file", b"hello world").is_err());
+        match sig.verify("git", MESSAGE) {
+            Err(Error(ErrorKind::NamespaceMismatch(ref namespace), _)) if namespace == "file" => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let sig = SshSig::parse(TEST_SIG_SHA256).unwrap();
+        assert_eq!(HashAlgorithm::Sha256, sig.hash_algorithm());
+        sig.verify("file", MESSAGE).unwrap();
+        SshSig::parse(TEST_SIG_RSA).unwrap().verify("file", MESSAGE).unwrap();
+        SshSig::parse(TEST_SIG_ECDSA).unwrap().verify("git", MESSAGE).unwrap();
+        SshSig::parse(TEST_SIG_SK_ECDSA).unwrap().verify("file", MESSAGE).unwrap();
+        assert!(sig.certificate().is_none());
+    }
+
+    #[test]
+    fn verify_certificate() {
+        let sig = SshSig::parse(TEST_SIG_CERT).unwrap();
+        let cert = sig.certificate().unwrap();
+        assert_eq!(&["demos@example.com".to_string()], cert.valid_principals());
+        assert_eq!(cert.key().data(), sig.key().data());
+        assert_eq!(PublicKey::parse(include_str!("../fixtures/ed25519.pub")).unwrap().fingerprint(), sig.fingerprint());
+        sig.verify("file", MESSAGE).unwrap();
+        assert!(sig.verify("file", b"hello world").is_err());
+    }
+
+    #[test]
+    fn sign() {
+        // ed25519 and rsa signatures are deterministic, so these come out the
+        // same as ssh-keygen's
+        let key = PrivateKey::parse(include_str!("../fixtures/ed25519")).unwrap();
+        assert_eq!(TEST_SIG_ED25519, SshSig::sign(&key, "file", MESSAGE).unwrap().to_string());
+        assert_eq!(TEST_SIG_SHA256, SshSig::sign_with_hash(&key, "file", HashAlgorithm::Sha256, MESSAGE).unwrap().to_string());
+        let key = PrivateKey::parse(include_str!("../fixtures/rsa")).unwrap();
+        assert_eq!(TEST_SIG_RSA, SshSig::sign(&key, "file", MESSAGE).unwrap().to_string());
+
+        for key in &[include_str!("../fixtures/dsa"), include_str!("../fixtures/ecdsa")] {
+            let key = PrivateKey::parse(key).unwrap();
+            let sig = SshSig::parse(&SshSig::sign(&key, "git", MESSAGE).unwrap().to_string()).unwrap();
+            sig.verify("git", MESSAGE).unwrap();
+        }
+        assert!(SshSig::sign(&key, "", MESSAGE).is_err());
+    }
+
+    #[test]
+    fn sha1_rsa() {
+        // ssh-keygen doesn't accept ssh-rsa signatures
+        let key = PrivateKey::parse(include_str!("../fixtures/rsa")).unwrap();
+        let mut sig = SshSig::sign(&key, "file", MESSAGE).unwrap();
+        sig.signature = key.sign_with_algorithm(&sig.signed_data(MESSAGE), SignatureAlgorithm::SshRsa).unwrap();
+        match sig.verify("file", MESSAGE) {
+            Err(Error(ErrorKind::DisallowedSignatureAlgorithm(_), _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert!(SshSig::parse(&TEST_SIG_ED25519.replace("SSH SIGNATURE", "SSH SIG")).is_err());
+        let data = SshSig::parse(TEST_SIG_ED25519).unwrap().data();
+        assert!(SshSig::from_blob(&data[..data.len() - 1]).is_err());
+        assert!(SshSig::from_blob(&data[1..]).is_err());
+        // unknown hash algorithms
+        let data = SshSig::parse(TEST_SIG_ED25519).unwrap().data();
+        let pos = data.windows(6).position(|w| w == b"sha512").unwrap();
+        let mut bad = data.clone();
+        bad[pos..pos + 6].copy_from_slice(b"sha384");
+        match SshSig::from_blob(&bad) {
+            Err(Error(ErrorKind::UnsupportedHash(_), _)) => (),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -10,20 +10,30 @@ pub struct Writer {
 
 impl Writer {
     pub fn new() -> Writer {
-        Writer {data: vec![]}
+        Writer { data: vec![] }
     }
 
-    pub fn to_vec(self) -> Vec<u8> {
+    pub fn into_vec(self) -> Vec<u8> {
         self.data
     }
 
     pub fn write_int(&mut self, val: u32) {
-        match self.data.write_u32::<BigEndian>(val) {
-            Err(..) => unreachable!(),
-            _ => {},
+        if self.data.write_u32::<BigEndian>(val).is_err() {
+            unreachable!()
         }
     }
 
+    pub fn write_u64(&mut self, val: u64) {
+        if self.data.write_u64::<BigEndian>(val).is_err() {
+            unreachable!()
+        }
+    }
+
+    /// append writes raw bytes, without a length
+    pub fn append(&mut self, buf: &mut Vec<u8>) {
+        self.data.append(buf);
+    }
+
     pub fn write_bytes(&mut self, mut buf: Vec<u8>) {
         // The first four bytes represent the length of the encoded data.
         self.write_int(buf.len() as u32);
@@ -39,7 +49,7 @@ impl Writer {
         // If the number is positive then we are required to guarentee that the
         // most significant bit is set to zero if the first bit in the first
         // byte is going to be one.
-        if num.get(0).unwrap() & 0x80 != 0 {
+        if num.first().unwrap() & 0x80 != 0 {
             num.insert(0, 0);
         }
         // other than that it's just normal ssh encoding
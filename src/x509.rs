@@ -0,0 +1,207 @@
+//! x509
+//!
+//! this module provides a minimal reader for X.509 certificates, as described
+//! in https://tools.ietf.org/html/rfc5280#section-4.1, with just enough to
+//! check that one certificate was signed by another, and whether a
+//! certificate is a CA. nothing else about them, like the validity period or
+//! the other extensions, is looked at.
+
+use errors::*;
+
+use der;
+use pkcs::OID_ED25519;
+use writer::Writer;
+use {Data, PublicKey};
+
+// the signature algorithms from https://tools.ietf.org/html/rfc4055#section-5
+// and https://tools.ietf.org/html/rfc5758#section-3.2 that have a matching
+// ssh signature algorithm. sha256WithRSAEncryption 1.2.840.113549.1.1.11,
+// sha512WithRSAEncryption 1.2.840.113549.1.1.13, and ecdsa-with-SHA256,
+// SHA384 and SHA512 1.2.840.10045.4.3.2 to 4. ed25519 signatures use the
+// key's algorithm identifier.
+const OID_SHA256_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+const OID_SHA512_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d];
+const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const OID_ECDSA_WITH_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+const OID_ECDSA_WITH_SHA512: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x04];
+// the basicConstraints extension, 2.5.29.19, from
+// https://tools.ietf.org/html/rfc5280#section-4.2.1.9
+const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+// the explicit tag of the extensions in the tbsCertificate
+const EXTENSIONS_TAG: u8 = 0xa3;
+
+/// Certificate is a parsed X.509 certificate
+#[derive(Clone, Debug)]
+pub struct Certificate {
+    tbs: Vec<u8>,
+    issuer: Vec<u8>,
+    subject: Vec<u8>,
+    key: PublicKey,
+    ca: bool,
+    signature_algorithm: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl Certificate {
+    /// from_der reads a DER encoded certificate
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let mut reader = der::Reader::new(der);
+        let mut cert = reader.read_sequence()?;
+        if !reader.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+        let tbs = cert.read_raw()?;
+        let mut algorithm = cert.read_sequence()?;
+        let signature_algorithm = algorithm.read_oid()?;
+        let signature = cert.read_bit_string()?;
+        if !cert.is_empty() {
+            return Err(ErrorKind::InvalidFormat.into());
+        }
+
+        let mut fields = der::Reader::new(tbs).read_sequence()?;
+        // the version is an optional explicitly tagged field
+        if fields.peek_tag() == Some(0xa0) {
+            fields.read_tlv()?;
+        }
+        // serial number, then the signature algorithm again
+        fields.read(der::INTEGER)?;
+        fields.read_sequence()?;
+        let issuer = fields.read_raw()?;
+        // validity
+        fields.read_sequence()?;
+        let subject = fields.read_raw()?;
+        let key = PublicKey::read_spki(&mut fields)?;
+        // the unique ids that can come before the extensions are skipped
+        let mut ca = false;
+        while !fields.is_empty() {
+            let (tag, contents) = fields.read_tlv()?;
+            if tag == EXTENSIONS_TAG {
+                ca = is_ca(contents)?;
+            }
+        }
+
+        Ok(Certificate {
+            tbs: tbs.into(),
+            issuer: issuer.into(),
+            subject: subject.into(),
+            key,
+            ca,
+            signature_algorithm: signature_algorithm.into(),
+            signature: signature.into(),
+        })
+    }
+
+    /// key returns the subject's public key
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    /// is_ca returns whether the certificate has the basicConstraints
+    /// extension with cA set, which makes it a CA certificate that can issue
+    /// other certificates
+    pub fn is_ca(&self) -> bool {
+        self.ca
+    }
+
+    /// is_signed_by returns whether issuer is the issuer of this certificate
+    /// and made its signature. names are compared by their encoding.
+    pub fn is_signed_by(&self, issuer: &Certificate) -> bool {
+        let algorithm = match self.signature_algorithm.as_slice() {
+            OID_SHA256_WITH_RSA => "rsa-sha2-256",
+            OID_SHA512_WITH_RSA => "rsa-sha2-512",
+            OID_ECDSA_WITH_SHA256 => "ecdsa-sha2-nistp256",
+            OID_ECDSA_WITH_SHA384 => "ecdsa-sha2-nistp384",
+            OID_ECDSA_WITH_SHA512 => "ecdsa-sha2-nistp521",
+            OID_ED25519 => "ssh-ed25519",
+            _ => return false,
+        };
+        self.issuer == issuer.subject && verify(&issuer.key, algorithm, &self.tbs, &self.signature).is_ok()
+    }
+}
+
+/// is_ca reads the extensions of a certificate and returns whether they have
+/// basicConstraints with cA set
+fn is_ca(extensions: &[u8]) -> Result<bool> {
+    let mut extensions = der::Reader::new(extensions).read_sequence()?;
+    while !extensions.is_empty() {
+        let mut extension = extensions.read_sequence()?;
+        if extension.read_oid()? != OID_BASIC_CONSTRAINTS {
+            continue;
+        }
+        // critical, which defaults to false
+        if extension.peek_tag() == Some(der::BOOLEAN) {
+            extension.read_boolean()?;
+        }
+        let mut constraints = der::Reader::new(extension.read_octet_string()?).read_sequence()?;
+        // cA defaults to false, and is followed by pathLenConstraint
+        return Ok(constraints.peek_tag() == Some(der::BOOLEAN) && constraints.read_boolean()?);
+    }
+    Ok(false)
+}
+
+/// verify checks a signature in the encoding used outside of ssh, which is
+/// the same as the ssh one for rsa and ed25519 keys, and a DER encoded
+/// Ecdsa-Sig-Value from https://tools.ietf.org/html/rfc3279#section-2.2.3 for
+/// ecdsa keys. algorithm is the ssh signature algorithm with the same hash.
+pub fn verify(key: &PublicKey, algorithm: &str, message: &[u8], signature: &[u8]) -> Result<()> {
+    let blob = match key.data {
+        Data::Ecdsa { .. } => {
+            let mut reader = der::Reader::new(signature);
+            let mut sig = reader.read_sequence()?;
+            let r = sig.read_integer()?;
+            let s = sig.read_integer()?;
+            if !sig.is_empty() || !reader.is_empty() {
+                return Err(ErrorKind::InvalidFormat.into());
+            }
+            let mut writer = Writer::new();
+            writer.write_mpint(r.into());
+            writer.write_mpint(s.into());
+            writer.into_vec()
+        },
+        _ => signature.into(),
+    };
+    let mut writer = Writer::new();
+    writer.write_string(algorithm);
+    writer.write_bytes(blob);
+    key.verify(message, &writer.into_vec())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pem;
+
+    fn certificate(text: &str) -> Certificate {
+        Certificate::from_der(&pem::parse(text).unwrap().data).unwrap()
+    }
+
+    #[test]
+    fn signed_by() {
+        // the rsa root signs with sha256WithRSAEncryption, and the ecdsa one
+        // with ecdsa-with-SHA256
+        let root = certificate(include_str!("../fixtures/attestation-root.pem"));
+        let other = certificate(include_str!("../fixtures/attestation-other-root.pem"));
+        assert_eq!("ssh-rsa", root.key().keytype());
+        assert!(root.is_signed_by(&root));
+        assert!(other.is_signed_by(&other));
+        assert!(!root.is_signed_by(&other));
+        assert!(!other.is_signed_by(&root));
+    }
+
+    #[test]
+    fn ca() {
+        assert!(certificate(include_str!("../fixtures/attestation-root.pem")).is_ca());
+        assert!(certificate(include_str!("../fixtures/attestation-issuer.pem")).is_ca());
+        assert!(!certificate(include_str!("../fixtures/attestation-issuer-not-ca.pem")).is_ca());
+    }
+
+    #[test]
+    fn invalid() {
+        let der = pem::parse(include_str!("../fixtures/attestation-root.pem")).unwrap().data;
+        assert!(Certificate::from_der(&der[..der.len() - 1]).is_err());
+        let mut trailing = der.clone();
+        trailing.push(0);
+        assert!(Certificate::from_der(&trailing).is_err());
+    }
+}